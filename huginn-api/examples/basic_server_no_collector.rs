@@ -1,7 +1,6 @@
 use huginn_api::server::{run_server_with_config, ApiServerConfig};
 use huginn_collector::CollectorConfig;
 use std::net::SocketAddr;
-use tracing_subscriber;
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
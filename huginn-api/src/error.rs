@@ -65,6 +65,12 @@ impl ApiError {
             ApiError::BadRequest(_) => StatusCode::BAD_REQUEST,
             ApiError::Configuration(_) => StatusCode::BAD_REQUEST,
             ApiError::Serialization(_) => StatusCode::BAD_REQUEST,
+            ApiError::Collection(huginn_collector::CollectorError::SignatureDatabase(_)) => {
+                StatusCode::UNPROCESSABLE_ENTITY
+            }
+            ApiError::Collection(huginn_collector::CollectorError::Unsupported(_)) => {
+                StatusCode::CONFLICT
+            }
             _ => StatusCode::INTERNAL_SERVER_ERROR,
         }
     }
@@ -5,9 +5,14 @@ use axum::{
     http::StatusCode,
     response::Json,
 };
-use huginn_core::TrafficProfile;
+use huginn_collector::{BridgeStats, CollectorStatus, ProfileFilter, SignatureReload};
+use huginn_core::{
+    ConnectionObservation, ConsistencyFinding, HopHistory, NatAnalysis, RiskScore, TrafficProfile,
+    UserAgent,
+};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::net::IpAddr;
 
 /// Structure that matches the user's TcpInfo example - each field is separate, no merging
 #[derive(Serialize, Clone)]
@@ -20,6 +25,11 @@ pub struct TcpInfo {
     pub http_response: Option<HttpResponse>,
     pub source_ip: Option<String>,
     pub tls_client: Option<TlsClient>,
+    pub consistency: Vec<ConsistencyFinding>,
+    pub nat: NatAnalysis,
+    pub risk: RiskScore,
+    pub hops: HopHistory,
+    pub last_reboot_estimate: Option<chrono::DateTime<chrono::Utc>>,
 }
 
 #[derive(Serialize, Clone)]
@@ -76,6 +86,8 @@ pub struct TlsClient {
     pub ja4_raw: String,
     pub ja4_original: String,
     pub ja4_original_raw: String,
+    pub ja3: String,
+    pub ja3_hash: String,
     pub observed: TlsClientObserved,
 }
 
@@ -102,6 +114,15 @@ pub struct Uptime {
     pub freq: String,
 }
 
+/// Comma-separated p0f notation of a list of options or quirks
+fn join_display<T: std::fmt::Display>(items: &[T]) -> String {
+    items
+        .iter()
+        .map(|item| item.to_string())
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
 // Helper function to convert TrafficProfile to TcpInfo structure (EXACTLY matching user's example)
 fn convert_profile_to_tcp_info(profile: &TrafficProfile) -> TcpInfo {
     let mut tcp_info = TcpInfo {
@@ -113,6 +134,11 @@ fn convert_profile_to_tcp_info(profile: &TrafficProfile) -> TcpInfo {
         http_response: None,
         source_ip: Some(profile.ip.to_string()), // Use profile IP directly
         tls_client: None,
+        consistency: profile.consistency.clone(),
+        nat: profile.nat.clone(),
+        risk: profile.risk.clone(),
+        hops: profile.hops.clone(),
+        last_reboot_estimate: profile.last_reboot_estimate,
     };
 
     // Convert SYN packet data (CLIENT)
@@ -136,13 +162,13 @@ fn convert_profile_to_tcp_info(profile: &TrafficProfile) -> TcpInfo {
             signature: syn_data.signature.clone(),
             observed: TcpObserved {
                 version: syn_data.details.version.clone(),
-                ittl: syn_data.details.initial_ttl.clone(),
+                ittl: syn_data.details.initial_ttl.to_string(),
                 olen: syn_data.details.options_length,
                 mss: syn_data.details.mss,
-                wsize: syn_data.details.window_size.clone(),
+                wsize: syn_data.details.window_size.to_string(),
                 wscale: syn_data.details.window_scale,
-                olayout: syn_data.details.options_layout.clone(),
-                quirks: syn_data.details.quirks.clone(),
+                olayout: join_display(&syn_data.details.options),
+                quirks: join_display(&syn_data.details.quirks),
                 pclass: syn_data.details.payload_class.clone(),
             },
         });
@@ -169,13 +195,13 @@ fn convert_profile_to_tcp_info(profile: &TrafficProfile) -> TcpInfo {
             signature: syn_ack_data.signature.clone(),
             observed: TcpObserved {
                 version: syn_ack_data.details.version.clone(),
-                ittl: syn_ack_data.details.initial_ttl.clone(),
+                ittl: syn_ack_data.details.initial_ttl.to_string(),
                 olen: syn_ack_data.details.options_length,
                 mss: syn_ack_data.details.mss,
-                wsize: syn_ack_data.details.window_size.clone(),
+                wsize: syn_ack_data.details.window_size.to_string(),
                 wscale: syn_ack_data.details.window_scale,
-                olayout: syn_ack_data.details.options_layout.clone(),
-                quirks: syn_ack_data.details.quirks.clone(),
+                olayout: join_display(&syn_ack_data.details.options),
+                quirks: join_display(&syn_ack_data.details.quirks),
                 pclass: syn_ack_data.details.payload_class.clone(),
             },
         });
@@ -193,7 +219,11 @@ fn convert_profile_to_tcp_info(profile: &TrafficProfile) -> TcpInfo {
         tcp_info.http_request = Some(HttpRequest {
             lang,
             diagnosis: "none".to_string(),
-            browser: "Chrome Android".to_string(), // Extract from User-Agent if available
+            browser: http_req_data
+                .user_agent
+                .as_deref()
+                .map(|user_agent| UserAgent::parse(user_agent).summary())
+                .unwrap_or_else(|| "Unknown".to_string()),
             quality: format!("{:.2}", http_req_data.quality),
             signature: http_req_data.signature.clone(),
             observed: HttpObserved {
@@ -240,6 +270,8 @@ fn convert_profile_to_tcp_info(profile: &TrafficProfile) -> TcpInfo {
             ja4_raw: tls_data.ja4_raw.clone(),
             ja4_original: tls_data.ja4.clone(),
             ja4_original_raw: tls_data.ja4_raw.clone(),
+            ja3: tls_data.ja3.clone(),
+            ja3_hash: tls_data.ja3_hash.clone(),
             observed: TlsClientObserved {
                 version: tls_data.details.version.clone(),
                 sni: tls_data.details.sni.clone(),
@@ -255,18 +287,23 @@ fn convert_profile_to_tcp_info(profile: &TrafficProfile) -> TcpInfo {
     // Convert MTU data
     if let Some(mtu_data) = &profile.raw_data.mtu {
         tcp_info.mtu = Some(Mtu {
-            link: "ethernet".to_string(),
+            link: profile
+                .link_type
+                .clone()
+                .or_else(|| mtu_data.link.clone())
+                .unwrap_or_else(|| "unknown".to_string()),
             mtu: mtu_data.mtu_value,
         });
     }
 
     // Convert uptime data
     if let Some(uptime_data) = &profile.raw_data.uptime {
-        let hours = uptime_data.uptime_seconds / 3600;
-        let minutes = (uptime_data.uptime_seconds % 3600) / 60;
         tcp_info.uptime = Some(Uptime {
-            time: format!("0 days, {} hrs, {} min (modulo 0 days)", hours, minutes),
-            freq: "0.00 Hz".to_string(),
+            time: format!(
+                "{} days, {} hrs, {} min (modulo {} days)",
+                uptime_data.days, uptime_data.hours, uptime_data.minutes, uptime_data.modulo_days
+            ),
+            freq: format!("{:.2} Hz", uptime_data.frequency_hz),
         });
     }
 
@@ -284,13 +321,13 @@ pub struct HealthResponse {
 /// Response for the profiles list endpoint  
 #[derive(Serialize)]
 pub struct ProfilesResponse {
-    pub profiles: HashMap<String, TcpInfo>,
+    pub profiles: OrderedProfiles<TcpInfo>,
     pub count: usize,
     pub timestamp: chrono::DateTime<chrono::Utc>,
 }
 
 /// Query parameters for filtering profiles
-#[derive(Deserialize)]
+#[derive(Deserialize, Default)]
 pub struct ProfileQuery {
     /// Filter by minimum completeness (0.0-1.0)
     pub min_completeness: Option<f64>,
@@ -300,89 +337,184 @@ pub struct ProfileQuery {
     pub has_http: Option<bool>,
     /// Filter by having TLS data
     pub has_tls: Option<bool>,
+    /// Filter by JA3 hash or full JA3 string
+    pub ja3: Option<String>,
+    /// Filter by minimum bot/automation risk score (0-100)
+    pub min_risk: Option<u8>,
+    /// Filter by maximum bot/automation risk score (0-100)
+    pub max_risk: Option<u8>,
+    /// Result order (unsorted when absent)
+    pub sort: Option<ProfileSort>,
     /// Limit number of results
     pub limit: Option<usize>,
 }
 
-/// Health check endpoint
-/// GET /health
-pub async fn health() -> Json<HealthResponse> {
-    Json(HealthResponse {
-        status: "healthy".to_string(),
-        version: crate::VERSION.to_string(),
-        timestamp: chrono::Utc::now(),
-    })
+/// Sort orders for profile lists
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProfileSort {
+    /// Highest risk score first
+    Risk,
+    /// Lowest risk score first
+    RiskAsc,
 }
 
-/// Get all traffic profiles
-/// GET /api/profiles
-pub async fn get_profiles(
-    State(state): State<AppState>,
-    Query(query): Query<ProfileQuery>,
-) -> Result<Json<ProfilesResponse>> {
-    let all_profiles = state.get_profiles();
+/// Profiles keyed by IP, serialized as a JSON object in list order
+#[derive(Debug, Clone)]
+pub struct OrderedProfiles<T>(pub Vec<(String, T)>);
 
-    // Apply filters and convert to TcpInfo
-    let mut filtered_profiles = HashMap::new();
+impl<T> OrderedProfiles<T> {
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
 
-    for (_key, profile) in all_profiles.iter() {
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl<T: Serialize> Serialize for OrderedProfiles<T> {
+    fn serialize<S: serde::Serializer>(
+        &self,
+        serializer: S,
+    ) -> std::result::Result<S::Ok, S::Error> {
+        use serde::ser::SerializeMap;
+
+        let mut map = serializer.serialize_map(Some(self.0.len()))?;
+        for (key, value) in &self.0 {
+            map.serialize_entry(key, value)?;
+        }
+        map.end()
+    }
+}
+
+impl ProfileQuery {
+    /// Profiles passing the filters, in the requested order and cut to the limit
+    pub fn select<'a>(
+        &self,
+        profiles: &'a HashMap<String, TrafficProfile>,
+    ) -> Vec<(&'a String, &'a TrafficProfile)> {
+        let mut selected: Vec<_> = profiles
+            .iter()
+            .filter(|(_, profile)| self.matches(profile))
+            .collect();
+
+        match self.sort {
+            Some(ProfileSort::Risk) => {
+                selected.sort_by_key(|(_, profile)| std::cmp::Reverse(profile.risk.score))
+            }
+            Some(ProfileSort::RiskAsc) => selected.sort_by_key(|(_, profile)| profile.risk.score),
+            None => {}
+        }
+        if let Some(limit) = self.limit {
+            selected.truncate(limit);
+        }
+
+        selected
+    }
+
+    /// The part of the query the profile store can answer itself
+    pub fn store_filter(&self) -> ProfileFilter {
+        ProfileFilter {
+            ja3: self.ja3.clone(),
+            ..Default::default()
+        }
+    }
+
+    /// Whether a profile passes the completeness, has_*, ja3 and risk filters (limit is not applied)
+    pub fn matches(&self, profile: &TrafficProfile) -> bool {
         // Apply completeness filter
-        if let Some(min_completeness) = query.min_completeness {
+        if let Some(min_completeness) = self.min_completeness {
             if profile.metadata.completeness < min_completeness {
-                continue;
+                return false;
             }
         }
 
         // Apply TCP filter (check raw data for more accurate filtering)
-        if let Some(has_tcp) = query.has_tcp {
+        if let Some(has_tcp) = self.has_tcp {
             let has_tcp_data = profile.raw_data.syn.is_some()
                 || profile.raw_data.syn_ack.is_some()
                 || profile.tcp.is_some();
-            if has_tcp && !has_tcp_data {
-                continue;
-            }
-            if !has_tcp && has_tcp_data {
-                continue;
+            if has_tcp != has_tcp_data {
+                return false;
             }
         }
 
         // Apply HTTP filter (check raw data for more accurate filtering)
-        if let Some(has_http) = query.has_http {
+        if let Some(has_http) = self.has_http {
             let has_http_data = profile.raw_data.http_request.is_some()
                 || profile.raw_data.http_response.is_some()
                 || profile.http.is_some();
-            if has_http && !has_http_data {
-                continue;
-            }
-            if !has_http && has_http_data {
-                continue;
+            if has_http != has_http_data {
+                return false;
             }
         }
 
         // Apply TLS filter (check raw data for more accurate filtering)
-        if let Some(has_tls) = query.has_tls {
+        if let Some(has_tls) = self.has_tls {
             let has_tls_data = profile.raw_data.tls_client.is_some() || profile.tls.is_some();
-            if has_tls && !has_tls_data {
-                continue;
-            }
-            if !has_tls && has_tls_data {
-                continue;
+            if has_tls != has_tls_data {
+                return false;
             }
         }
 
-        // Convert TrafficProfile to TcpInfo
-        let tcp_info = convert_profile_to_tcp_info(profile);
-        // Use IP only as key (matching user's example structure)
-        let ip_key = profile.ip.to_string();
-        filtered_profiles.insert(ip_key, tcp_info);
-
-        // Apply limit
-        if let Some(limit) = query.limit {
-            if filtered_profiles.len() >= limit {
-                break;
+        // Apply JA3 filter (hash or full string)
+        if let Some(ja3) = &self.ja3 {
+            let matches_ja3 = |hash: &str, string: &str| {
+                !hash.is_empty() && (hash.eq_ignore_ascii_case(ja3) || string == ja3)
+            };
+            let tls_match = profile
+                .tls
+                .as_ref()
+                .is_some_and(|tls| matches_ja3(&tls.ja3_hash, &tls.ja3));
+            let raw_match = profile
+                .raw_data
+                .tls_client
+                .as_ref()
+                .is_some_and(|tls| matches_ja3(&tls.ja3_hash, &tls.ja3));
+            if !tls_match && !raw_match {
+                return false;
             }
         }
+
+        // Apply risk score range
+        if self.min_risk.is_some_and(|min| profile.risk.score < min)
+            || self.max_risk.is_some_and(|max| profile.risk.score > max)
+        {
+            return false;
+        }
+
+        true
     }
+}
+
+/// Health check endpoint
+/// GET /health
+pub async fn health() -> Json<HealthResponse> {
+    Json(HealthResponse {
+        status: "healthy".to_string(),
+        version: crate::VERSION.to_string(),
+        timestamp: chrono::Utc::now(),
+    })
+}
+
+/// Get all traffic profiles
+/// GET /api/profiles
+pub async fn get_profiles(
+    State(state): State<AppState>,
+    Query(query): Query<ProfileQuery>,
+) -> Result<Json<ProfilesResponse>> {
+    let all_profiles = state.query_profiles(query.store_filter()).await?;
+
+    // Apply filters, sort and limit, then convert to TcpInfo
+    // (keyed by IP only, matching user's example structure)
+    let filtered_profiles = OrderedProfiles(
+        query
+            .select(&all_profiles)
+            .into_iter()
+            .map(|(_key, profile)| (profile.ip.to_string(), convert_profile_to_tcp_info(profile)))
+            .collect(),
+    );
 
     Ok(Json(ProfilesResponse {
         count: filtered_profiles.len(),
@@ -397,7 +529,7 @@ pub async fn get_profile(
     State(state): State<AppState>,
     Path(key): Path<String>,
 ) -> Result<Json<TcpInfo>> {
-    match state.get_profile(&key) {
+    match state.query_profile(&key).await? {
         Some(profile) => {
             let tcp_info = convert_profile_to_tcp_info(&profile);
             Ok(Json(tcp_info))
@@ -412,23 +544,132 @@ pub async fn delete_profile(
     State(state): State<AppState>,
     Path(key): Path<String>,
 ) -> Result<StatusCode> {
-    match state.remove_profile(&key) {
-        Some(_) => Ok(StatusCode::NO_CONTENT),
-        None => Err(ApiError::not_found(format!("Profile not found: {}", key))),
+    if state.remove_stored_profile(&key).await? {
+        Ok(StatusCode::NO_CONTENT)
+    } else {
+        Err(ApiError::not_found(format!("Profile not found: {}", key)))
     }
 }
 
 /// Clear all traffic profiles
 /// DELETE /api/profiles
-pub async fn clear_profiles(State(state): State<AppState>) -> StatusCode {
-    state.clear_profiles();
-    StatusCode::NO_CONTENT
+pub async fn clear_profiles(State(state): State<AppState>) -> Result<StatusCode> {
+    state.clear_stored_profiles().await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Response for the connections list endpoint
+#[derive(Serialize)]
+pub struct ConnectionsResponse {
+    pub connections: HashMap<String, ConnectionObservation>,
+    pub count: usize,
+    pub timestamp: chrono::DateTime<chrono::Utc>,
+}
+
+/// Query parameters for filtering connections
+#[derive(Deserialize)]
+pub struct ConnectionQuery {
+    /// Only return connections from this client IP
+    pub ip: Option<String>,
+    /// Limit number of results
+    pub limit: Option<usize>,
+}
+
+/// Get all per-connection observations
+/// GET /api/connections
+pub async fn get_connections(
+    State(state): State<AppState>,
+    Query(query): Query<ConnectionQuery>,
+) -> Result<Json<ConnectionsResponse>> {
+    let client_ip = match &query.ip {
+        Some(ip) => Some(
+            ip.parse::<IpAddr>()
+                .map_err(|e| ApiError::bad_request(format!("Invalid ip '{}': {}", ip, e)))?,
+        ),
+        None => None,
+    };
+
+    let all_connections = state.get_connections();
+    let mut connections = HashMap::new();
+
+    for (key, connection) in all_connections.iter() {
+        if let Some(ip) = client_ip {
+            if connection.key.client_ip() != ip {
+                continue;
+            }
+        }
+
+        connections.insert(key.clone(), connection.clone());
+
+        if let Some(limit) = query.limit {
+            if connections.len() >= limit {
+                break;
+            }
+        }
+    }
+
+    Ok(Json(ConnectionsResponse {
+        count: connections.len(),
+        connections,
+        timestamp: chrono::Utc::now(),
+    }))
+}
+
+/// Get a specific connection observation by `client-server` key
+/// GET /api/connections/{key}
+pub async fn get_connection(
+    State(state): State<AppState>,
+    Path(key): Path<String>,
+) -> Result<Json<ConnectionObservation>> {
+    match state.get_connection(&key) {
+        Some(connection) => Ok(Json(connection)),
+        None => Err(ApiError::not_found(format!(
+            "Connection not found: {}",
+            key
+        ))),
+    }
+}
+
+/// Reload the collector's signature database without restarting the server
+/// POST /api/signatures/reload
+pub async fn reload_signatures(State(state): State<AppState>) -> Result<Json<SignatureReload>> {
+    let collector = state
+        .collector_handle
+        .as_ref()
+        .ok_or_else(|| ApiError::bad_request("Network collector is not running"))?;
+
+    Ok(Json(collector.reload_signatures().await?))
+}
+
+/// Response for the collector status
+#[derive(Serialize)]
+pub struct CollectorStatusResponse {
+    #[serde(flatten)]
+    pub status: CollectorStatus,
+    /// Results received, forwarded and dropped between huginn-net and the processor
+    pub bridge: BridgeStats,
+}
+
+/// Report whether the packet capture is running, restarting or has failed
+/// GET /api/collector/status
+pub async fn get_collector_status(
+    State(state): State<AppState>,
+) -> Result<Json<CollectorStatusResponse>> {
+    let collector = state
+        .collector_handle
+        .as_ref()
+        .ok_or_else(|| ApiError::not_found("Network collector is not running"))?;
+
+    Ok(Json(CollectorStatusResponse {
+        status: collector.status(),
+        bridge: collector.bridge_stats(),
+    }))
 }
 
 /// Get statistics about traffic profiles
 /// GET /api/stats
-pub async fn get_stats(State(state): State<AppState>) -> Json<ProfileStats> {
-    Json(state.get_stats())
+pub async fn get_stats(State(state): State<AppState>) -> Result<Json<ProfileStats>> {
+    Ok(Json(state.query_stats().await?))
 }
 
 /// Response for profile search
@@ -463,7 +704,7 @@ pub async fn search_profiles(
     State(state): State<AppState>,
     Query(query): Query<SearchQuery>,
 ) -> Result<Json<SearchResponse>> {
-    let all_profiles = state.get_profiles();
+    let all_profiles = state.query_profiles(ProfileFilter::default()).await?;
     let search_term = query.q.to_lowercase();
     let limit = query.limit.unwrap_or(50);
 
@@ -487,6 +728,26 @@ pub async fn search_profiles(
             }
         }
 
+        // Search in JA4T (client SYN) and JA4TS (server SYN-ACK) fingerprints
+        let ja4t = profile
+            .raw_data
+            .syn
+            .as_ref()
+            .and_then(|syn| syn.ja4t.as_ref());
+        let ja4ts = profile
+            .raw_data
+            .syn_ack
+            .as_ref()
+            .and_then(|syn_ack| syn_ack.ja4ts.as_ref());
+        if ja4t
+            .into_iter()
+            .chain(ja4ts)
+            .any(|fingerprint| fingerprint.contains(&search_term))
+        {
+            relevance += 0.6;
+            matches += 1;
+        }
+
         // Search in HTTP browser detection
         if let Some(http) = &profile.http {
             if http.browser.to_lowercase().contains(&search_term) {
@@ -495,12 +756,64 @@ pub async fn search_profiles(
             }
         }
 
-        // Search in TLS JA4 fingerprint
+        // Search in parsed User-Agent (browser, OS and device class)
+        if let Some(user_agent) = profile
+            .http
+            .as_ref()
+            .and_then(|http| http.user_agent.as_ref())
+        {
+            let browser_match = user_agent
+                .browser_family
+                .as_ref()
+                .is_some_and(|browser| browser.to_lowercase().contains(&search_term));
+            let os_match = user_agent
+                .os_family
+                .is_some_and(|os| os.name().to_lowercase().contains(&search_term));
+            if browser_match || os_match || user_agent.device.as_str() == search_term {
+                relevance += 0.7;
+                matches += 1;
+            }
+        }
+
+        // Search in HTTP JA4H fingerprint
+        if let Some(ja4h) = profile
+            .raw_data
+            .http_request
+            .as_ref()
+            .and_then(|request| request.ja4h.as_ref())
+        {
+            if ja4h.to_lowercase().contains(&search_term) {
+                relevance += 0.6;
+                matches += 1;
+            }
+        }
+
+        // Search in TLS JA4/JA3 fingerprints and the application label
         if let Some(tls) = &profile.tls {
             if tls.ja4.to_lowercase().contains(&search_term) {
                 relevance += 0.6;
                 matches += 1;
             }
+            if !tls.ja3_hash.is_empty() && tls.ja3_hash.contains(&search_term) {
+                relevance += 0.6;
+                matches += 1;
+            }
+            if let Some(application) = &tls.client_application {
+                if application.name.to_lowercase().contains(&search_term) {
+                    relevance += 0.8 * application.confidence;
+                    matches += 1;
+                }
+            }
+        }
+
+        // Search in cross-layer consistency findings
+        if profile.consistency.iter().any(|finding| {
+            finding.kind.as_str().contains(&search_term)
+                || finding.description.to_lowercase().contains(&search_term)
+        }) || (profile.is_inconsistent() && search_term == "inconsistent")
+        {
+            relevance += 0.7;
+            matches += 1;
         }
 
         // Only include results with matches
@@ -585,6 +898,34 @@ pub async fn api_info() -> Json<ApiInfoResponse> {
                 path: "/api/profiles".to_string(),
                 description: "Clear all traffic profiles".to_string(),
             },
+            EndpointInfo {
+                method: "GET".to_string(),
+                path: "/api/connections".to_string(),
+                description: "Get per-connection (4-tuple) observations".to_string(),
+            },
+            EndpointInfo {
+                method: "GET".to_string(),
+                path: "/api/connections/{key}".to_string(),
+                description: "Get a specific connection observation".to_string(),
+            },
+            EndpointInfo {
+                method: "POST".to_string(),
+                path: "/api/signatures/reload".to_string(),
+                description: "Reload the signature database (also triggered by SIGHUP)".to_string(),
+            },
+            EndpointInfo {
+                method: "GET".to_string(),
+                path: "/api/v2/store/profiles".to_string(),
+                description:
+                    "Query the profile store by cidr, since/until and ja4/ja3/ja4h/ja4t (v2 format)"
+                        .to_string(),
+            },
+            EndpointInfo {
+                method: "GET".to_string(),
+                path: "/api/collector/status".to_string(),
+                description: "Capture state, restart count, last capture error and drop counts"
+                    .to_string(),
+            },
             EndpointInfo {
                 method: "GET".to_string(),
                 path: "/api/stats".to_string(),
@@ -595,6 +936,16 @@ pub async fn api_info() -> Json<ApiInfoResponse> {
                 path: "/api/search".to_string(),
                 description: "Search traffic profiles".to_string(),
             },
+            EndpointInfo {
+                method: "GET".to_string(),
+                path: "/api/v2/profiles".to_string(),
+                description: "Get observed traffic profiles with optional filtering".to_string(),
+            },
+            EndpointInfo {
+                method: "GET".to_string(),
+                path: "/api/v2/profiles/{key}".to_string(),
+                description: "Get a specific observed traffic profile".to_string(),
+            },
             EndpointInfo {
                 method: "GET".to_string(),
                 path: "/ws".to_string(),
@@ -7,6 +7,7 @@ pub mod error;
 pub mod handlers;
 pub mod server;
 pub mod state;
+pub mod v2;
 pub mod websocket;
 
 // Re-export main types
@@ -37,8 +38,10 @@ mod tests {
 
     #[test]
     fn test_server_config_interface() {
-        let mut config = ApiServerConfig::default();
-        config.interface = "wlan0".to_string();
+        let config = ApiServerConfig {
+            interface: "wlan0".to_string(),
+            ..Default::default()
+        };
         assert_eq!(config.interface, "wlan0");
     }
 
@@ -56,6 +59,14 @@ mod tests {
         assert!(error.to_string().contains("test error"));
     }
 
+    #[test]
+    fn test_unsupported_collector_operation_is_a_conflict() {
+        let error = ApiError::from(huginn_collector::CollectorError::unsupported(
+            "Signature reload is only supported for live captures",
+        ));
+        assert_eq!(error.status_code(), axum::http::StatusCode::CONFLICT);
+    }
+
     #[test]
     fn test_api_error_configuration() {
         let error = ApiError::configuration("config error");
@@ -69,6 +80,295 @@ mod tests {
         drop(state);
     }
 
+    #[test]
+    fn test_app_state_connections() {
+        let state = AppState::new();
+        assert!(state.get_connections().is_empty());
+        assert!(state
+            .get_connection("10.0.0.5:40001-10.0.0.1:443")
+            .is_none());
+
+        state.clear_profiles();
+        assert!(state.get_connections().is_empty());
+    }
+
+    #[test]
+    fn test_evicted_profiles_are_reported_as_removed() {
+        let state = AppState::new();
+        let profile = huginn_core::TrafficProfile::new("10.0.0.5".parse().unwrap(), 40001);
+        state.update_profiles([("10.0.0.5".to_string(), profile)].into());
+
+        let mut updates = state.subscribe_updates();
+        state.update_profiles(std::collections::HashMap::new());
+
+        let update = updates.try_recv().unwrap();
+        assert!(matches!(
+            update.update_type,
+            state::UpdateType::ProfileRemoved
+        ));
+        assert_eq!(update.key, "10.0.0.5");
+        assert_eq!(state.profile_count(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_store_query_filters_by_cidr() {
+        use huginn_collector::{MemoryStore, ProfileFilter, ProfileStore};
+
+        let store = std::sync::Arc::new(MemoryStore::new());
+        for ip in ["10.0.0.5", "10.0.1.7", "192.168.1.10"] {
+            store
+                .put(
+                    ip,
+                    huginn_core::TrafficProfile::new(ip.parse().unwrap(), 40001),
+                )
+                .unwrap();
+        }
+
+        let filter = ProfileFilter {
+            cidr: Some("10.0.0.0/16".parse().unwrap()),
+            ..Default::default()
+        };
+        let axum::Json(response) = v2::query_store(
+            axum::extract::State(AppState::with_store(store)),
+            axum::extract::Query(filter),
+        )
+        .await
+        .unwrap();
+        assert_eq!(response.count, 2);
+
+        let missing = v2::query_store(
+            axum::extract::State(AppState::new()),
+            axum::extract::Query(ProfileFilter::default()),
+        )
+        .await;
+        assert!(missing.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_profile_lists_read_the_store() {
+        use huginn_collector::{MemoryStore, ProfileStore};
+
+        let store = std::sync::Arc::new(MemoryStore::new());
+        let state = AppState::with_store(store.clone());
+        store
+            .put(
+                "10.0.0.5",
+                huginn_core::TrafficProfile::new("10.0.0.5".parse().unwrap(), 40001),
+            )
+            .unwrap();
+        assert_eq!(state.profile_count(), 0, "nothing was polled yet");
+
+        let axum::Json(v1) = handlers::get_profiles(
+            axum::extract::State(state.clone()),
+            axum::extract::Query(handlers::ProfileQuery::default()),
+        )
+        .await
+        .unwrap();
+        let axum::Json(v2) = v2::get_profiles(
+            axum::extract::State(state.clone()),
+            axum::extract::Query(handlers::ProfileQuery::default()),
+        )
+        .await
+        .unwrap();
+        assert_eq!((v1.count, v2.count), (1, 1));
+
+        let axum::Json(stats) = handlers::get_stats(axum::extract::State(state.clone()))
+            .await
+            .unwrap();
+        assert_eq!(stats.total_profiles, 1);
+        let axum::Json(search) = handlers::search_profiles(
+            axum::extract::State(state.clone()),
+            axum::extract::Query(handlers::SearchQuery {
+                q: "10.0.0.5".to_string(),
+                limit: None,
+            }),
+        )
+        .await
+        .unwrap();
+        assert_eq!(search.count, 1);
+        let key = || axum::extract::Path("10.0.0.5".to_string());
+        assert!(
+            handlers::get_profile(axum::extract::State(state.clone()), key())
+                .await
+                .is_ok()
+        );
+        assert!(v2::get_profile(axum::extract::State(state.clone()), key())
+            .await
+            .is_ok());
+
+        // A poll applies the changed profiles and drops the evicted ones
+        state.merge_profiles(
+            store.query(&Default::default()).unwrap(),
+            &store.keys().unwrap().into_iter().collect(),
+        );
+        assert_eq!(state.profile_count(), 1);
+        state.merge_profiles(Vec::new(), &Default::default());
+        assert_eq!(state.profile_count(), 0);
+
+        // Deleting removes the profile from the store, not just the cache
+        handlers::delete_profile(axum::extract::State(state.clone()), key())
+            .await
+            .unwrap();
+        assert!(store.get("10.0.0.5").unwrap().is_none());
+        assert!(v2::get_profile(axum::extract::State(state.clone()), key())
+            .await
+            .is_err());
+    }
+
+    #[test]
+    fn test_v2_profile_has_no_placeholders() {
+        let profile = huginn_core::TrafficProfile::new("192.168.1.10".parse().unwrap(), 51234);
+        let json = serde_json::to_value(v2::ProfileV2::from(&profile)).unwrap();
+
+        assert_eq!(json["ip"], "192.168.1.10");
+        for field in [
+            "syn",
+            "syn_ack",
+            "mtu",
+            "uptime",
+            "http_request",
+            "tls_client",
+        ] {
+            assert!(json[field].is_null(), "{} should be null", field);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_v1_uptime_reports_observed_clock() {
+        let mut profile = huginn_core::TrafficProfile::new("10.0.0.5".parse().unwrap(), 40001);
+        profile.raw_data.uptime = Some(huginn_core::profile::UptimeData {
+            source: huginn_core::profile::NetworkEndpoint {
+                ip: "10.0.0.5".to_string(),
+                port: 40001,
+            },
+            uptime_seconds: 2 * 86400 + 3 * 3600 + 4 * 60,
+            days: 2,
+            hours: 3,
+            minutes: 4,
+            modulo_days: 49,
+            frequency_hz: 1000.0,
+            timestamp: chrono::Utc::now(),
+        });
+        let state = AppState::new();
+        state.upsert_profile("10.0.0.5".to_string(), profile);
+
+        let axum::Json(info) = handlers::get_profile(
+            axum::extract::State(state),
+            axum::extract::Path("10.0.0.5".to_string()),
+        )
+        .await
+        .unwrap();
+        let uptime = info.uptime.unwrap();
+        assert_eq!(uptime.time, "2 days, 3 hrs, 4 min (modulo 49 days)");
+        assert_eq!(uptime.freq, "1000.00 Hz");
+    }
+
+    #[test]
+    fn test_profile_query_filters_by_ja3() {
+        let ja3 = huginn_core::Ja3::from_client_hello(771, &[4865], &[0, 10], &[29], &[0]);
+        let mut profile = huginn_core::TrafficProfile::new("10.0.0.5".parse().unwrap(), 40001);
+        profile.update_tls(huginn_core::TlsAnalysis {
+            ja4: String::new(),
+            ja4_raw: String::new(),
+            ja4_original: String::new(),
+            ja4_original_raw: String::new(),
+            client_application: None,
+            ja3: ja3.string.clone(),
+            ja3_hash: ja3.hash.clone(),
+            details: huginn_core::profile::TlsDetails {
+                version: "13".to_string(),
+                sni: None,
+                alpn: None,
+                cipher_suites: vec![4865],
+                extensions: vec![0, 10],
+                signature_algorithms: vec![],
+                elliptic_curves: vec![29],
+                elliptic_curve_point_formats: vec![0],
+            },
+        });
+
+        let by_hash = handlers::ProfileQuery {
+            ja3: Some(ja3.hash.to_uppercase()),
+            ..Default::default()
+        };
+        let by_string = handlers::ProfileQuery {
+            ja3: Some(ja3.string),
+            ..Default::default()
+        };
+        let other = handlers::ProfileQuery {
+            ja3: Some("00000000000000000000000000000000".to_string()),
+            ..Default::default()
+        };
+        assert!(by_hash.matches(&profile));
+        assert!(by_string.matches(&profile));
+        assert!(!other.matches(&profile));
+    }
+
+    #[test]
+    fn test_profile_query_sorts_and_filters_by_risk() {
+        let profiles: std::collections::HashMap<_, _> =
+            [("10.0.0.1", 20), ("10.0.0.2", 80), ("10.0.0.3", 50)]
+                .into_iter()
+                .map(|(ip, score)| {
+                    let mut profile = huginn_core::TrafficProfile::new(ip.parse().unwrap(), 40001);
+                    profile.risk.score = score;
+                    (ip.to_string(), profile)
+                })
+                .collect();
+
+        let riskiest = handlers::ProfileQuery {
+            sort: Some(handlers::ProfileSort::Risk),
+            limit: Some(2),
+            ..Default::default()
+        };
+        let keys: Vec<_> = riskiest
+            .select(&profiles)
+            .into_iter()
+            .map(|(key, _)| key.as_str())
+            .collect();
+        assert_eq!(keys, ["10.0.0.2", "10.0.0.3"]);
+
+        let moderate = handlers::ProfileQuery {
+            min_risk: Some(30),
+            max_risk: Some(60),
+            sort: Some(handlers::ProfileSort::RiskAsc),
+            ..Default::default()
+        };
+        let selected = moderate.select(&profiles);
+        assert_eq!(selected.len(), 1);
+        assert_eq!(selected[0].0, "10.0.0.3");
+
+        let ordered = handlers::OrderedProfiles(vec![("b".to_string(), 1), ("a".to_string(), 2)]);
+        assert_eq!(serde_json::to_string(&ordered).unwrap(), r#"{"b":1,"a":2}"#);
+    }
+
+    #[tokio::test]
+    async fn test_search_matches_inconsistent_as_a_whole_word() {
+        let mut profile = huginn_core::TrafficProfile::new("10.0.0.5".parse().unwrap(), 40001);
+        profile.consistency.push(huginn_core::ConsistencyFinding {
+            kind: huginn_core::FindingKind::UserAgentOsMismatch,
+            severity: huginn_core::Severity::High,
+            claimed: "Windows".to_string(),
+            observed: "Linux".to_string(),
+            description: "User-Agent claims Windows but the SYN looks like Linux".to_string(),
+        });
+        let state = AppState::new();
+        state.upsert_profile("10.0.0.5".to_string(), profile);
+
+        for (term, count) in [("inconsistent", 1), ("tent", 0)] {
+            let axum::Json(response) = handlers::search_profiles(
+                axum::extract::State(state.clone()),
+                axum::extract::Query(handlers::SearchQuery {
+                    q: term.to_string(),
+                    limit: None,
+                }),
+            )
+            .await
+            .unwrap();
+            assert_eq!(response.count, count, "search for {:?}", term);
+        }
+    }
+
     #[test]
     fn test_socket_addr_parsing() {
         let addr: SocketAddr = "127.0.0.1:8080".parse().unwrap();
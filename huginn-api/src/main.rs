@@ -1,5 +1,4 @@
 use huginn_api::server::run_server;
-use tracing_subscriber;
 
 #[tokio::main]
 async fn main() {
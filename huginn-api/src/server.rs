@@ -2,12 +2,21 @@ use crate::{
     error::{ApiError, Result},
     handlers::*,
     state::AppState,
+    v2,
     // websocket::websocket_handler,
 };
-use axum::{routing::get, Router};
+use axum::{
+    routing::{get, post},
+    Router,
+};
 use clap::Parser;
-use huginn_collector::{CollectorConfig, NetworkCollector};
+use huginn_collector::{
+    CaptureSource, CaptureState, CollectorConfig, NetworkCollector, OverflowPolicy, ProfileStore,
+    SqliteStore, DEFAULT_MAX_CONNECTIONS,
+};
 use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::Arc;
 // use tower::ServiceBuilder;
 use tower_http::{
     cors::{Any, CorsLayer},
@@ -59,6 +68,22 @@ pub struct ApiServerArgs {
     #[arg(short = 'b', long, default_value = "127.0.0.1:8080")]
     pub bind: String,
 
+    /// Replay a pcap/pcapng file instead of capturing on the interface
+    #[arg(long)]
+    pub pcap: Option<PathBuf>,
+
+    /// Signature database file (p0f format); reload it with SIGHUP or POST /api/signatures/reload
+    #[arg(long)]
+    pub signature_db: Option<PathBuf>,
+
+    /// JA4 label file used to name TLS clients (one `<ja4> <label>` per line)
+    #[arg(long)]
+    pub ja4_db: Option<PathBuf>,
+
+    /// MTU table used to name link types (one `<mtu|min-max> <yes|no> <label>` per line)
+    #[arg(long)]
+    pub link_table: Option<PathBuf>,
+
     /// Disable network collector (serve static profiles only)
     #[arg(long)]
     pub no_collector: bool,
@@ -90,6 +115,26 @@ pub struct ApiServerArgs {
     /// Buffer size for profile processing
     #[arg(long, default_value = "1000")]
     pub buffer_size: usize,
+
+    /// Maximum number of profiles kept (least recently updated evicted first)
+    #[arg(long)]
+    pub max_profiles: Option<usize>,
+
+    /// Maximum number of connection observations kept (least recently updated evicted first)
+    #[arg(long, default_value_t = DEFAULT_MAX_CONNECTIONS)]
+    pub max_connections: usize,
+
+    /// Evict profiles and connections not updated for this many seconds
+    #[arg(long)]
+    pub idle_ttl_secs: Option<u64>,
+
+    /// SQLite database to persist profiles in (with --no-collector, serve its profiles)
+    #[arg(long)]
+    pub store: Option<PathBuf>,
+
+    /// What to do when the collector falls behind: block, drop-newest, drop-oldest or sample:<N>
+    #[arg(long, default_value = "block")]
+    pub overflow_policy: OverflowPolicy,
 }
 
 impl From<ApiServerArgs> for ApiServerConfig {
@@ -99,10 +144,23 @@ impl From<ApiServerArgs> for ApiServerConfig {
             .parse()
             .unwrap_or_else(|_| SocketAddr::from(([127, 0, 0, 1], 8080)));
 
-        let mut collector_config = CollectorConfig::default();
-        collector_config.interface = args.interface.clone();
+        let mut collector_config = CollectorConfig {
+            source: match args.pcap {
+                Some(path) => CaptureSource::PcapFile(path),
+                None => CaptureSource::Interface(args.interface.clone()),
+            },
+            ..Default::default()
+        };
+        collector_config.signature_db = args.signature_db;
+        collector_config.ja4_db = args.ja4_db;
+        collector_config.link_table = args.link_table;
         collector_config.buffer_size = args.buffer_size;
         collector_config.channel_buffer_size = args.buffer_size;
+        collector_config.overflow_policy = args.overflow_policy;
+        collector_config.max_profiles = args.max_profiles;
+        collector_config.max_connections = Some(args.max_connections);
+        collector_config.idle_ttl_secs = args.idle_ttl_secs;
+        collector_config.store_path = args.store;
         collector_config.analyzer.enable_tcp = args.enable_tcp;
         collector_config.analyzer.enable_http = args.enable_http;
         collector_config.analyzer.enable_tls = args.enable_tls;
@@ -150,8 +208,8 @@ impl ApiServer {
         // Start network collector if enabled
         if self.config.enable_collector {
             info!(
-                "Starting network collector on interface: {}",
-                self.config.interface
+                "Starting network collector on {}",
+                self.config.collector_config.source
             );
 
             match self.start_collector().await {
@@ -163,6 +221,9 @@ impl ApiServer {
             }
         } else {
             info!("Network collector disabled");
+            if let Some(path) = self.config.collector_config.store_path.clone() {
+                self.open_store(&path)?;
+            }
         }
 
         // Build the router
@@ -195,6 +256,19 @@ impl ApiServer {
         Ok(())
     }
 
+    /// Serve the profiles persisted in the store at `path`
+    fn open_store(&mut self, path: &std::path::Path) -> Result<()> {
+        let store: Arc<dyn ProfileStore> = Arc::new(SqliteStore::open(path)?);
+        self.state = AppState::with_store(store.clone());
+        self.state.update_profiles(store.all()?);
+        info!(
+            "Serving {} stored profiles from {}",
+            self.state.get_profiles().len(),
+            path.display()
+        );
+        Ok(())
+    }
+
     /// Start the network collector
     async fn start_collector(&mut self) -> Result<()> {
         let collector = NetworkCollector::new(self.config.collector_config.clone())?;
@@ -205,6 +279,10 @@ impl ApiServer {
         // Update state with collector handle
         self.state = AppState::with_collector(collector_handle);
 
+        // Reload signatures on SIGHUP
+        #[cfg(unix)]
+        Self::spawn_sighup_reload(self.state.collector_handle.as_ref().unwrap().clone());
+
         // Start profile polling task
         let state_clone = self.state.clone();
         let collector_handle_clone = self.state.collector_handle.as_ref().unwrap().clone();
@@ -213,6 +291,9 @@ impl ApiServer {
             info!("Starting profile polling task");
 
             let mut interval = tokio::time::interval(std::time::Duration::from_secs(2));
+            let mut capture_state = CaptureState::Running;
+            let mut profile_sequence = 0;
+            let mut newest_connection = None;
 
             loop {
                 interval.tick().await;
@@ -223,15 +304,68 @@ impl ApiServer {
                     break;
                 }
 
-                // Get profiles from the collector
-                match collector_handle_clone.get_profiles().await {
-                    Ok(profiles) => {
-                        if !profiles.is_empty() {
-                            info!("Retrieved {} profiles from collector", profiles.len());
-                            state_clone.update_profiles(profiles);
-                        } else {
-                            debug!("No profiles available from collector");
+                // The supervisor restarts a failed capture; say so when its state changes
+                let status = collector_handle_clone.status();
+                if status.state != capture_state {
+                    capture_state = status.state;
+                    match status.state {
+                        CaptureState::Restarting | CaptureState::Failed => warn!(
+                            "Capture is {:?} after {} restarts: {}",
+                            status.state,
+                            status.restarts,
+                            status.last_error.as_deref().unwrap_or("unknown error")
+                        ),
+                        _ => info!("Capture is {:?}", status.state),
+                    }
+                }
+
+                // Same for connection observations: only those updated since the last poll
+                let connection_changes = async {
+                    let changed = match newest_connection {
+                        Some(since) => collector_handle_clone.get_connections_since(since).await?,
+                        None => collector_handle_clone
+                            .get_connections()
+                            .await?
+                            .into_iter()
+                            .collect(),
+                    };
+                    let keys = collector_handle_clone.get_connection_keys().await?;
+                    Ok::<_, huginn_collector::CollectorError>((changed, keys))
+                };
+                match connection_changes.await {
+                    Ok((changed, keys)) => {
+                        newest_connection = changed
+                            .iter()
+                            .map(|(_, connection)| connection.last_updated)
+                            .chain(newest_connection)
+                            .max();
+                        state_clone.merge_connections(changed, &keys.into_iter().collect());
+                    }
+                    Err(e) => {
+                        error!("Failed to get connections from collector: {}", e);
+                    }
+                }
+
+                // Only read profiles written since the last poll, by the store's
+                // change sequence: late results may carry older update times.
+                // Keys missing from the store were evicted and go out as ProfileRemoved
+                let changes = async {
+                    let changes = collector_handle_clone
+                        .get_profile_changes(profile_sequence)
+                        .await?;
+                    let keys = collector_handle_clone.get_profile_keys().await?;
+                    Ok::<_, huginn_collector::CollectorError>((changes, keys))
+                };
+                match changes.await {
+                    Ok((changes, keys)) => {
+                        if !changes.profiles.is_empty() {
+                            debug!(
+                                "Retrieved {} updated profiles from collector",
+                                changes.profiles.len()
+                            );
                         }
+                        profile_sequence = changes.sequence;
+                        state_clone.merge_profiles(changes.profiles, &keys.into_iter().collect());
                     }
                     Err(e) => {
                         error!("Failed to get profiles from collector: {}", e);
@@ -245,6 +379,33 @@ impl ApiServer {
         Ok(())
     }
 
+    /// Reload the signature database whenever the process receives SIGHUP
+    #[cfg(unix)]
+    fn spawn_sighup_reload(collector_handle: std::sync::Arc<huginn_collector::CollectorHandle>) {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        let mut hangup = match signal(SignalKind::hangup()) {
+            Ok(hangup) => hangup,
+            Err(e) => {
+                warn!("Failed to install SIGHUP handler: {}", e);
+                return;
+            }
+        };
+
+        tokio::spawn(async move {
+            while hangup.recv().await.is_some() {
+                info!("SIGHUP received, reloading signature database");
+                match collector_handle.reload_signatures().await {
+                    Ok(reload) => info!(
+                        "Signature database reloaded (capture generation {})",
+                        reload.generation
+                    ),
+                    Err(e) => error!("Signature reload failed: {}", e),
+                }
+            }
+        });
+    }
+
     /// Build the Axum router
     fn build_router(&self) -> Router {
         let mut router = Router::new()
@@ -257,8 +418,16 @@ impl ApiServer {
                 "/api/profiles/{key}",
                 get(get_profile).delete(delete_profile),
             )
+            .route("/api/connections", get(get_connections))
+            .route("/api/connections/{key}", get(get_connection))
+            .route("/api/signatures/reload", post(reload_signatures))
+            .route("/api/collector/status", get(get_collector_status))
             .route("/api/stats", get(get_stats))
             .route("/api/search", get(search_profiles))
+            // v2 endpoints (observed data only, no placeholders)
+            .route("/api/v2/profiles", get(v2::get_profiles))
+            .route("/api/v2/profiles/{key}", get(v2::get_profile))
+            .route("/api/v2/store/profiles", get(v2::query_store))
             // WebSocket endpoint (temporarily disabled)
             // .route("/ws", get(websocket_handler))
             // Add state
@@ -1,19 +1,25 @@
 use arc_swap::ArcSwap;
-use huginn_collector::CollectorHandle;
-use huginn_core::TrafficProfile;
-use std::collections::HashMap;
+use huginn_collector::{CollectorHandle, ProfileFilter, ProfileStore};
+use huginn_core::{ConnectionObservation, TrafficProfile};
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 use tokio::sync::broadcast;
 
 /// Shared application state
 #[derive(Clone)]
 pub struct AppState {
-    /// Traffic profiles cache (thread-safe)
+    /// Profiles as of the last collector poll, diffed to feed `updates_tx`
+    ///
+    /// Reads go to `store` when there is one; this cache only answers them without a store.
     pub profiles: Arc<ArcSwap<HashMap<String, TrafficProfile>>>,
+    /// Per-connection observations keyed by `client-server` 4-tuple (thread-safe)
+    pub connections: Arc<ArcSwap<HashMap<String, ConnectionObservation>>>,
     /// Broadcast channel for real-time updates
     pub updates_tx: broadcast::Sender<ProfileUpdate>,
     /// Optional collector handle for management
     pub collector_handle: Option<Arc<CollectorHandle>>,
+    /// Profile store backing the collector, queried directly by the store endpoints
+    pub store: Option<Arc<dyn ProfileStore>>,
 }
 
 /// Update event for real-time notifications
@@ -50,18 +56,28 @@ impl AppState {
 
         Self {
             profiles: Arc::new(ArcSwap::new(Arc::new(HashMap::new()))),
+            connections: Arc::new(ArcSwap::new(Arc::new(HashMap::new()))),
             updates_tx,
             collector_handle: None,
+            store: None,
         }
     }
 
     /// Create application state with collector handle
     pub fn with_collector(collector_handle: CollectorHandle) -> Self {
         let mut state = Self::new();
+        state.store = Some(collector_handle.store());
         state.collector_handle = Some(Arc::new(collector_handle));
         state
     }
 
+    /// Create application state that serves the profiles in `store` without a collector
+    pub fn with_store(store: Arc<dyn ProfileStore>) -> Self {
+        let mut state = Self::new();
+        state.store = Some(store);
+        state
+    }
+
     /// Get all profiles
     pub fn get_profiles(&self) -> Arc<HashMap<String, TrafficProfile>> {
         self.profiles.load_full()
@@ -72,6 +88,76 @@ impl AppState {
         self.profiles.load().get(key).cloned()
     }
 
+    /// Profiles matching `filter`, read from the store when there is one
+    ///
+    /// Without a store this filters the cached profiles instead.
+    pub async fn query_profiles(
+        &self,
+        filter: ProfileFilter,
+    ) -> crate::Result<HashMap<String, TrafficProfile>> {
+        let Some(store) = self.store.clone() else {
+            return Ok(self
+                .get_profiles()
+                .iter()
+                .filter(|(_, profile)| filter.matches(profile))
+                .map(|(key, profile)| (key.clone(), profile.clone()))
+                .collect());
+        };
+
+        // SQLite calls block, so keep them off the async workers
+        let matching = tokio::task::spawn_blocking(move || store.query(&filter))
+            .await
+            .map_err(|e| crate::ApiError::internal(format!("Store query failed: {}", e)))??;
+        Ok(matching.into_iter().collect())
+    }
+
+    /// The profile stored under `key`, read from the store when there is one
+    pub async fn query_profile(&self, key: &str) -> crate::Result<Option<TrafficProfile>> {
+        let Some(store) = self.store.clone() else {
+            return Ok(self.get_profile(key));
+        };
+
+        let key = key.to_string();
+        tokio::task::spawn_blocking(move || store.get(&key))
+            .await
+            .map_err(|e| crate::ApiError::internal(format!("Store query failed: {}", e)))?
+            .map_err(Into::into)
+    }
+
+    /// Statistics over every profile, read from the store when there is one
+    pub async fn query_stats(&self) -> crate::Result<ProfileStats> {
+        let profiles = self.query_profiles(ProfileFilter::default()).await?;
+        Ok(ProfileStats::of(&profiles))
+    }
+
+    /// Remove a profile from the store (when there is one) and the cache
+    pub async fn remove_stored_profile(&self, key: &str) -> crate::Result<bool> {
+        let cached = self.remove_profile(key).is_some();
+        let Some(store) = self.store.clone() else {
+            return Ok(cached);
+        };
+
+        let key = key.to_string();
+        tokio::task::spawn_blocking(move || store.remove(&key))
+            .await
+            .map_err(|e| crate::ApiError::internal(format!("Store update failed: {}", e)))?
+            .map_err(Into::into)
+    }
+
+    /// Remove every profile from the collector or store (when there is one) and the cache
+    pub async fn clear_stored_profiles(&self) -> crate::Result<()> {
+        if let Some(collector) = &self.collector_handle {
+            // The collector forgets its connection observations too
+            collector.clear_profiles().await?;
+        } else if let Some(store) = self.store.clone() {
+            tokio::task::spawn_blocking(move || store.clear())
+                .await
+                .map_err(|e| crate::ApiError::internal(format!("Store update failed: {}", e)))??;
+        }
+        self.clear_profiles();
+        Ok(())
+    }
+
     /// Update profiles and notify subscribers
     pub fn update_profiles(&self, new_profiles: HashMap<String, TrafficProfile>) {
         let old_profiles = self.profiles.load_full();
@@ -118,6 +204,55 @@ impl AppState {
         self.profiles.store(Arc::new(new_profiles));
     }
 
+    /// Apply the profiles changed since the last poll, dropping those not in `keys`
+    pub fn merge_profiles(&self, changed: Vec<(String, TrafficProfile)>, keys: &HashSet<String>) {
+        let mut new_profiles: HashMap<_, _> = self
+            .profiles
+            .load()
+            .iter()
+            .filter(|(key, _)| keys.contains(*key))
+            .map(|(key, profile)| (key.clone(), profile.clone()))
+            .collect();
+        new_profiles.extend(changed);
+        self.update_profiles(new_profiles);
+    }
+
+    /// Get all connection observations
+    pub fn get_connections(&self) -> Arc<HashMap<String, ConnectionObservation>> {
+        self.connections.load_full()
+    }
+
+    /// Get a specific connection observation by key
+    pub fn get_connection(&self, key: &str) -> Option<ConnectionObservation> {
+        self.connections.load().get(key).cloned()
+    }
+
+    /// Apply the connections changed since the last poll, dropping those not in `keys`
+    ///
+    /// Leaves the current map alone when nothing changed.
+    pub fn merge_connections(
+        &self,
+        changed: Vec<(String, ConnectionObservation)>,
+        keys: &HashSet<String>,
+    ) {
+        let current = self.connections.load();
+        if changed.is_empty() && current.keys().all(|key| keys.contains(key)) {
+            return;
+        }
+        let mut new_connections: HashMap<_, _> = current
+            .iter()
+            .filter(|(key, _)| keys.contains(*key))
+            .map(|(key, connection)| (key.clone(), connection.clone()))
+            .collect();
+        new_connections.extend(changed);
+        self.update_connections(new_connections);
+    }
+
+    /// Replace the connection observations
+    pub fn update_connections(&self, new_connections: HashMap<String, ConnectionObservation>) {
+        self.connections.store(Arc::new(new_connections));
+    }
+
     /// Add or update a single profile
     pub fn upsert_profile(&self, key: String, profile: TrafficProfile) {
         let current = self.profiles.load_full();
@@ -165,6 +300,7 @@ impl AppState {
     /// Clear all profiles
     pub fn clear_profiles(&self) {
         let current = self.profiles.load_full();
+        self.connections.store(Arc::new(HashMap::new()));
 
         // Notify removal of each profile
         for key in current.keys() {
@@ -189,28 +325,75 @@ impl AppState {
         self.updates_tx.subscribe()
     }
 
-    /// Get statistics about profiles
+    /// Get statistics about the cached profiles
     pub fn get_stats(&self) -> ProfileStats {
-        let profiles = self.profiles.load();
+        ProfileStats::of(&self.profiles.load())
+    }
+
+    /// Send update notification
+    fn notify_update(&self, update: ProfileUpdate) {
+        // Ignore errors if no subscribers
+        let _ = self.updates_tx.send(update);
+    }
+}
 
+impl Default for AppState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ProfileStats {
+    /// Statistics over `profiles`
+    pub fn of(profiles: &HashMap<String, TrafficProfile>) -> Self {
         let mut tcp_count = 0;
         let mut http_count = 0;
         let mut tls_count = 0;
         let mut complete_count = 0;
+        let mut inconsistent_count = 0;
+        let mut nat_count = 0;
+        let mut estimated_hosts = 0;
+        let mut client_applications = HashMap::new();
+        let mut browsers = HashMap::new();
+        let mut operating_systems = HashMap::new();
+        let mut device_classes = HashMap::new();
 
         for profile in profiles.values() {
             if profile.tcp.is_some() {
                 tcp_count += 1;
             }
-            if profile.http.is_some() {
+            if let Some(http) = &profile.http {
                 http_count += 1;
+                if let Some(user_agent) = &http.user_agent {
+                    if let Some(browser) = &user_agent.browser_family {
+                        *browsers.entry(browser.clone()).or_insert(0) += 1;
+                    }
+                    if let Some(os) = user_agent.os_family {
+                        *operating_systems.entry(os.to_string()).or_insert(0) += 1;
+                    }
+                    *device_classes
+                        .entry(user_agent.device.as_str().to_string())
+                        .or_insert(0) += 1;
+                }
             }
-            if profile.tls.is_some() {
+            if let Some(tls) = &profile.tls {
                 tls_count += 1;
+                if let Some(application) = &tls.client_application {
+                    *client_applications
+                        .entry(application.name.clone())
+                        .or_insert(0) += 1;
+                }
             }
             if profile.metadata.completeness >= 1.0 {
                 complete_count += 1;
             }
+            if profile.is_inconsistent() {
+                inconsistent_count += 1;
+            }
+            if profile.nat.behind_nat {
+                nat_count += 1;
+            }
+            estimated_hosts += profile.nat.estimated_hosts.max(1);
         }
 
         ProfileStats {
@@ -219,21 +402,16 @@ impl AppState {
             http_profiles: http_count,
             tls_profiles: tls_count,
             complete_profiles: complete_count,
+            inconsistent_profiles: inconsistent_count,
+            nat_profiles: nat_count,
+            estimated_hosts,
+            client_applications,
+            browsers,
+            operating_systems,
+            device_classes,
             timestamp: chrono::Utc::now(),
         }
     }
-
-    /// Send update notification
-    fn notify_update(&self, update: ProfileUpdate) {
-        // Ignore errors if no subscribers
-        let _ = self.updates_tx.send(update);
-    }
-}
-
-impl Default for AppState {
-    fn default() -> Self {
-        Self::new()
-    }
 }
 
 /// Statistics about traffic profiles
@@ -249,6 +427,20 @@ pub struct ProfileStats {
     pub tls_profiles: usize,
     /// Number of complete profiles (all data types)
     pub complete_profiles: usize,
+    /// Number of profiles with cross-layer inconsistencies
+    pub inconsistent_profiles: usize,
+    /// Number of IPs that appear to be shared by several hosts (NAT)
+    pub nat_profiles: usize,
+    /// Estimated number of distinct hosts across all profiles
+    pub estimated_hosts: usize,
+    /// Number of profiles per labelled TLS client application
+    pub client_applications: HashMap<String, usize>,
+    /// Number of profiles per User-Agent browser family
+    pub browsers: HashMap<String, usize>,
+    /// Number of profiles per User-Agent OS family
+    pub operating_systems: HashMap<String, usize>,
+    /// Number of profiles per User-Agent device class
+    pub device_classes: HashMap<String, usize>,
     /// When these stats were generated
     pub timestamp: chrono::DateTime<chrono::Utc>,
 }
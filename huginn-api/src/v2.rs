@@ -0,0 +1,208 @@
+//! Version 2 of the profile API
+//!
+//! Unlike the v1 `TcpInfo` shape, v2 serializes the data huginn-core actually
+//! produced. Nothing is filled in: values that were not observed are `null`.
+
+use crate::error::{ApiError, Result};
+use crate::handlers::{OrderedProfiles, ProfileQuery};
+use crate::state::AppState;
+use axum::{
+    extract::{Path, Query, State},
+    response::Json,
+};
+use huginn_collector::ProfileFilter;
+use huginn_core::profile::{
+    HttpDetails, HttpRequestData, HttpResponseData, MtuData, ProfileMetadata, SynAckPacketData,
+    SynPacketData, TlsClientData, UptimeData,
+};
+use huginn_core::{
+    ClientApplication, ConsistencyFinding, HopHistory, NatAnalysis, RiskScore, TrafficProfile,
+    UptimeHistory, UserAgent,
+};
+use serde::Serialize;
+
+/// Browser label huginn-core uses when no signature matched
+const UNKNOWN_BROWSER: &str = "Unknown";
+
+/// A traffic profile as observed, without placeholder values
+#[derive(Debug, Serialize, Clone)]
+pub struct ProfileV2 {
+    pub ip: String,
+    pub port: u16,
+    pub syn: Option<SynPacketData>,
+    pub syn_ack: Option<SynAckPacketData>,
+    pub mtu: Option<MtuData>,
+    pub link_type: Option<String>,
+    pub likely_vpn: bool,
+    pub uptime: Option<UptimeData>,
+    pub http_request: Option<HttpRequestV2>,
+    pub http_response: Option<HttpResponseData>,
+    pub tls_client: Option<TlsClientV2>,
+    pub consistency: Vec<ConsistencyFinding>,
+    pub nat: NatAnalysis,
+    pub risk: RiskScore,
+    pub hops: HopHistory,
+    pub last_reboot_estimate: Option<chrono::DateTime<chrono::Utc>>,
+    pub uptime_history: UptimeHistory,
+    pub metadata: ProfileMetadata,
+}
+
+/// HTTP request data together with the analysis of that request, if any
+#[derive(Debug, Serialize, Clone)]
+pub struct HttpRequestV2 {
+    #[serde(flatten)]
+    pub request: HttpRequestData,
+    /// Matched browser, `null` when no signature matched
+    pub browser: Option<String>,
+    /// Parsed User-Agent, `null` when the request had none
+    pub parsed_user_agent: Option<UserAgent>,
+    pub lang: Option<String>,
+    pub diagnosis: Option<String>,
+    /// Observed signature fields, `null` when the request was not analyzed
+    pub observed: Option<HttpDetails>,
+}
+
+/// TLS ClientHello data with both JA4 variants
+#[derive(Debug, Serialize, Clone)]
+pub struct TlsClientV2 {
+    #[serde(flatten)]
+    pub client: TlsClientData,
+    /// JA4 over the original (unsorted) ordering, `null` when not analyzed
+    pub ja4_original: Option<String>,
+    pub ja4_original_raw: Option<String>,
+    /// Application label from the JA4 database, `null` when unknown
+    pub client_application: Option<ClientApplication>,
+}
+
+impl From<&TrafficProfile> for ProfileV2 {
+    fn from(profile: &TrafficProfile) -> Self {
+        // Only use the HTTP analysis when it came from a request
+        let http_analysis = profile.http.as_ref().filter(|http| http.request.is_some());
+
+        let http_request = profile
+            .raw_data
+            .http_request
+            .as_ref()
+            .map(|request| HttpRequestV2 {
+                request: request.clone(),
+                browser: http_analysis
+                    .map(|http| http.browser.clone())
+                    .filter(|browser| browser != UNKNOWN_BROWSER),
+                parsed_user_agent: request.user_agent.as_deref().map(UserAgent::parse),
+                lang: http_analysis.and_then(|http| http.language.clone()),
+                diagnosis: http_analysis.map(|http| http.diagnosis.clone()),
+                observed: http_analysis.map(|http| http.details.clone()),
+            });
+
+        let tls_client = profile
+            .raw_data
+            .tls_client
+            .as_ref()
+            .map(|client| TlsClientV2 {
+                client: client.clone(),
+                ja4_original: profile.tls.as_ref().map(|tls| tls.ja4_original.clone()),
+                ja4_original_raw: profile.tls.as_ref().map(|tls| tls.ja4_original_raw.clone()),
+                client_application: profile
+                    .tls
+                    .as_ref()
+                    .and_then(|tls| tls.client_application.clone()),
+            });
+
+        Self {
+            ip: profile.ip.to_string(),
+            port: profile.port,
+            syn: profile.raw_data.syn.clone(),
+            syn_ack: profile.raw_data.syn_ack.clone(),
+            mtu: profile.raw_data.mtu.clone(),
+            link_type: profile.link_type.clone(),
+            likely_vpn: profile.likely_vpn,
+            uptime: profile.raw_data.uptime.clone(),
+            http_request,
+            http_response: profile.raw_data.http_response.clone(),
+            tls_client,
+            consistency: profile.consistency.clone(),
+            nat: profile.nat.clone(),
+            risk: profile.risk.clone(),
+            hops: profile.hops.clone(),
+            last_reboot_estimate: profile.last_reboot_estimate,
+            uptime_history: profile.uptime_history.clone(),
+            metadata: profile.metadata.clone(),
+        }
+    }
+}
+
+/// Response for the v2 profiles list endpoint
+#[derive(Serialize)]
+pub struct ProfilesResponseV2 {
+    pub profiles: OrderedProfiles<ProfileV2>,
+    pub count: usize,
+    pub timestamp: chrono::DateTime<chrono::Utc>,
+}
+
+/// Get all traffic profiles
+/// GET /api/v2/profiles
+pub async fn get_profiles(
+    State(state): State<AppState>,
+    Query(query): Query<ProfileQuery>,
+) -> Result<Json<ProfilesResponseV2>> {
+    let all_profiles = state.query_profiles(query.store_filter()).await?;
+    let profiles = OrderedProfiles(
+        query
+            .select(&all_profiles)
+            .into_iter()
+            .map(|(key, profile)| (key.clone(), ProfileV2::from(profile)))
+            .collect(),
+    );
+
+    Ok(Json(ProfilesResponseV2 {
+        count: profiles.len(),
+        profiles,
+        timestamp: chrono::Utc::now(),
+    }))
+}
+
+/// Get a specific traffic profile
+/// GET /api/v2/profiles/{key}
+pub async fn get_profile(
+    State(state): State<AppState>,
+    Path(key): Path<String>,
+) -> Result<Json<ProfileV2>> {
+    state
+        .query_profile(&key)
+        .await?
+        .map(|profile| Json(ProfileV2::from(&profile)))
+        .ok_or_else(|| ApiError::not_found(format!("Profile not found: {}", key)))
+}
+
+/// Query the profile store by IP/CIDR, time range and fingerprints
+/// GET /api/v2/store/profiles?cidr=10.0.0.0/8&since=...&ja4=...
+///
+/// Reads the store directly, so with a persistent store this also returns
+/// profiles from earlier runs.
+pub async fn query_store(
+    State(state): State<AppState>,
+    Query(filter): Query<ProfileFilter>,
+) -> Result<Json<ProfilesResponseV2>> {
+    let store = state
+        .store
+        .clone()
+        .ok_or_else(|| ApiError::not_found("No profile store is configured"))?;
+
+    // SQLite calls block, so keep them off the async workers
+    let matching = tokio::task::spawn_blocking(move || store.query(&filter))
+        .await
+        .map_err(|e| ApiError::internal(format!("Store query failed: {}", e)))??;
+
+    let profiles = OrderedProfiles(
+        matching
+            .iter()
+            .map(|(key, profile)| (key.clone(), ProfileV2::from(profile)))
+            .collect(),
+    );
+
+    Ok(Json(ProfilesResponseV2 {
+        count: profiles.len(),
+        profiles,
+        timestamp: chrono::Utc::now(),
+    }))
+}
@@ -265,7 +265,7 @@ pub enum ServerMessage {
 
     #[serde(rename = "profile_update")]
     ProfileUpdate {
-        update: ProfileUpdate,
+        update: Box<ProfileUpdate>,
         stats: crate::state::ProfileStats,
         timestamp: chrono::DateTime<chrono::Utc>,
     },
@@ -10,6 +10,10 @@ struct Args {
     #[arg(short = 'i', long, default_value = "lo")]
     interface: String,
 
+    /// Replay a pcap/pcapng file instead of capturing live (runs until end of file)
+    #[arg(short = 'r', long)]
+    pcap: Option<std::path::PathBuf>,
+
     /// How long to run the collector (in seconds)
     #[arg(short = 'd', long, default_value = "30")]
     duration: u64,
@@ -38,11 +42,21 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     }
 
     info!("Starting Huginn Collector example");
-    info!("Interface: {}", args.interface);
-    info!("Duration: {} seconds", args.duration);
+    match &args.pcap {
+        Some(path) => info!("Pcap file: {}", path.display()),
+        None => {
+            info!("Interface: {}", args.interface);
+            info!("Duration: {} seconds", args.duration);
+        }
+    }
     info!("Min quality: {}", args.min_quality);
 
-    let collector = NetworkCollectorBuilder::new(args.interface)
+    let builder = match args.pcap.clone() {
+        Some(path) => NetworkCollectorBuilder::from_pcap(path),
+        None => NetworkCollectorBuilder::new(args.interface),
+    };
+
+    let collector = builder
         .min_quality(args.min_quality)
         .buffer_size(100)
         .channel_buffer_size(1000)
@@ -55,12 +69,27 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let handle = collector.start()?;
 
     info!("Collector started successfully");
-    info!(
-        "Monitoring network traffic for {} seconds...",
-        args.duration
-    );
 
-    sleep(Duration::from_secs(args.duration)).await;
+    if args.pcap.is_some() {
+        info!("Replaying capture file...");
+        handle.wait_for_capture().await?;
+
+        for (key, profile) in handle.get_profiles().await? {
+            info!("{} - {}", key, profile.summary());
+        }
+        info!(
+            "Replay finished: {} profiles, {} connections",
+            handle.get_profile_count().await?,
+            handle.get_connections().await?.len()
+        );
+    } else {
+        info!(
+            "Monitoring network traffic for {} seconds...",
+            args.duration
+        );
+
+        sleep(Duration::from_secs(args.duration)).await;
+    }
 
     info!("Stopping collector...");
 
@@ -1,69 +1,331 @@
 use crate::error::{CollectorError, Result};
 use huginn_net::fingerprint_result::FingerprintResult;
-use std::sync::mpsc;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{mpsc, Arc};
+use std::time::Duration;
 use tokio::sync::mpsc as async_mpsc;
+use tokio::sync::mpsc::error::TrySendError;
 use tracing::{debug, error, warn};
 
+/// How often an idle bridge checks whether it was asked to stop
+const STOP_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// How often a bridge holding back results retries the async channel
+const RETRY_INTERVAL: Duration = Duration::from_millis(5);
+
+/// What the bridge does with a result when the async channel is full
+///
+/// huginn-net sends on an unbounded channel, so only `Block` lets results
+/// pile up in memory; the other policies drain it and shed load instead.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OverflowPolicy {
+    /// Wait for room, never dropping a result
+    #[default]
+    Block,
+    /// Drop the result that just arrived
+    DropNewest,
+    /// Hold results back in a buffer the size of the channel, dropping the
+    /// oldest held-back result when it is full
+    DropOldest,
+    /// Forward one in every N results that arrive while the channel is full;
+    /// the sampled result waits for room, replacing an older one still waiting
+    Sample(u32),
+}
+
+impl fmt::Display for OverflowPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OverflowPolicy::Block => write!(f, "block"),
+            OverflowPolicy::DropNewest => write!(f, "drop-newest"),
+            OverflowPolicy::DropOldest => write!(f, "drop-oldest"),
+            OverflowPolicy::Sample(every) => write!(f, "sample:{}", every),
+        }
+    }
+}
+
+impl FromStr for OverflowPolicy {
+    type Err = String;
+
+    /// Parse `block`, `drop-newest`, `drop-oldest` or `sample:<N>`
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "block" => Ok(OverflowPolicy::Block),
+            "drop-newest" => Ok(OverflowPolicy::DropNewest),
+            "drop-oldest" => Ok(OverflowPolicy::DropOldest),
+            _ => match s.strip_prefix("sample:").map(str::parse) {
+                Some(Ok(every)) if every > 0 => Ok(OverflowPolicy::Sample(every)),
+                _ => Err(format!(
+                    "invalid overflow policy '{}' (expected block, drop-newest, drop-oldest or sample:<N>)",
+                    s
+                )),
+            },
+        }
+    }
+}
+
+/// Counters shared between the bridge and whoever reports on it
+#[derive(Debug, Default)]
+pub struct BridgeCounters {
+    received: AtomicU64,
+    forwarded: AtomicU64,
+    dropped: AtomicU64,
+}
+
+impl BridgeCounters {
+    /// Current counter values
+    pub fn snapshot(&self) -> BridgeStats {
+        BridgeStats {
+            received: self.received.load(Ordering::Relaxed),
+            forwarded: self.forwarded.load(Ordering::Relaxed),
+            dropped: self.dropped.load(Ordering::Relaxed),
+        }
+    }
+
+    fn drop_one(&self, policy: OverflowPolicy) {
+        let dropped = self.dropped.fetch_add(1, Ordering::Relaxed) + 1;
+        // Log the 1st, 2nd, 4th, 8th... drop so a sustained overload doesn't flood the log
+        if dropped.is_power_of_two() {
+            warn!(
+                "Bridge dropped {} results so far (async channel full, policy {})",
+                dropped, policy
+            );
+        }
+    }
+}
+
+/// Result counts of the channel bridge
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BridgeStats {
+    /// Results received from huginn-net
+    pub received: u64,
+    /// Results handed to the profile processor
+    pub forwarded: u64,
+    /// Results dropped by the overflow policy
+    pub dropped: u64,
+}
+
 /// Bridge between synchronous and asynchronous channels
 ///
-/// This struct handles the conversion between std::sync::mpsc (used by huginn-net)
-/// and tokio::sync::mpsc (used by async code)
-pub struct ChannelBridge {
-    // Synchronous receiver from huginn-net
-    sync_receiver: mpsc::Receiver<FingerprintResult>,
+/// This struct handles the conversion between std::sync::mpsc (used by the
+/// capture thread) and tokio::sync::mpsc (used by async code)
+pub struct ChannelBridge<T = FingerprintResult> {
+    // Synchronous receiver from the capture thread
+    sync_receiver: mpsc::Receiver<T>,
     // Asynchronous sender to the rest of the application
-    async_sender: async_mpsc::Sender<FingerprintResult>,
+    async_sender: async_mpsc::Sender<T>,
+    // Set to make the bridge stop and close the synchronous receiver
+    stop: Arc<AtomicBool>,
+    // What to do when the async channel is full
+    policy: OverflowPolicy,
+    // Received/forwarded/dropped counts
+    counters: Arc<BridgeCounters>,
 }
 
-impl ChannelBridge {
+impl<T: Send + 'static> ChannelBridge<T> {
     /// Create a new channel bridge
-    pub fn new(
-        sync_receiver: mpsc::Receiver<FingerprintResult>,
-        async_sender: async_mpsc::Sender<FingerprintResult>,
-    ) -> Self {
+    pub fn new(sync_receiver: mpsc::Receiver<T>, async_sender: async_mpsc::Sender<T>) -> Self {
         Self {
             sync_receiver,
             async_sender,
+            stop: Arc::new(AtomicBool::new(false)),
+            policy: OverflowPolicy::default(),
+            counters: Arc::new(BridgeCounters::default()),
         }
     }
 
+    /// Set what the bridge does when the async channel is full
+    pub fn with_overflow_policy(mut self, policy: OverflowPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Count into the given counters (e.g. to keep totals across bridges)
+    pub fn with_counters(mut self, counters: Arc<BridgeCounters>) -> Self {
+        self.counters = counters;
+        self
+    }
+
+    /// Counters of this bridge
+    pub fn counters(&self) -> Arc<BridgeCounters> {
+        self.counters.clone()
+    }
+
+    /// Flag that stops the bridge once set
+    ///
+    /// Stopping the bridge drops the synchronous receiver, so huginn-net's next
+    /// send fails and its capture loop returns.
+    pub fn stop_signal(&self) -> Arc<AtomicBool> {
+        self.stop.clone()
+    }
+
     /// Start the bridge in a blocking thread
     ///
     /// This method will block the current thread and continuously forward
-    /// messages from the synchronous receiver to the asynchronous sender
+    /// messages from the synchronous receiver to the asynchronous sender,
+    /// until the sender disconnects or the stop signal is set. Results that
+    /// don't fit in the async channel are handled by the overflow policy.
+    ///
+    /// The async channel closing is an error while the bridge runs, and a
+    /// normal end once it was asked to stop.
     pub fn start_blocking(self) -> Result<()> {
-        debug!("Starting channel bridge in blocking mode");
+        debug!(
+            "Starting channel bridge in blocking mode (overflow policy {})",
+            self.policy
+        );
 
-        loop {
-            match self.sync_receiver.recv() {
+        // Results held back under `DropOldest` and `Sample`
+        let mut held = VecDeque::new();
+        let forwarded = self.forward(&mut held);
+
+        // Asked to stop: whatever is still held back never reaches the processor
+        for _ in held.drain(..) {
+            self.counters.drop_one(self.policy);
+        }
+
+        match forwarded {
+            Err(CollectorError::Channel(_)) if self.stop.load(Ordering::Relaxed) => {
+                debug!("Async channel closed after the bridge was asked to stop");
+            }
+            Err(e) => {
+                error!("Bridge failed to send to async channel: {}", e);
+                return Err(e);
+            }
+            Ok(()) => {}
+        }
+
+        debug!("Channel bridge stopped");
+        Ok(())
+    }
+
+    /// Forward results until the input ends or the stop signal is set
+    fn forward(&self, held: &mut VecDeque<T>) -> Result<()> {
+        // Overflowing results since the last one `Sample` forwarded
+        let mut overflowed = 0u64;
+
+        while !self.stop.load(Ordering::Relaxed) {
+            self.flush(held)?;
+            let wait = if held.is_empty() {
+                STOP_POLL_INTERVAL
+            } else {
+                RETRY_INTERVAL
+            };
+
+            match self.sync_receiver.recv_timeout(wait) {
                 Ok(result) => {
                     debug!("Bridge received fingerprint result");
-
-                    // Try to send to async channel
-                    match self.async_sender.blocking_send(result) {
-                        Ok(_) => {
-                            debug!("Bridge forwarded result to async channel");
-                        }
-                        Err(e) => {
-                            error!("Bridge failed to send to async channel: {}", e);
-                            return Err(CollectorError::channel(format!(
-                                "Failed to send to async channel: {}",
-                                e
-                            )));
-                        }
-                    }
+                    self.counters.received.fetch_add(1, Ordering::Relaxed);
+                    self.offer(result, held, &mut overflowed)?;
                 }
+                Err(mpsc::RecvTimeoutError::Timeout) => {}
                 Err(e) => {
                     warn!("Bridge sync receiver disconnected: {}", e);
+                    // The capture is over; hand over what is left before closing
+                    while let Some(result) = held.pop_front() {
+                        self.send_blocking(result)?;
+                    }
                     break;
                 }
             }
         }
+        Ok(())
+    }
 
-        debug!("Channel bridge stopped");
+    /// Apply the overflow policy to a freshly received result
+    fn offer(&self, result: T, held: &mut VecDeque<T>, overflowed: &mut u64) -> Result<()> {
+        match self.policy {
+            OverflowPolicy::Block => self.send_blocking(result),
+            OverflowPolicy::DropNewest => {
+                if let Some(_result) = self.try_send(result)? {
+                    self.counters.drop_one(self.policy);
+                }
+                Ok(())
+            }
+            OverflowPolicy::DropOldest => {
+                // Keep arrival order: nothing overtakes a held-back result
+                let result = if held.is_empty() {
+                    self.try_send(result)?
+                } else {
+                    Some(result)
+                };
+                if let Some(result) = result {
+                    held.push_back(result);
+                    if held.len() > self.async_sender.max_capacity() {
+                        held.pop_front();
+                        self.counters.drop_one(self.policy);
+                    }
+                }
+                Ok(())
+            }
+            OverflowPolicy::Sample(every) => {
+                // Keep arrival order: nothing overtakes a held-back sample
+                let result = if held.is_empty() {
+                    self.try_send(result)?
+                } else {
+                    Some(result)
+                };
+                if let Some(result) = result {
+                    *overflowed += 1;
+                    if *overflowed >= u64::from(every) {
+                        *overflowed = 0;
+                        // Never block: the sample waits for room like a held-back result
+                        if held.pop_front().is_some() {
+                            self.counters.drop_one(self.policy);
+                        }
+                        held.push_back(result);
+                    } else {
+                        self.counters.drop_one(self.policy);
+                    }
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// Forward held-back results while the async channel has room
+    fn flush(&self, held: &mut VecDeque<T>) -> Result<()> {
+        while let Some(result) = held.pop_front() {
+            if let Some(result) = self.try_send(result)? {
+                held.push_front(result);
+                break;
+            }
+        }
         Ok(())
     }
 
+    /// Forward without waiting, handing the result back if the channel is full
+    fn try_send(&self, result: T) -> Result<Option<T>> {
+        match self.async_sender.try_send(result) {
+            Ok(_) => {
+                debug!("Bridge forwarded result to async channel");
+                self.counters.forwarded.fetch_add(1, Ordering::Relaxed);
+                Ok(None)
+            }
+            Err(TrySendError::Full(result)) => Ok(Some(result)),
+            Err(TrySendError::Closed(_)) => Err(Self::closed()),
+        }
+    }
+
+    /// Forward, waiting for room in the async channel
+    fn send_blocking(&self, result: T) -> Result<()> {
+        match self.async_sender.blocking_send(result) {
+            Ok(_) => {
+                debug!("Bridge forwarded result to async channel");
+                self.counters.forwarded.fetch_add(1, Ordering::Relaxed);
+                Ok(())
+            }
+            Err(_) => Err(Self::closed()),
+        }
+    }
+
+    fn closed() -> CollectorError {
+        CollectorError::channel("Failed to send to async channel: channel closed")
+    }
+
     /// Start the bridge in a separate thread
     ///
     /// This method spawns a new thread and starts the bridge there,
@@ -80,16 +342,12 @@ impl ChannelBridge {
 /// Create a channel bridge with the specified buffer size
 ///
 /// Returns:
-/// - sync_sender: Send end for huginn-net to use
+/// - sync_sender: Send end for the capture thread to use
 /// - async_receiver: Receive end for async code to use
 /// - bridge: The bridge that needs to be started
-pub fn create_bridge(
+pub fn create_bridge<T: Send + 'static>(
     buffer_size: usize,
-) -> (
-    mpsc::Sender<FingerprintResult>,
-    async_mpsc::Receiver<FingerprintResult>,
-    ChannelBridge,
-) {
+) -> (mpsc::Sender<T>, async_mpsc::Receiver<T>, ChannelBridge<T>) {
     // Create synchronous channel
     let (sync_sender, sync_receiver) = mpsc::channel();
 
@@ -141,7 +399,7 @@ mod tests {
 
     #[tokio::test]
     async fn test_bridge_handles_sender_drop() {
-        let (sync_sender, mut async_receiver, bridge) = create_bridge(10);
+        let (sync_sender, mut async_receiver, bridge) = create_bridge::<FingerprintResult>(10);
 
         // Start bridge in separate thread
         let handle = bridge.start_in_thread().unwrap();
@@ -157,4 +415,152 @@ mod tests {
         let received = async_receiver.recv().await;
         assert!(received.is_none());
     }
+
+    #[test]
+    fn test_bridge_stops_on_signal() {
+        let (sync_sender, _async_receiver, bridge) = create_bridge(10);
+        let stop = bridge.stop_signal();
+        let handle = bridge.start_in_thread().unwrap();
+
+        stop.store(true, Ordering::Relaxed);
+        assert!(handle.join().unwrap().is_ok());
+
+        // huginn-net sees a closed channel on its next send
+        let result = FingerprintResult {
+            syn: None,
+            syn_ack: None,
+            mtu: None,
+            uptime: None,
+            http_request: None,
+            http_response: None,
+            tls_client: None,
+        };
+        assert!(sync_sender.send(result).is_err());
+    }
+
+    fn empty_result() -> FingerprintResult {
+        FingerprintResult {
+            syn: None,
+            syn_ack: None,
+            mtu: None,
+            uptime: None,
+            http_request: None,
+            http_response: None,
+            tls_client: None,
+        }
+    }
+
+    /// Push `count` results through a bridge whose consumer only reads once the input closes
+    fn overflow(policy: OverflowPolicy, count: usize) -> (BridgeStats, usize) {
+        let (sync_sender, mut async_receiver, bridge) = create_bridge(2);
+        let bridge = bridge.with_overflow_policy(policy);
+        let counters = bridge.counters();
+        let handle = bridge.start_in_thread().unwrap();
+
+        for _ in 0..count {
+            sync_sender.send(empty_result()).unwrap();
+        }
+        // A blocking bridge waits for the consumer instead of draining its input
+        while policy != OverflowPolicy::Block && counters.snapshot().received < count as u64 {
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        drop(sync_sender);
+
+        let mut delivered = 0;
+        while async_receiver.blocking_recv().is_some() {
+            delivered += 1;
+        }
+        assert!(handle.join().unwrap().is_ok());
+        (counters.snapshot(), delivered)
+    }
+
+    #[test]
+    fn test_overflow_policies_count_drops() {
+        // 2 in the channel, the other 8 dropped
+        let (stats, delivered) = overflow(OverflowPolicy::DropNewest, 10);
+        assert_eq!((stats.received, stats.forwarded, stats.dropped), (10, 2, 8));
+        assert_eq!(delivered, 2);
+
+        // 2 in the channel, 2 held back, the 6 oldest held-back results dropped
+        let (stats, delivered) = overflow(OverflowPolicy::DropOldest, 10);
+        assert_eq!((stats.received, stats.forwarded, stats.dropped), (10, 4, 6));
+        assert_eq!(delivered, 4);
+
+        // 2 in the channel; every 3rd overflowing result is held back, the
+        // newer sample replacing the older one, and delivered once input closes
+        let (stats, delivered) = overflow(OverflowPolicy::Sample(3), 10);
+        assert_eq!((stats.received, stats.forwarded, stats.dropped), (10, 3, 7));
+        assert_eq!(delivered, 3);
+
+        let (stats, delivered) = overflow(OverflowPolicy::Block, 10);
+        assert_eq!((stats.forwarded, stats.dropped), (10, 0));
+        assert_eq!(delivered, 10);
+    }
+
+    #[test]
+    fn test_held_back_results_count_as_dropped_on_stop() {
+        for policy in [OverflowPolicy::DropOldest, OverflowPolicy::Sample(1)] {
+            let (sync_sender, _async_receiver, bridge) = create_bridge(2);
+            let bridge = bridge.with_overflow_policy(policy);
+            let counters = bridge.counters();
+            let stop = bridge.stop_signal();
+            let handle = bridge.start_in_thread().unwrap();
+
+            for _ in 0..5 {
+                sync_sender.send(empty_result()).unwrap();
+            }
+            while counters.snapshot().received < 5 {
+                std::thread::sleep(Duration::from_millis(1));
+            }
+            stop.store(true, Ordering::Relaxed);
+            assert!(handle.join().unwrap().is_ok());
+
+            let stats = counters.snapshot();
+            assert_eq!(stats.forwarded, 2, "{}", policy);
+            assert_eq!(
+                stats.received,
+                stats.forwarded + stats.dropped,
+                "{}",
+                policy
+            );
+        }
+    }
+
+    #[test]
+    fn test_closed_channel_is_only_an_error_while_running() {
+        for stopping in [false, true] {
+            let (sync_sender, async_receiver, bridge) = create_bridge(2);
+            let counters = bridge.counters();
+            let stop = bridge.stop_signal();
+            let handle = bridge.start_in_thread().unwrap();
+
+            // Two results fill the channel, the third keeps the bridge waiting for room
+            for _ in 0..3 {
+                sync_sender.send(empty_result()).unwrap();
+            }
+            while counters.snapshot().received < 3 {
+                std::thread::sleep(Duration::from_millis(1));
+            }
+            if stopping {
+                stop.store(true, Ordering::Relaxed);
+            }
+            drop(async_receiver);
+
+            assert_eq!(handle.join().unwrap().is_ok(), stopping);
+        }
+    }
+
+    #[test]
+    fn test_overflow_policy_parsing() {
+        for policy in [
+            OverflowPolicy::Block,
+            OverflowPolicy::DropNewest,
+            OverflowPolicy::DropOldest,
+            OverflowPolicy::Sample(10),
+        ] {
+            assert_eq!(policy.to_string().parse::<OverflowPolicy>(), Ok(policy));
+        }
+        assert!("sample:0".parse::<OverflowPolicy>().is_err());
+        assert!("drop".parse::<OverflowPolicy>().is_err());
+    }
 }
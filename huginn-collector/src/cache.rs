@@ -0,0 +1,104 @@
+use chrono::{DateTime, Utc};
+use huginn_core::{ConnectionObservation, TrafficProfile};
+use std::collections::HashMap;
+
+/// Cache entry that can be aged out
+pub(crate) trait Idle {
+    /// When the entry was last updated
+    fn last_updated(&self) -> DateTime<Utc>;
+}
+
+impl Idle for TrafficProfile {
+    fn last_updated(&self) -> DateTime<Utc> {
+        self.metadata.last_updated
+    }
+}
+
+impl Idle for ConnectionObservation {
+    fn last_updated(&self) -> DateTime<Utc> {
+        self.last_updated
+    }
+}
+
+/// Remove the least recently updated entries until at most `max` remain
+///
+/// Returns the removed keys.
+pub(crate) fn evict_lru<T: Idle>(entries: &mut HashMap<String, T>, max: usize) -> Vec<String> {
+    let excess = entries.len().saturating_sub(max);
+    if excess == 0 {
+        return Vec::new();
+    }
+
+    let mut by_age: Vec<(DateTime<Utc>, &String)> = entries
+        .iter()
+        .map(|(key, entry)| (entry.last_updated(), key))
+        .collect();
+    by_age.select_nth_unstable(excess - 1);
+    let evicted: Vec<String> = by_age[..excess]
+        .iter()
+        .map(|(_, key)| (*key).clone())
+        .collect();
+
+    for key in &evicted {
+        entries.remove(key);
+    }
+    evicted
+}
+
+/// Remove entries last updated before `cutoff`
+///
+/// Returns the removed keys.
+pub(crate) fn expire_idle<T: Idle>(
+    entries: &mut HashMap<String, T>,
+    cutoff: DateTime<Utc>,
+) -> Vec<String> {
+    let mut expired = Vec::new();
+    entries.retain(|key, entry| {
+        let keep = entry.last_updated() >= cutoff;
+        if !keep {
+            expired.push(key.clone());
+        }
+        keep
+    });
+    expired
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn profiles(ages: &[(&str, i64)]) -> HashMap<String, TrafficProfile> {
+        ages.iter()
+            .map(|(ip, secs)| {
+                let seen = Utc.timestamp_opt(1_700_000_000 + secs, 0).unwrap();
+                (
+                    ip.to_string(),
+                    TrafficProfile::new_at(ip.parse().unwrap(), 40001, seen),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_lru_evicts_least_recently_updated() {
+        let mut cache = profiles(&[("10.0.0.1", 30), ("10.0.0.2", 10), ("10.0.0.3", 20)]);
+
+        assert!(evict_lru(&mut cache, 3).is_empty());
+        assert_eq!(evict_lru(&mut cache, 2), ["10.0.0.2"]);
+
+        let mut evicted = evict_lru(&mut cache, 0);
+        evicted.sort();
+        assert_eq!(evicted, ["10.0.0.1", "10.0.0.3"]);
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn test_idle_entries_expire() {
+        let mut cache = profiles(&[("10.0.0.1", 30), ("10.0.0.2", 10)]);
+
+        let cutoff = Utc.timestamp_opt(1_700_000_020, 0).unwrap();
+        assert_eq!(expire_idle(&mut cache, cutoff), ["10.0.0.2"]);
+        assert!(cache.contains_key("10.0.0.1"));
+    }
+}
@@ -0,0 +1,260 @@
+use crate::config::CaptureSource;
+use chrono::{DateTime, Utc};
+use huginn_core::{WireDetails, WireTracker};
+use huginn_net::fingerprint_result::FingerprintResult;
+use huginn_net::{db::Database, HuginnNet};
+use pcap_file::pcap::PcapReader;
+use pcap_file::pcapng::{Block, PcapNgReader};
+use pnet_datalink::{Channel, DataLinkReceiver};
+use std::fs::File;
+use std::io::{self, Read, Seek};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc;
+use std::time::Duration;
+
+/// Longest a live capture waits for a packet before checking its stop flag
+pub(crate) const READ_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// Type of the section header block every pcapng file starts with
+const PCAPNG_MAGIC: [u8; 4] = [0x0a, 0x0d, 0x0d, 0x0a];
+
+/// A huginn-net result and the details of the packet it came from
+pub(crate) struct Captured {
+    pub(crate) result: FingerprintResult,
+    pub(crate) wire: WireDetails,
+    /// When the packet was recorded, for packets read from a file
+    ///
+    /// Live packets have none; they are stamped when they reach the collector.
+    pub(crate) captured_at: Option<DateTime<Utc>>,
+}
+
+impl From<FingerprintResult> for Captured {
+    fn from(result: FingerprintResult) -> Self {
+        Self {
+            result,
+            wire: WireDetails::default(),
+            captured_at: None,
+        }
+    }
+}
+
+/// One run of a packet capture, as driven by the capture supervisor
+///
+/// A live capture never waits for a packet longer than [`READ_TIMEOUT`], so it
+/// notices `stop` even on an interface without traffic.
+pub(crate) trait Capture: Send + Sync {
+    /// Capture from `source` until it ends, fails, `stop` is set or the channel is closed
+    fn run(
+        &self,
+        source: &CaptureSource,
+        database: &Database,
+        buffer_size: usize,
+        sender: mpsc::Sender<Captured>,
+        stop: &AtomicBool,
+    ) -> std::result::Result<(), String>;
+}
+
+/// Captures with huginn-net
+///
+/// Frames are read here and handed to huginn-net one at a time, so each
+/// result can carry what huginn-net 1.4 parses but does not report.
+#[derive(Debug, Default)]
+pub(crate) struct HuginnCapture;
+
+impl Capture for HuginnCapture {
+    fn run(
+        &self,
+        source: &CaptureSource,
+        database: &Database,
+        buffer_size: usize,
+        sender: mpsc::Sender<Captured>,
+        stop: &AtomicBool,
+    ) -> std::result::Result<(), String> {
+        let mut analyzer = FrameAnalyzer {
+            huginn: HuginnNet::new(Some(database), buffer_size, None),
+            tracker: WireTracker::default(),
+            sender,
+            stop,
+        };
+        match source {
+            CaptureSource::Interface(interface) => analyzer.capture_interface(interface),
+            CaptureSource::PcapFile(path) => analyzer.replay_file(path),
+        }
+    }
+}
+
+/// Runs huginn-net and the wire tracker over the frames of one capture
+struct FrameAnalyzer<'a> {
+    huginn: HuginnNet<'a>,
+    tracker: WireTracker,
+    sender: mpsc::Sender<Captured>,
+    stop: &'a AtomicBool,
+}
+
+impl FrameAnalyzer<'_> {
+    /// Analyze one frame; `false` once the result channel is closed
+    fn analyze(&mut self, frame: &[u8], captured_at: Option<DateTime<Utc>>) -> bool {
+        let wire = self.tracker.observe(frame);
+        let result = self.huginn.analyze_tcp(frame);
+        let captured = Captured {
+            result,
+            wire,
+            captured_at,
+        };
+        if self.sender.send(captured).is_err() {
+            tracing::debug!("Result channel closed, stopping packet processing");
+            return false;
+        }
+        true
+    }
+
+    fn capture_interface(&mut self, interface: &str) -> std::result::Result<(), String> {
+        let interface = pnet_datalink::interfaces()
+            .into_iter()
+            .find(|candidate| candidate.name == interface)
+            .ok_or_else(|| format!("Could not find network interface: {}", interface))?;
+        tracing::debug!("Using network interface: {}", interface.name);
+
+        let config = pnet_datalink::Config {
+            read_timeout: Some(READ_TIMEOUT),
+            promiscuous: true,
+            ..Default::default()
+        };
+        let mut receiver = match pnet_datalink::channel(&interface, config) {
+            Ok(Channel::Ethernet(_, receiver)) => receiver,
+            Ok(_) => return Err("Unhandled channel type".to_string()),
+            Err(e) => return Err(format!("Unable to create channel: {}", e)),
+        };
+        self.read_frames(receiver.as_mut());
+        Ok(())
+    }
+
+    /// Analyze frames from a live receiver until asked to stop
+    fn read_frames(&mut self, receiver: &mut dyn DataLinkReceiver) {
+        while !self.stop.load(Ordering::Relaxed) {
+            match receiver.next() {
+                Ok(frame) => {
+                    if !self.analyze(frame, None) {
+                        return;
+                    }
+                }
+                Err(e) if e.kind() == io::ErrorKind::TimedOut => {}
+                Err(e) => tracing::error!("Failed to read packet: {}", e),
+            }
+        }
+        tracing::debug!("Capture asked to stop");
+    }
+
+    /// Replay a pcap or pcapng file, told apart by its first bytes
+    fn replay_file(&mut self, path: &std::path::Path) -> std::result::Result<(), String> {
+        let mut file = File::open(path).map_err(|e| e.to_string())?;
+        let mut magic = [0u8; 4];
+        file.read_exact(&mut magic).map_err(|e| e.to_string())?;
+        file.rewind().map_err(|e| e.to_string())?;
+
+        if magic == PCAPNG_MAGIC {
+            self.replay_pcapng(file)
+        } else {
+            self.replay_pcap(file)
+        }
+    }
+
+    fn replay_pcap(&mut self, file: File) -> std::result::Result<(), String> {
+        let mut reader = PcapReader::new(file).map_err(|e| e.to_string())?;
+
+        while let Some(packet) = reader.next_packet() {
+            if self.stop.load(Ordering::Relaxed) {
+                break;
+            }
+            match packet {
+                Ok(packet) => {
+                    if !self.analyze(&packet.data, recorded_at(packet.timestamp)) {
+                        break;
+                    }
+                }
+                // The reader doesn't move past a bad record, e.g. a truncated tail
+                Err(e) => {
+                    tracing::warn!("Stopping replay at an unreadable packet: {}", e);
+                    break;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn replay_pcapng(&mut self, file: File) -> std::result::Result<(), String> {
+        let mut reader = PcapNgReader::new(file).map_err(|e| e.to_string())?;
+
+        while let Some(block) = reader.next_block() {
+            if self.stop.load(Ordering::Relaxed) {
+                break;
+            }
+            let analyzed = match block {
+                Ok(Block::EnhancedPacket(packet)) => {
+                    self.analyze(&packet.data, recorded_at(packet.timestamp))
+                }
+                // Simple packet blocks carry no timestamp
+                Ok(Block::SimplePacket(packet)) => self.analyze(&packet.data, None),
+                Ok(_) => true,
+                Err(e) => {
+                    tracing::warn!("Stopping replay at an unreadable block: {}", e);
+                    break;
+                }
+            };
+            if !analyzed {
+                break;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Time a packet was recorded, from its offset since the Unix epoch
+fn recorded_at(timestamp: Duration) -> Option<DateTime<Utc>> {
+    DateTime::from_timestamp(
+        i64::try_from(timestamp.as_secs()).unwrap_or(i64::MAX),
+        timestamp.subsec_nanos(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+
+    /// A receiver on an interface without traffic
+    struct SilentReceiver;
+
+    impl DataLinkReceiver for SilentReceiver {
+        fn next(&mut self) -> io::Result<&[u8]> {
+            std::thread::sleep(READ_TIMEOUT);
+            Err(io::ErrorKind::TimedOut.into())
+        }
+    }
+
+    #[test]
+    fn test_live_capture_without_packets_stops_on_request() {
+        let database = crate::signatures::load_database(None).unwrap();
+        let (sender, _receiver) = mpsc::channel();
+        let stop = AtomicBool::new(false);
+
+        std::thread::scope(|scope| {
+            let capture = scope.spawn(|| {
+                FrameAnalyzer {
+                    huginn: HuginnNet::new(Some(&database), 100, None),
+                    tracker: WireTracker::default(),
+                    sender,
+                    stop: &stop,
+                }
+                .read_frames(&mut SilentReceiver)
+            });
+            std::thread::sleep(READ_TIMEOUT * 2);
+            assert!(!capture.is_finished());
+
+            let stopped = Instant::now();
+            stop.store(true, Ordering::Relaxed);
+            capture.join().unwrap();
+            assert!(stopped.elapsed() < READ_TIMEOUT * 3);
+        });
+    }
+}
@@ -1,79 +1,220 @@
-use crate::bridge::create_bridge;
-use crate::config::CollectorConfig;
+use crate::bridge::{create_bridge, BridgeCounters, BridgeStats, OverflowPolicy};
+use crate::cache::{evict_lru, expire_idle};
+use crate::capture::{Capture, Captured, HuginnCapture};
+use crate::config::{CaptureSource, CollectorConfig};
 use crate::error::{CollectorError, Result};
-use huginn_core::{HuginnAnalyzer, LoggingEventHandler, TrafficProfile};
-use huginn_net::fingerprint_result::FingerprintResult;
-use huginn_net::{db::Database, HuginnNet};
-use std::collections::HashMap;
-use tokio::sync::{mpsc as async_mpsc, oneshot};
+use crate::signatures::{load_database, SignatureReload};
+use crate::sqlite::SqliteStore;
+use crate::store::{MemoryStore, ProfileChanges, ProfileStore};
+use crate::supervisor::{
+    sleep_unless_stopped, Backoff, CollectorStatus, RestartPolicy, StatusCell,
+};
+use chrono::{DateTime, Utc};
+use huginn_core::{
+    Clock, ConnectionObservation, HostSignals, HuginnAnalyzer, Ja4Database, LinkTable,
+    LoggingEventHandler, ProfileEnricher, TrafficProfile,
+};
+use huginn_net::db::Database;
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::{mpsc as async_mpsc, oneshot, watch};
 use tokio::task::JoinHandle;
+use tokio::time::Instant;
 use tracing::{debug, error, info, warn};
 
 /// Commands that can be sent to the collector
 #[derive(Debug)]
 pub enum CollectorCommand {
-    /// Get all profiles
-    GetProfiles(oneshot::Sender<HashMap<String, TrafficProfile>>),
-    /// Get a specific profile by key
-    GetProfile(String, oneshot::Sender<Option<TrafficProfile>>),
-    /// Get profile count
-    GetProfileCount(oneshot::Sender<usize>),
-    /// Clear all profiles
+    /// Get all per-connection observations
+    GetConnections(oneshot::Sender<HashMap<String, ConnectionObservation>>),
+    /// Get the connection observations updated at or after a time
+    GetConnectionsSince(
+        DateTime<Utc>,
+        oneshot::Sender<Vec<(String, ConnectionObservation)>>,
+    ),
+    /// Get the keys of all connection observations
+    GetConnectionKeys(oneshot::Sender<Vec<String>>),
+    /// Get a specific connection observation by key
+    GetConnection(String, oneshot::Sender<Option<ConnectionObservation>>),
+    /// Clear all profiles (and their connection observations)
     ClearProfiles,
+    /// Reload the signature database and restart the capture with it
+    ReloadSignatures(oneshot::Sender<Result<SignatureReload>>),
+}
+
+/// How often `stop()` checks whether a capture thread has finished
+const THREAD_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Threads of one capture generation (huginn-net analyzer and channel bridge)
+struct CaptureThreads {
+    analyzer_handle: std::thread::JoinHandle<()>,
+    bridge_handle: std::thread::JoinHandle<Result<()>>,
+    /// Stops the bridge and the capture
+    stop: Arc<AtomicBool>,
+}
+
+impl CaptureThreads {
+    /// Ask the generation to stop
+    ///
+    /// The bridge stops right away, the capture within its read timeout.
+    /// Call this before the receiving end goes away, so the bridge does not
+    /// take the closed channel for a failure.
+    fn request_stop(&self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+
+    /// Wait for both threads, giving up at `deadline`
+    async fn join(self, deadline: Instant) -> Result<()> {
+        join_thread(self.bridge_handle, deadline, "Channel bridge").await??;
+        join_thread(self.analyzer_handle, deadline, "Huginn-net capture").await
+    }
+}
+
+/// Join a thread without blocking the runtime, giving up at `deadline`
+async fn join_thread<T>(
+    handle: std::thread::JoinHandle<T>,
+    deadline: Instant,
+    name: &str,
+) -> Result<T> {
+    while !handle.is_finished() {
+        if Instant::now() >= deadline {
+            return Err(CollectorError::shutdown(format!(
+                "{} thread did not stop in time",
+                name
+            )));
+        }
+        tokio::time::sleep(THREAD_POLL_INTERVAL).await;
+    }
+
+    handle.join().map_err(|e| {
+        error!("{} thread join error: {:?}", name, e);
+        CollectorError::Unknown(format!("{} thread panic: {:?}", name, e))
+    })
+}
+
+/// Run a store call on the blocking thread pool
+///
+/// SQLite calls block, so they must stay off the async workers.
+async fn on_store<T, F>(store: &Arc<dyn ProfileStore>, call: F) -> Result<T>
+where
+    T: Send + 'static,
+    F: FnOnce(&dyn ProfileStore) -> Result<T> + Send + 'static,
+{
+    let store = store.clone();
+    tokio::task::spawn_blocking(move || call(store.as_ref()))
+        .await
+        .map_err(|e| CollectorError::store(format!("Store task failed: {}", e)))?
 }
 
 /// Handle for controlling a running network collector
 pub struct CollectorHandle {
-    /// Handle to the huginn-net analyzer thread
-    analyzer_handle: Option<std::thread::JoinHandle<()>>,
-    /// Handle to the channel bridge thread
-    bridge_handle: Option<std::thread::JoinHandle<Result<()>>>,
+    /// Threads of the current capture generation (replaced on signature reload)
+    capture: Arc<Mutex<Option<CaptureThreads>>>,
     /// Handle to the profile processor task
     processor_handle: Option<JoinHandle<Result<()>>>,
     /// Channel to send shutdown signal
     shutdown_sender: Option<async_mpsc::Sender<()>>,
     /// Channel to send commands to the collector
     command_sender: async_mpsc::Sender<CollectorCommand>,
+    /// Flips to `true` once the capture source is exhausted (end of a pcap file)
+    capture_finished: watch::Receiver<bool>,
+    /// How long `stop()` waits for everything to finish
+    shutdown_timeout: Duration,
+    /// Capture health reported by the supervisor
+    status: Arc<StatusCell>,
+    /// Bridge counters, totalled over every capture generation
+    bridge_counters: Arc<BridgeCounters>,
+    /// Profile store, read directly rather than through the processor
+    store: Arc<dyn ProfileStore>,
 }
 
 impl CollectorHandle {
     /// Get all profiles from the collector
     pub async fn get_profiles(&self) -> Result<HashMap<String, TrafficProfile>> {
+        on_store(&self.store, |store| store.all()).await
+    }
+
+    /// Get the profiles written after change number `sequence` (0 for all of them)
+    pub async fn get_profile_changes(&self, sequence: u64) -> Result<ProfileChanges> {
+        on_store(&self.store, move |store| store.changed_since(sequence)).await
+    }
+
+    /// Get the keys of all profiles
+    pub async fn get_profile_keys(&self) -> Result<Vec<String>> {
+        on_store(&self.store, |store| store.keys()).await
+    }
+
+    /// Get a specific profile by key
+    pub async fn get_profile(&self, key: &str) -> Result<Option<TrafficProfile>> {
+        let key = key.to_string();
+        on_store(&self.store, move |store| store.get(&key)).await
+    }
+
+    /// Get the number of profiles
+    pub async fn get_profile_count(&self) -> Result<usize> {
+        on_store(&self.store, |store| store.len()).await
+    }
+
+    /// Profile store the collector writes to (for IP/CIDR, time and fingerprint queries)
+    pub fn store(&self) -> Arc<dyn ProfileStore> {
+        self.store.clone()
+    }
+
+    /// Get all per-connection observations
+    pub async fn get_connections(&self) -> Result<HashMap<String, ConnectionObservation>> {
         let (tx, rx) = oneshot::channel();
 
         self.command_sender
-            .send(CollectorCommand::GetProfiles(tx))
+            .send(CollectorCommand::GetConnections(tx))
             .await
-            .map_err(|_| CollectorError::channel("Failed to send get_profiles command"))?;
+            .map_err(|_| CollectorError::channel("Failed to send get_connections command"))?;
 
         rx.await
-            .map_err(|_| CollectorError::channel("Failed to receive profiles response"))
+            .map_err(|_| CollectorError::channel("Failed to receive connections response"))
     }
 
-    /// Get a specific profile by key
-    pub async fn get_profile(&self, key: &str) -> Result<Option<TrafficProfile>> {
+    /// Get the connection observations updated at or after `since`
+    pub async fn get_connections_since(
+        &self,
+        since: DateTime<Utc>,
+    ) -> Result<Vec<(String, ConnectionObservation)>> {
         let (tx, rx) = oneshot::channel();
 
         self.command_sender
-            .send(CollectorCommand::GetProfile(key.to_string(), tx))
+            .send(CollectorCommand::GetConnectionsSince(since, tx))
             .await
-            .map_err(|_| CollectorError::channel("Failed to send get_profile command"))?;
+            .map_err(|_| CollectorError::channel("Failed to send get_connections_since command"))?;
 
         rx.await
-            .map_err(|_| CollectorError::channel("Failed to receive profile response"))
+            .map_err(|_| CollectorError::channel("Failed to receive connections response"))
     }
 
-    /// Get the number of profiles
-    pub async fn get_profile_count(&self) -> Result<usize> {
+    /// Get the keys of all connection observations
+    pub async fn get_connection_keys(&self) -> Result<Vec<String>> {
+        let (tx, rx) = oneshot::channel();
+
+        self.command_sender
+            .send(CollectorCommand::GetConnectionKeys(tx))
+            .await
+            .map_err(|_| CollectorError::channel("Failed to send get_connection_keys command"))?;
+
+        rx.await
+            .map_err(|_| CollectorError::channel("Failed to receive connection keys response"))
+    }
+
+    /// Get a specific connection observation by `client-server` key
+    pub async fn get_connection(&self, key: &str) -> Result<Option<ConnectionObservation>> {
         let (tx, rx) = oneshot::channel();
 
         self.command_sender
-            .send(CollectorCommand::GetProfileCount(tx))
+            .send(CollectorCommand::GetConnection(key.to_string(), tx))
             .await
-            .map_err(|_| CollectorError::channel("Failed to send get_profile_count command"))?;
+            .map_err(|_| CollectorError::channel("Failed to send get_connection command"))?;
 
         rx.await
-            .map_err(|_| CollectorError::channel("Failed to receive profile count response"))
+            .map_err(|_| CollectorError::channel("Failed to receive connection response"))
     }
 
     /// Clear all profiles
@@ -84,51 +225,105 @@ impl CollectorHandle {
             .map_err(|_| CollectorError::channel("Failed to send clear_profiles command"))
     }
 
+    /// Reload the configured signature database and restart the capture with it
+    ///
+    /// On failure the running capture keeps its current signatures.
+    pub async fn reload_signatures(&self) -> Result<SignatureReload> {
+        let (tx, rx) = oneshot::channel();
+
+        self.command_sender
+            .send(CollectorCommand::ReloadSignatures(tx))
+            .await
+            .map_err(|_| CollectorError::channel("Failed to send reload_signatures command"))?;
+
+        rx.await
+            .map_err(|_| CollectorError::channel("Failed to receive reload response"))?
+    }
+
     /// Stop the collector gracefully
-    pub async fn stop(mut self) -> Result<()> {
+    ///
+    /// Returns once the profile processor, the channel bridge and the huginn-net
+    /// capture have all finished, or with a shutdown error naming the part that
+    /// did not stop within the configured shutdown timeout.
+    pub async fn stop(self) -> Result<()> {
+        let timeout = self.shutdown_timeout;
+        self.stop_within(timeout).await
+    }
+
+    /// Stop the collector, waiting at most `timeout` for everything to finish
+    pub async fn stop_within(mut self, timeout: Duration) -> Result<()> {
         info!("Stopping network collector");
+        let deadline = Instant::now() + timeout;
 
         // Send shutdown signal
         if let Some(sender) = self.shutdown_sender.take() {
             let _ = sender.send(()).await;
         }
 
-        // Wait for processor to finish
+        // Wait for processor to finish (it may still swap in a new capture generation)
         if let Some(handle) = self.processor_handle.take() {
-            match handle.await {
-                Ok(result) => result?,
-                Err(e) => {
+            match tokio::time::timeout_at(deadline, handle).await {
+                Ok(Ok(result)) => result?,
+                Ok(Err(e)) => {
                     error!("Processor task join error: {}", e);
                     return Err(CollectorError::Join(e));
                 }
-            }
-        }
-
-        // Wait for bridge to finish
-        if let Some(handle) = self.bridge_handle.take() {
-            match handle.join() {
-                Ok(result) => result?,
-                Err(e) => {
-                    error!("Bridge thread join error: {:?}", e);
-                    return Err(CollectorError::Unknown(format!(
-                        "Bridge thread panic: {:?}",
-                        e
-                    )));
+                Err(_) => {
+                    return Err(CollectorError::shutdown(
+                        "Profile processor did not stop in time",
+                    ));
                 }
             }
         }
 
-        // Note: We don't wait for the analyzer thread as it's blocking on network capture
-        // and will stop when the process terminates
-        if let Some(handle) = self.analyzer_handle.take() {
-            // Just detach it, huginn-net will handle cleanup
-            std::mem::drop(handle);
+        let capture = self
+            .capture
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .take();
+
+        // Stop the bridge and wait for it and the capture thread
+        if let Some(capture) = capture {
+            capture.request_stop();
+            let joined = capture.join(deadline).await;
+            self.status.stopped();
+            joined?;
         }
 
         info!("Network collector stopped successfully");
         Ok(())
     }
 
+    /// Check if the capture source has been fully consumed
+    ///
+    /// Live captures never finish on their own; pcap replays finish at end of file.
+    /// Profiles stay queryable after the capture has finished.
+    pub fn is_capture_finished(&self) -> bool {
+        *self.capture_finished.borrow()
+    }
+
+    /// Wait until the capture source has been fully consumed and every result processed
+    pub async fn wait_for_capture(&self) -> Result<()> {
+        let mut capture_finished = self.capture_finished.clone();
+        capture_finished
+            .wait_for(|finished| *finished)
+            .await
+            .map(|_| ())
+            .map_err(|_| {
+                CollectorError::channel("Profile processor stopped before capture finished")
+            })
+    }
+
+    /// Current capture health (running, restarting or failed, with the last error)
+    pub fn status(&self) -> CollectorStatus {
+        self.status.get()
+    }
+
+    /// Results received, forwarded and dropped by the channel bridge
+    pub fn bridge_stats(&self) -> BridgeStats {
+        self.bridge_counters.snapshot()
+    }
+
     /// Check if the collector is still running
     pub fn is_running(&self) -> bool {
         self.processor_handle
@@ -144,11 +339,27 @@ impl CollectorHandle {
 /// 1. Starts huginn-net to capture network packets
 /// 2. Bridges between sync and async channels
 /// 3. Processes fingerprint results using huginn-core
-/// 4. Maintains a cache of traffic profiles
+/// 4. Keeps traffic profiles (per IP) in a `ProfileStore` and connection observations
+///    (per 4-tuple) in memory
+///
+/// Packets replayed from a file are stamped with the time they were recorded.
+/// Live packets are stamped when they reach the collector, using the analyzer
+/// clock (see [`NetworkCollectorBuilder::clock`]).
 pub struct NetworkCollector {
     config: CollectorConfig,
     analyzer: HuginnAnalyzer,
-    profiles: HashMap<String, TrafficProfile>,
+    store: Arc<dyn ProfileStore>,
+    connections: HashMap<String, ConnectionObservation>,
+    /// Signature database used by the current capture generation
+    database: Arc<Database>,
+    /// Capture generation counter, bumped on every signature reload
+    generation: u64,
+    /// Capture health, shared by every capture generation
+    status: Arc<StatusCell>,
+    /// Bridge counters, shared by every capture generation
+    bridge_counters: Arc<BridgeCounters>,
+    /// Runs the packet capture (huginn-net, or a stand-in in tests)
+    pub(crate) capture: Arc<dyn Capture>,
 }
 
 impl NetworkCollector {
@@ -157,18 +368,66 @@ impl NetworkCollector {
         // Validate configuration
         config.validate().map_err(CollectorError::configuration)?;
 
+        // Load the signature database up front so a bad file fails fast
+        let database = load_database(config.signature_db.as_deref())?;
+        if let Some(path) = &config.signature_db {
+            info!("Loaded signature database from {}", path.display());
+        }
+
         // Create analyzer with the configured settings
         let mut analyzer = HuginnAnalyzer::with_config(config.analyzer.clone());
 
+        // Name TLS clients from the JA4 label file, if configured
+        if let Some(path) = &config.ja4_db {
+            let ja4_database = Ja4Database::from_file(path)?;
+            info!(
+                "Loaded {} JA4 labels from {}",
+                ja4_database.len(),
+                path.display()
+            );
+            analyzer.set_ja4_database(ja4_database);
+        }
+
+        // Classify links with the local MTU table, if configured
+        if let Some(path) = &config.link_table {
+            let link_table = LinkTable::from_file(path)?;
+            info!(
+                "Loaded {} link types from {}",
+                link_table.len(),
+                path.display()
+            );
+            analyzer.set_link_table(link_table);
+        }
+
         // Add logging event handler
         analyzer
             .event_dispatcher_mut()
             .add_handler(LoggingEventHandler);
 
+        // Persist profiles in SQLite, if configured; otherwise keep them in memory
+        let store: Arc<dyn ProfileStore> = match &config.store_path {
+            Some(path) => {
+                let store = SqliteStore::open(path)?;
+                info!(
+                    "Opened profile store {} ({} profiles)",
+                    path.display(),
+                    store.len()?
+                );
+                Arc::new(store)
+            }
+            None => Arc::new(MemoryStore::new()),
+        };
+
         Ok(Self {
             config,
             analyzer,
-            profiles: HashMap::new(),
+            store,
+            connections: HashMap::new(),
+            database: Arc::new(database),
+            generation: 1,
+            status: Arc::new(StatusCell::new(1)),
+            bridge_counters: Arc::new(BridgeCounters::default()),
+            capture: Arc::new(HuginnCapture),
         })
     }
 
@@ -179,99 +438,306 @@ impl NetworkCollector {
     /// - Channel bridge in a separate thread  
     /// - Profile processor as an async task
     pub fn start(self) -> Result<CollectorHandle> {
-        info!(
-            "Starting network collector on interface: {}",
-            self.config.interface
-        );
+        info!("Starting network collector on {}", self.config.source);
+
+        let shutdown_timeout = Duration::from_millis(self.config.shutdown_timeout_ms);
+        let status = self.status.clone();
+        let bridge_counters = self.bridge_counters.clone();
+        let store = self.store.clone();
+        let (capture, async_receiver) = self.spawn_capture(self.database.clone())?;
+        let capture = Arc::new(Mutex::new(Some(capture)));
+
+        // Create shutdown channel
+        let (shutdown_sender, shutdown_receiver) = async_mpsc::channel(1);
+
+        // Create command channel
+        let (command_sender, command_receiver) = async_mpsc::channel(100);
+
+        // Create capture completion channel
+        let (capture_finished_sender, capture_finished) = watch::channel(false);
+
+        // Start the profile processor
+        let processor_capture = capture.clone();
+        let processor_handle = tokio::spawn(async move {
+            self.process_profiles(
+                async_receiver,
+                shutdown_receiver,
+                command_receiver,
+                capture_finished_sender,
+                processor_capture,
+            )
+            .await
+        });
+
+        Ok(CollectorHandle {
+            capture,
+            processor_handle: Some(processor_handle),
+            shutdown_sender: Some(shutdown_sender),
+            command_sender,
+            capture_finished,
+            shutdown_timeout,
+            status,
+            bridge_counters,
+            store,
+        })
+    }
 
+    /// Start huginn-net and the channel bridge for one capture generation
+    ///
+    /// The capture thread supervises huginn-net: when a live capture fails it
+    /// is restarted with exponential backoff until the restart policy gives up
+    /// or the generation is asked to stop. A failed pcap replay is not retried.
+    ///
+    /// Returns the generation's threads and the receiver its results arrive on.
+    fn spawn_capture(
+        &self,
+        database: Arc<Database>,
+    ) -> Result<(CaptureThreads, async_mpsc::Receiver<Captured>)> {
         // Create the channel bridge
         let (sync_sender, async_receiver, bridge) = create_bridge(self.config.channel_buffer_size);
+        let bridge = bridge
+            .with_overflow_policy(self.config.overflow_policy)
+            .with_counters(self.bridge_counters.clone());
 
         // Start the bridge in a separate thread
+        let stop = bridge.stop_signal();
         let bridge_handle = bridge.start_in_thread()?;
 
-        // Create huginn-net database
-        let db = Box::leak(Box::new(Database::default()));
-
         // Start huginn-net analyzer in a separate thread
-        let interface = self.config.interface.clone();
+        let source = self.config.source.clone();
         let buffer_size = self.config.buffer_size;
+        let generation = self.generation;
+        let restart = self.config.restart.clone();
+        let status = self.status.clone();
+        let capture_stop = stop.clone();
+        let capture = self.capture.clone();
         let analyzer_handle = std::thread::spawn(move || {
-            info!("Starting huginn-net analyzer on interface: {}", interface);
+            let mut backoff = Backoff::new(restart);
+
+            loop {
+                info!(
+                    "Starting huginn-net analyzer on {} (generation {})",
+                    source, generation
+                );
+                status.running(generation);
+                let started = std::time::Instant::now();
+
+                let result = capture.run(
+                    &source,
+                    &database,
+                    buffer_size,
+                    sync_sender.clone(),
+                    &capture_stop,
+                );
 
-            match HuginnNet::new(Some(db), buffer_size, None)
-                .analyze_network(&interface, sync_sender)
-            {
-                Ok(_) => {
-                    info!("Huginn-net analyzer finished successfully");
+                let error = match result {
+                    Ok(()) if !source.is_live() => {
+                        info!(
+                            "Huginn-net analyzer (generation {}) finished successfully",
+                            generation
+                        );
+                        status.finished(generation);
+                        break;
+                    }
+                    Ok(()) if capture_stop.load(Ordering::Relaxed) => {
+                        info!("Huginn-net analyzer (generation {}) stopped", generation);
+                        break;
+                    }
+                    // A live capture only ends on its own when something went wrong
+                    Ok(()) => "live capture ended unexpectedly".to_string(),
+                    Err(e) => e,
+                };
+                error!("Huginn-net analyzer error: {}", error);
+
+                if capture_stop.load(Ordering::Relaxed) {
+                    break;
                 }
-                Err(e) => {
-                    error!("Huginn-net analyzer error: {}", e);
+                if !source.is_live() {
+                    status.failed(generation, error);
+                    break;
+                }
+
+                let Some(delay) = backoff.next_delay(started.elapsed()) else {
+                    error!(
+                        "Huginn-net analyzer (generation {}) keeps failing, giving up",
+                        generation
+                    );
+                    status.failed(generation, error);
+                    break;
+                };
+                warn!(
+                    "Restarting huginn-net analyzer on {} in {:?}",
+                    source, delay
+                );
+                status.restarting(generation, error, delay);
+                if !sleep_unless_stopped(delay, &capture_stop) {
+                    break;
                 }
             }
         });
 
-        // Create shutdown channel
-        let (shutdown_sender, shutdown_receiver) = async_mpsc::channel(1);
+        Ok((
+            CaptureThreads {
+                analyzer_handle,
+                bridge_handle,
+                stop,
+            },
+            async_receiver,
+        ))
+    }
 
-        // Create command channel
-        let (command_sender, command_receiver) = async_mpsc::channel(100);
+    /// Load the configured signature database and move the capture onto it
+    ///
+    /// The new generation is started before the old one is released, so a
+    /// database that fails to load leaves the running capture untouched. The old
+    /// generation is then stopped and joined within the shutdown timeout.
+    async fn reload_signatures(
+        &mut self,
+        receiver: &mut async_mpsc::Receiver<Captured>,
+        capture: &Mutex<Option<CaptureThreads>>,
+    ) -> Result<SignatureReload> {
+        if !self.config.source.is_live() {
+            return Err(CollectorError::unsupported(
+                "Signature reload is only supported for live captures; replay the file again instead",
+            ));
+        }
 
-        // Start the profile processor
-        let processor_handle = tokio::spawn(async move {
-            self.process_profiles(async_receiver, shutdown_receiver, command_receiver)
-                .await
-        });
+        let database = Arc::new(load_database(self.config.signature_db.as_deref())?);
 
-        Ok(CollectorHandle {
-            analyzer_handle: Some(analyzer_handle),
-            bridge_handle: Some(bridge_handle),
-            processor_handle: Some(processor_handle),
-            shutdown_sender: Some(shutdown_sender),
-            command_sender,
+        self.generation += 1;
+        let (threads, new_receiver) = match self.spawn_capture(database.clone()) {
+            Ok(started) => started,
+            Err(e) => {
+                self.generation -= 1;
+                return Err(e);
+            }
+        };
+
+        // Stopping the old bridge closes the old capture's channel
+        let old = capture
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .replace(threads);
+        if let Some(old) = &old {
+            old.request_stop();
+        }
+        *receiver = new_receiver;
+        self.database = database;
+        if let Some(old) = old {
+            debug!("Stopping capture generation {}", self.generation - 1);
+            let deadline = Instant::now() + Duration::from_millis(self.config.shutdown_timeout_ms);
+            if let Err(e) = old.join(deadline).await {
+                warn!(
+                    "Capture generation {} did not stop cleanly: {}",
+                    self.generation - 1,
+                    e
+                );
+            }
+        }
+
+        info!(
+            "Signature database reloaded from {}, capture generation {}",
+            self.config
+                .signature_db
+                .as_ref()
+                .map(|path| path.display().to_string())
+                .unwrap_or_else(|| "built-in signatures".to_string()),
+            self.generation
+        );
+
+        Ok(SignatureReload {
+            path: self.config.signature_db.clone(),
+            generation: self.generation,
         })
     }
 
     /// Process fingerprint results and maintain traffic profiles
     async fn process_profiles(
         mut self,
-        mut receiver: async_mpsc::Receiver<FingerprintResult>,
+        mut receiver: async_mpsc::Receiver<Captured>,
         mut shutdown: async_mpsc::Receiver<()>,
         mut command_receiver: async_mpsc::Receiver<CollectorCommand>,
+        capture_finished: watch::Sender<bool>,
+        capture: Arc<Mutex<Option<CaptureThreads>>>,
     ) -> Result<()> {
         info!("Starting profile processor");
 
+        let mut input_closed = false;
+
+        // Idle profiles and connections are only swept when an idle TTL is configured
+        let idle_ttl = self.config.idle_ttl_secs;
+        let mut sweep =
+            tokio::time::interval(Duration::from_secs(self.config.sweep_interval_secs.max(1)));
+        sweep.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
         loop {
             tokio::select! {
                 // Process incoming fingerprint results
-                Some(result) = receiver.recv() => {
-                    if let Err(e) = self.process_fingerprint_result(result).await {
-                        error!("Error processing fingerprint result: {}", e);
+                result = receiver.recv(), if !input_closed => {
+                    match result {
+                        Some(result) => {
+                            if let Err(e) = self.process_fingerprint_result(result).await {
+                                error!("Error processing fingerprint result: {}", e);
+                            }
+                        }
+                        None => {
+                            // Capture finished (e.g. end of pcap file); keep serving commands
+                            let collected = on_store(&self.store, |store| store.len()).await;
+                            info!(
+                                "Capture input finished, {} profiles collected",
+                                collected.unwrap_or_default()
+                            );
+                            input_closed = true;
+                            let _ = capture_finished.send(true);
+                        }
                     }
                 }
 
                 // Handle commands from the API
                 Some(command) = command_receiver.recv() => {
                     match command {
-                        CollectorCommand::GetProfiles(tx) => {
-                            let profiles = self.profiles.clone();
-                            let _ = tx.send(profiles);
+                        CollectorCommand::GetConnections(tx) => {
+                            let connections = self.connections.clone();
+                            let _ = tx.send(connections);
+                        }
+                        CollectorCommand::GetConnectionsSince(since, tx) => {
+                            let connections = self
+                                .connections
+                                .iter()
+                                .filter(|(_, connection)| connection.last_updated >= since)
+                                .map(|(key, connection)| (key.clone(), connection.clone()))
+                                .collect();
+                            let _ = tx.send(connections);
                         }
-                        CollectorCommand::GetProfile(key, tx) => {
-                            let profile = self.profiles.get(&key).cloned();
-                            let _ = tx.send(profile);
+                        CollectorCommand::GetConnectionKeys(tx) => {
+                            let _ = tx.send(self.connections.keys().cloned().collect());
                         }
-                        CollectorCommand::GetProfileCount(tx) => {
-                            let count = self.profiles.len();
-                            let _ = tx.send(count);
+                        CollectorCommand::GetConnection(key, tx) => {
+                            let connection = self.connections.get(&key).cloned();
+                            let _ = tx.send(connection);
                         }
                         CollectorCommand::ClearProfiles => {
-                            self.profiles.clear();
-                            info!("Cleared all profiles");
+                            self.connections.clear();
+                            match on_store(&self.store, |store| store.clear()).await {
+                                Ok(()) => info!("Cleared all profiles"),
+                                Err(e) => error!("Failed to clear profiles: {}", e),
+                            }
+                        }
+                        CollectorCommand::ReloadSignatures(tx) => {
+                            let reload = self.reload_signatures(&mut receiver, &capture).await;
+                            if let Err(e) = &reload {
+                                error!("Signature reload failed: {}", e);
+                            }
+                            let _ = tx.send(reload);
                         }
                     }
                 }
 
+                // Evict profiles and connections that went idle
+                _ = sweep.tick(), if idle_ttl.is_some() => {
+                    self.sweep_idle().await;
+                }
+
                 // Handle shutdown signal
                 _ = shutdown.recv() => {
                     info!("Profile processor received shutdown signal");
@@ -286,37 +752,73 @@ impl NetworkCollector {
             }
         }
 
+        // The receiver goes away with the processor; stop the bridge first
+        if let Some(capture) = capture.lock().unwrap_or_else(|e| e.into_inner()).as_ref() {
+            capture.request_stop();
+        }
+
         info!("Profile processor stopped");
         Ok(())
     }
 
     /// Process a single fingerprint result
-    async fn process_fingerprint_result(&mut self, result: FingerprintResult) -> Result<()> {
+    async fn process_fingerprint_result(&mut self, captured: Captured) -> Result<()> {
         debug!("Processing fingerprint result");
+        let Captured {
+            result,
+            wire,
+            captured_at,
+        } = captured;
+
+        let connection_key = self.analyzer.connection_key(&result);
 
-        // Analyze the result using huginn-core
-        match self.analyzer.analyze(result) {
+        // Analyze the result using huginn-core, stamped with the capture time
+        let observed_at = captured_at.unwrap_or_else(|| self.analyzer.now());
+        match self.analyzer.analyze_packet(result, &wire, observed_at) {
             Ok(Some(profile)) => {
-                let key = profile.ip.to_string(); // Group by IP only, not IP:port
+                // Keep the per-flow record so two clients behind one IP don't overwrite each other
+                if let Some(connection_key) = connection_key {
+                    let connection_id = connection_key.to_string();
+                    match self.connections.get_mut(&connection_id) {
+                        Some(observation) => observation.record(&profile),
+                        None => {
+                            debug!("Tracking new connection {}", connection_id);
+                            self.connections.insert(
+                                connection_id,
+                                ConnectionObservation::from_profile(connection_key, &profile),
+                            );
+                        }
+                    }
+                }
+
+                let key = profile.ip.to_string(); // Per-IP rollup
+                let host_signals = HostSignals::from_profile(&profile);
 
                 // Check if this is a new profile or an update
-                let is_new_profile = !self.profiles.contains_key(&key);
-
-                if is_new_profile {
-                    // Insert new profile
-                    info!("Creating new profile for {}", key);
-                    self.profiles.insert(key, profile);
-                } else {
-                    // Merge the new profile data into existing profile
-                    debug!("Updating existing profile for {}", key);
-                    let existing = self.profiles.get_mut(&key).unwrap();
-                    Self::merge_profiles(existing, profile);
-                }
+                let lookup = key.clone();
+                let existing = on_store(&self.store, move |store| store.get(&lookup)).await?;
+                let (mut stored, is_new_profile) = match existing {
+                    Some(mut existing) => {
+                        // Merge the new profile data into existing profile
+                        debug!("Updating existing profile for {}", key);
+                        Self::merge_profiles(&mut existing, profile);
+                        (existing, false)
+                    }
+                    None => {
+                        info!("Creating new profile for {}", key);
+                        (profile, true)
+                    }
+                };
 
-                debug!(
-                    "Profile cache now contains {} profiles",
-                    self.profiles.len()
-                );
+                // Layers arrive separately, so compare them on the merged profile
+                self.analyzer.check_consistency(&mut stored);
+                self.analyzer.track_uptime(&mut stored, &host_signals);
+                self.analyzer.track_hops(&mut stored, &host_signals);
+                self.analyzer.track_hosts(&mut stored, host_signals);
+                self.analyzer.score_risk(&mut stored);
+
+                on_store(&self.store, move |store| store.put(&key, stored)).await?;
+                self.enforce_limits(is_new_profile).await?;
             }
             Ok(None) => {
                 debug!("Analysis returned no profile (likely filtered out)");
@@ -330,6 +832,86 @@ impl NetworkCollector {
         Ok(())
     }
 
+    /// Evict the least recently updated profiles and connections beyond the configured limits
+    ///
+    /// Only a new profile can push the store over its limit, so the store is
+    /// left alone after updates.
+    async fn enforce_limits(&mut self, new_profile: bool) -> Result<()> {
+        if let Some(max) = self.config.max_profiles.filter(|_| new_profile) {
+            let evicted = on_store(&self.store, move |store| store.evict_lru(max)).await?;
+            for key in &evicted {
+                debug!("Evicted profile {} (profile limit {})", key, max);
+            }
+            self.forget_connections_of(&evicted);
+        }
+
+        if let Some(max) = self.config.max_connections {
+            for key in evict_lru(&mut self.connections, max) {
+                debug!("Evicted connection {} (connection limit {})", key, max);
+            }
+        }
+        Ok(())
+    }
+
+    /// Evict profiles and connections not updated within the idle TTL
+    async fn sweep_idle(&mut self) {
+        let Some(ttl) = self
+            .config
+            .idle_ttl_secs
+            .and_then(|secs| chrono::Duration::try_seconds(i64::try_from(secs).ok()?))
+        else {
+            return;
+        };
+        let Some(cutoff) = self.cache_clock().await.checked_sub_signed(ttl) else {
+            return;
+        };
+
+        let expired = match on_store(&self.store, move |store| store.expire_idle(cutoff)).await {
+            Ok(expired) => expired,
+            Err(e) => {
+                error!("Failed to evict idle profiles: {}", e);
+                Vec::new()
+            }
+        };
+        let connections =
+            self.forget_connections_of(&expired) + expire_idle(&mut self.connections, cutoff).len();
+        if !expired.is_empty() || connections > 0 {
+            info!(
+                "Evicted {} idle profiles and {} idle connections",
+                expired.len(),
+                connections
+            );
+        }
+    }
+
+    /// Time idle entries are measured against
+    ///
+    /// The analyzer clock for live captures. A replayed file is stamped with its
+    /// recording times, so there it is the newest observation: entries go idle
+    /// relative to the end of the recording, not to the day it is replayed.
+    async fn cache_clock(&self) -> DateTime<Utc> {
+        if self.config.source.is_live() {
+            return self.analyzer.now();
+        }
+        on_store(&self.store, |store| store.newest_update())
+            .await
+            .ok()
+            .flatten()
+            .unwrap_or_else(|| self.analyzer.now())
+    }
+
+    /// Drop the connections whose client is one of the evicted profile IPs
+    fn forget_connections_of(&mut self, ips: &[String]) -> usize {
+        if ips.is_empty() {
+            return 0;
+        }
+        let ips: HashSet<&str> = ips.iter().map(String::as_str).collect();
+        let before = self.connections.len();
+        self.connections
+            .retain(|_, connection| !ips.contains(connection.key.client.ip().to_string().as_str()));
+        before - self.connections.len()
+    }
+
     /// Merge new profile data into existing profile
     fn merge_profiles(existing: &mut TrafficProfile, new: TrafficProfile) {
         // Update TCP data if new profile has it
@@ -347,9 +929,12 @@ impl NetworkCollector {
             existing.tcp_server = new.tcp_server;
         }
 
-        // Update HTTP data if new profile has it
-        if new.http.is_some() {
-            existing.http = new.http;
+        // Merge HTTP data: a response must not wipe the request already seen
+        if let Some(http) = new.http {
+            match &mut existing.http {
+                Some(existing_http) => existing_http.merge(http),
+                None => existing.http = Some(http),
+            }
         }
 
         // Update TLS data if new profile has it
@@ -379,13 +964,28 @@ impl NetworkCollector {
         if new.raw_data.uptime.is_some() {
             existing.raw_data.uptime = new.raw_data.uptime;
         }
+        // Keep the last link classification until a result has a new one
+        if new.link_type.is_some() {
+            existing.link_type = new.link_type;
+            existing.likely_vpn = new.likely_vpn;
+        }
+
         if new.raw_data.source_ip.is_some() {
             existing.raw_data.source_ip = new.raw_data.source_ip;
         }
 
-        // Update metadata
+        // Newer enricher values replace older ones under the same key
+        existing.enrichment.extend(new.enrichment);
+
+        // Update metadata (the rollup reports the client port of the latest connection).
+        // Observation times may arrive out of order when replaying, so keep the extremes.
+        existing.port = new.port;
         existing.timestamp = new.timestamp;
-        existing.metadata.last_updated = new.metadata.last_updated;
+        existing.metadata.first_seen = existing.metadata.first_seen.min(new.metadata.first_seen);
+        existing.metadata.last_updated = existing
+            .metadata
+            .last_updated
+            .max(new.metadata.last_updated);
         existing.metadata.packet_count += new.metadata.packet_count;
 
         // Recalculate completeness
@@ -403,30 +1003,54 @@ impl NetworkCollector {
         existing.metadata.completeness = score;
     }
 
+    /// Run a custom enrichment stage on every analyzed profile
+    pub fn add_enricher<E: ProfileEnricher + 'static>(&mut self, enricher: E) {
+        self.analyzer.add_enricher(enricher);
+    }
+
     /// Get a copy of all current profiles
-    pub fn get_profiles(&self) -> HashMap<String, TrafficProfile> {
-        self.profiles.clone()
+    pub fn get_profiles(&self) -> Result<HashMap<String, TrafficProfile>> {
+        self.store.all()
+    }
+
+    /// Get a specific profile by IP key
+    pub fn get_profile(&self, key: &str) -> Result<Option<TrafficProfile>> {
+        self.store.get(key)
+    }
+
+    /// Get the number of stored profiles
+    pub fn profile_count(&self) -> Result<usize> {
+        self.store.len()
     }
 
-    /// Get a specific profile by IP:port key
-    pub fn get_profile(&self, key: &str) -> Option<&TrafficProfile> {
-        self.profiles.get(key)
+    /// Profile store the collector writes to
+    pub fn store(&self) -> Arc<dyn ProfileStore> {
+        self.store.clone()
     }
 
-    /// Get the number of profiles in cache
-    pub fn profile_count(&self) -> usize {
-        self.profiles.len()
+    /// Get a copy of all current connection observations
+    pub fn get_connections(&self) -> HashMap<String, ConnectionObservation> {
+        self.connections.clone()
     }
 
-    /// Clear all profiles from cache
-    pub fn clear_profiles(&mut self) {
-        self.profiles.clear();
+    /// Get a specific connection observation by `client-server` key
+    pub fn get_connection(&self, key: &str) -> Option<&ConnectionObservation> {
+        self.connections.get(key)
+    }
+
+    /// Clear all profiles and connection observations
+    pub fn clear_profiles(&mut self) -> Result<()> {
+        self.connections.clear();
+        self.store.clear()
     }
 }
 
 /// Builder for creating a NetworkCollector with custom configuration
 pub struct NetworkCollectorBuilder {
     config: CollectorConfig,
+    enrichers: Vec<Arc<dyn ProfileEnricher>>,
+    store: Option<Arc<dyn ProfileStore>>,
+    clock: Option<Arc<dyn Clock>>,
 }
 
 impl NetworkCollectorBuilder {
@@ -434,9 +1058,58 @@ impl NetworkCollectorBuilder {
     pub fn new(interface: String) -> Self {
         Self {
             config: CollectorConfig::new(interface),
+            enrichers: Vec::new(),
+            store: None,
+            clock: None,
+        }
+    }
+
+    /// Create a new builder that replays the specified pcap/pcapng file
+    pub fn from_pcap<P: Into<std::path::PathBuf>>(path: P) -> Self {
+        Self {
+            config: CollectorConfig::from_pcap(path),
+            enrichers: Vec::new(),
+            store: None,
+            clock: None,
         }
     }
 
+    /// Set the capture source
+    pub fn source(mut self, source: CaptureSource) -> Self {
+        self.config.source = source;
+        self
+    }
+
+    /// Use the signature database at the given path
+    pub fn signature_db<P: Into<std::path::PathBuf>>(mut self, path: P) -> Self {
+        self.config = self.config.with_signature_db(path);
+        self
+    }
+
+    /// Label TLS clients using the JA4 database at the given path
+    pub fn ja4_db<P: Into<std::path::PathBuf>>(mut self, path: P) -> Self {
+        self.config = self.config.with_ja4_db(path);
+        self
+    }
+
+    /// Classify links using the MTU table at the given path
+    pub fn link_table<P: Into<std::path::PathBuf>>(mut self, path: P) -> Self {
+        self.config = self.config.with_link_table(path);
+        self
+    }
+
+    /// Set how long `stop()` waits for the collector to wind down
+    pub fn shutdown_timeout(mut self, timeout: Duration) -> Self {
+        self.config = self.config.with_shutdown_timeout(timeout);
+        self
+    }
+
+    /// Set how a failed live capture is restarted
+    pub fn restart_policy(mut self, policy: RestartPolicy) -> Self {
+        self.config = self.config.with_restart_policy(policy);
+        self
+    }
+
     /// Set the buffer size for huginn-net
     pub fn buffer_size(mut self, size: usize) -> Self {
         self.config = self.config.with_buffer_size(size);
@@ -449,6 +1122,55 @@ impl NetworkCollectorBuilder {
         self
     }
 
+    /// Set what the bridge does with results when the channel is full
+    pub fn overflow_policy(mut self, policy: OverflowPolicy) -> Self {
+        self.config = self.config.with_overflow_policy(policy);
+        self
+    }
+
+    /// Keep at most `max` profiles, evicting the least recently updated
+    pub fn max_profiles(mut self, max: usize) -> Self {
+        self.config = self.config.with_max_profiles(max);
+        self
+    }
+
+    /// Keep at most `max` connection observations, evicting the least recently updated
+    pub fn max_connections(mut self, max: usize) -> Self {
+        self.config = self.config.with_max_connections(max);
+        self
+    }
+
+    /// Evict profiles and connections not updated for `ttl`
+    pub fn idle_ttl(mut self, ttl: Duration) -> Self {
+        self.config = self.config.with_idle_ttl(ttl);
+        self
+    }
+
+    /// Set how often idle profiles and connections are swept
+    pub fn sweep_interval(mut self, interval: Duration) -> Self {
+        self.config = self.config.with_sweep_interval(interval);
+        self
+    }
+
+    /// Persist profiles in the SQLite database at `path`
+    pub fn store_path<P: Into<std::path::PathBuf>>(mut self, path: P) -> Self {
+        self.config = self.config.with_store_path(path);
+        self
+    }
+
+    /// Keep profiles in a custom store (takes precedence over `store_path`)
+    pub fn store<S: ProfileStore + 'static>(mut self, store: S) -> Self {
+        self.config.store_path = None;
+        self.store = Some(Arc::new(store));
+        self
+    }
+
+    /// Stamp results and measure idle time with `clock` instead of wall-clock time
+    pub fn clock<C: Clock + 'static>(mut self, clock: C) -> Self {
+        self.clock = Some(Arc::new(clock));
+        self
+    }
+
     /// Set the minimum quality threshold
     pub fn min_quality(mut self, quality: f64) -> Self {
         self.config.analyzer.min_quality = quality;
@@ -479,8 +1201,24 @@ impl NetworkCollectorBuilder {
         self
     }
 
+    /// Run a custom enrichment stage on every analyzed profile
+    pub fn enricher<E: ProfileEnricher + 'static>(mut self, enricher: E) -> Self {
+        self.enrichers.push(Arc::new(enricher));
+        self
+    }
+
     /// Build the NetworkCollector
     pub fn build(self) -> Result<NetworkCollector> {
-        NetworkCollector::new(self.config)
+        let mut collector = NetworkCollector::new(self.config)?;
+        for enricher in self.enrichers {
+            collector.analyzer.add_shared_enricher(enricher);
+        }
+        if let Some(store) = self.store {
+            collector.store = store;
+        }
+        if let Some(clock) = self.clock {
+            collector.analyzer.set_clock(clock);
+        }
+        Ok(collector)
     }
 }
@@ -1,30 +1,121 @@
+use crate::bridge::OverflowPolicy;
+use crate::supervisor::RestartPolicy;
 use clap::Parser;
 use huginn_core::AnalyzerConfig;
 use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::PathBuf;
+
+/// Connection observations kept unless configured otherwise
+pub const DEFAULT_MAX_CONNECTIONS: usize = 10_000;
+
+/// Where the collector reads packets from
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CaptureSource {
+    /// Live capture on a network interface (requires capture privileges)
+    Interface(String),
+    /// Offline replay of a pcap/pcapng file; the capture ends at end of file
+    PcapFile(PathBuf),
+}
+
+impl CaptureSource {
+    /// Whether this source is a live network interface
+    pub fn is_live(&self) -> bool {
+        matches!(self, CaptureSource::Interface(_))
+    }
+}
+
+impl fmt::Display for CaptureSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CaptureSource::Interface(interface) => write!(f, "interface {}", interface),
+            CaptureSource::PcapFile(path) => write!(f, "pcap file {}", path.display()),
+        }
+    }
+}
 
 /// Configuration for the network collector
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CollectorConfig {
-    /// Network interface to monitor
-    pub interface: String,
+    /// Packet source (live interface or pcap file)
+    pub source: CaptureSource,
     /// Buffer size for huginn-net
     pub buffer_size: usize,
     /// Channel buffer size for internal communication
     pub channel_buffer_size: usize,
+    /// What the bridge does with results when the channel is full
+    #[serde(default)]
+    pub overflow_policy: OverflowPolicy,
+    /// Signature database file in p0f format (`None` uses the built-in signatures)
+    #[serde(default)]
+    pub signature_db: Option<PathBuf>,
+    /// JA4 → application label file used to name TLS clients
+    #[serde(default)]
+    pub ja4_db: Option<PathBuf>,
+    /// MTU → link type table (`None` uses the built-in table)
+    #[serde(default)]
+    pub link_table: Option<PathBuf>,
     /// Analyzer configuration
     pub analyzer: AnalyzerConfig,
     /// Whether to enable detailed logging
     pub verbose: bool,
+    /// How long (milliseconds) `stop()` waits for the capture, bridge and processor
+    #[serde(default = "default_shutdown_timeout_ms")]
+    pub shutdown_timeout_ms: u64,
+    /// How a failed live capture is restarted
+    #[serde(default)]
+    pub restart: RestartPolicy,
+    /// Maximum number of profiles kept; the least recently updated are evicted first
+    #[serde(default)]
+    pub max_profiles: Option<usize>,
+    /// Maximum number of connection observations kept; the least recently updated are evicted first
+    ///
+    /// Defaults to [`DEFAULT_MAX_CONNECTIONS`]; `None` keeps every connection.
+    #[serde(default = "default_max_connections")]
+    pub max_connections: Option<usize>,
+    /// Evict profiles and connections not updated for this many seconds
+    #[serde(default)]
+    pub idle_ttl_secs: Option<u64>,
+    /// How often (seconds) idle profiles and connections are swept
+    #[serde(default = "default_sweep_interval_secs")]
+    pub sweep_interval_secs: u64,
+    /// SQLite database the profiles are persisted in (`None` keeps them in memory)
+    #[serde(default)]
+    pub store_path: Option<PathBuf>,
+}
+
+fn default_shutdown_timeout_ms() -> u64 {
+    5000
+}
+
+fn default_sweep_interval_secs() -> u64 {
+    60
+}
+
+fn default_max_connections() -> Option<usize> {
+    Some(DEFAULT_MAX_CONNECTIONS)
 }
 
 impl Default for CollectorConfig {
     fn default() -> Self {
         Self {
-            interface: "eth0".to_string(),
+            source: CaptureSource::Interface("eth0".to_string()),
             buffer_size: 100,
             channel_buffer_size: 1000,
+            overflow_policy: OverflowPolicy::default(),
+            signature_db: None,
+            ja4_db: None,
+            link_table: None,
             analyzer: AnalyzerConfig::default(),
             verbose: false,
+            shutdown_timeout_ms: default_shutdown_timeout_ms(),
+            restart: RestartPolicy::default(),
+            max_profiles: None,
+            max_connections: default_max_connections(),
+            idle_ttl_secs: None,
+            sweep_interval_secs: default_sweep_interval_secs(),
+            store_path: None,
         }
     }
 }
@@ -34,8 +125,29 @@ impl Default for CollectorConfig {
 #[command(version, about, long_about = None)]
 pub struct CollectorArgs {
     /// Network interface to monitor
-    #[arg(short = 'i', long)]
-    pub interface: String,
+    #[arg(
+        short = 'i',
+        long,
+        required_unless_present = "pcap",
+        conflicts_with = "pcap"
+    )]
+    pub interface: Option<String>,
+
+    /// Replay a pcap/pcapng file instead of capturing live traffic
+    #[arg(long)]
+    pub pcap: Option<PathBuf>,
+
+    /// Signature database file (p0f format) instead of the built-in signatures
+    #[arg(long)]
+    pub signature_db: Option<PathBuf>,
+
+    /// JA4 label file used to name TLS clients (one `<ja4> <label>` per line)
+    #[arg(long)]
+    pub ja4_db: Option<PathBuf>,
+
+    /// MTU table used to name link types (one `<mtu|min-max> <yes|no> <label>` per line)
+    #[arg(long)]
+    pub link_table: Option<PathBuf>,
 
     /// Buffer size for huginn-net
     #[arg(long, default_value = "100")]
@@ -45,6 +157,26 @@ pub struct CollectorArgs {
     #[arg(long, default_value = "1000")]
     pub channel_buffer_size: usize,
 
+    /// What to do when the channel is full: block, drop-newest, drop-oldest or sample:<N>
+    #[arg(long, default_value = "block")]
+    pub overflow_policy: OverflowPolicy,
+
+    /// Maximum number of profiles kept (least recently updated evicted first)
+    #[arg(long)]
+    pub max_profiles: Option<usize>,
+
+    /// Maximum number of connection observations kept (least recently updated evicted first)
+    #[arg(long, default_value_t = DEFAULT_MAX_CONNECTIONS)]
+    pub max_connections: usize,
+
+    /// Evict profiles and connections not updated for this many seconds
+    #[arg(long)]
+    pub idle_ttl_secs: Option<u64>,
+
+    /// SQLite database to persist profiles in (created if missing)
+    #[arg(long)]
+    pub store: Option<PathBuf>,
+
     /// Minimum quality threshold for analysis results
     #[arg(long, default_value = "0.0")]
     pub min_quality: f64,
@@ -68,17 +200,34 @@ pub struct CollectorArgs {
 
 impl From<CollectorArgs> for CollectorConfig {
     fn from(args: CollectorArgs) -> Self {
+        let source = match args.pcap {
+            Some(path) => CaptureSource::PcapFile(path),
+            None => CaptureSource::Interface(args.interface.unwrap_or_default()),
+        };
+
         Self {
-            interface: args.interface,
+            source,
             buffer_size: args.buffer_size,
             channel_buffer_size: args.channel_buffer_size,
+            overflow_policy: args.overflow_policy,
+            signature_db: args.signature_db,
+            ja4_db: args.ja4_db,
+            link_table: args.link_table,
             analyzer: AnalyzerConfig {
                 enable_tcp: !args.no_tcp,
                 enable_http: !args.no_http,
                 enable_tls: !args.no_tls,
                 min_quality: args.min_quality,
+                ..AnalyzerConfig::default()
             },
             verbose: args.verbose,
+            shutdown_timeout_ms: default_shutdown_timeout_ms(),
+            restart: RestartPolicy::default(),
+            max_profiles: args.max_profiles,
+            max_connections: Some(args.max_connections),
+            idle_ttl_secs: args.idle_ttl_secs,
+            sweep_interval_secs: default_sweep_interval_secs(),
+            store_path: args.store,
         }
     }
 }
@@ -86,12 +235,30 @@ impl From<CollectorArgs> for CollectorConfig {
 impl CollectorConfig {
     /// Create a new configuration with the specified interface
     pub fn new(interface: String) -> Self {
+        Self::with_source(CaptureSource::Interface(interface))
+    }
+
+    /// Create a new configuration that replays the specified pcap/pcapng file
+    pub fn from_pcap<P: Into<PathBuf>>(path: P) -> Self {
+        Self::with_source(CaptureSource::PcapFile(path.into()))
+    }
+
+    /// Create a new configuration with the specified capture source
+    pub fn with_source(source: CaptureSource) -> Self {
         Self {
-            interface,
+            source,
             ..Default::default()
         }
     }
 
+    /// Network interface being monitored (`None` when replaying a pcap file)
+    pub fn interface(&self) -> Option<&str> {
+        match &self.source {
+            CaptureSource::Interface(interface) => Some(interface),
+            CaptureSource::PcapFile(_) => None,
+        }
+    }
+
     /// Set the buffer size
     pub fn with_buffer_size(mut self, size: usize) -> Self {
         self.buffer_size = size;
@@ -104,6 +271,30 @@ impl CollectorConfig {
         self
     }
 
+    /// Set what the bridge does with results when the channel is full
+    pub fn with_overflow_policy(mut self, policy: OverflowPolicy) -> Self {
+        self.overflow_policy = policy;
+        self
+    }
+
+    /// Use the signature database at the given path
+    pub fn with_signature_db<P: Into<PathBuf>>(mut self, path: P) -> Self {
+        self.signature_db = Some(path.into());
+        self
+    }
+
+    /// Label TLS clients using the JA4 database at the given path
+    pub fn with_ja4_db<P: Into<PathBuf>>(mut self, path: P) -> Self {
+        self.ja4_db = Some(path.into());
+        self
+    }
+
+    /// Classify links using the MTU table at the given path
+    pub fn with_link_table<P: Into<PathBuf>>(mut self, path: P) -> Self {
+        self.link_table = Some(path.into());
+        self
+    }
+
     /// Set the analyzer configuration
     pub fn with_analyzer(mut self, analyzer: AnalyzerConfig) -> Self {
         self.analyzer = analyzer;
@@ -116,10 +307,82 @@ impl CollectorConfig {
         self
     }
 
+    /// Set how long `stop()` waits for the collector to wind down
+    pub fn with_shutdown_timeout(mut self, timeout: std::time::Duration) -> Self {
+        self.shutdown_timeout_ms = u64::try_from(timeout.as_millis()).unwrap_or(u64::MAX);
+        self
+    }
+
+    /// Set how a failed live capture is restarted
+    pub fn with_restart_policy(mut self, policy: RestartPolicy) -> Self {
+        self.restart = policy;
+        self
+    }
+
+    /// Keep at most `max` profiles, evicting the least recently updated
+    pub fn with_max_profiles(mut self, max: usize) -> Self {
+        self.max_profiles = Some(max);
+        self
+    }
+
+    /// Keep at most `max` connection observations, evicting the least recently updated
+    pub fn with_max_connections(mut self, max: usize) -> Self {
+        self.max_connections = Some(max);
+        self
+    }
+
+    /// Evict profiles and connections not updated for `ttl`
+    pub fn with_idle_ttl(mut self, ttl: std::time::Duration) -> Self {
+        self.idle_ttl_secs = Some(ttl.as_secs());
+        self
+    }
+
+    /// Set how often idle profiles and connections are swept
+    pub fn with_sweep_interval(mut self, interval: std::time::Duration) -> Self {
+        self.sweep_interval_secs = interval.as_secs();
+        self
+    }
+
+    /// Persist profiles in the SQLite database at `path`
+    pub fn with_store_path<P: Into<PathBuf>>(mut self, path: P) -> Self {
+        self.store_path = Some(path.into());
+        self
+    }
+
     /// Validate the configuration
     pub fn validate(&self) -> Result<(), String> {
-        if self.interface.is_empty() {
-            return Err("Interface cannot be empty".to_string());
+        match &self.source {
+            CaptureSource::Interface(interface) => {
+                if interface.is_empty() {
+                    return Err("Interface cannot be empty".to_string());
+                }
+            }
+            CaptureSource::PcapFile(path) => {
+                if path.as_os_str().is_empty() {
+                    return Err("Pcap file path cannot be empty".to_string());
+                }
+                if !path.is_file() {
+                    return Err(format!("Pcap file not found: {}", path.display()));
+                }
+            }
+        }
+
+        if let Some(path) = &self.signature_db {
+            if !path.is_file() {
+                return Err(format!("Signature database not found: {}", path.display()));
+            }
+        }
+
+        if let Some(path) = &self.ja4_db {
+            if !path.is_file() {
+                return Err(format!("JA4 database not found: {}", path.display()));
+            }
+        }
+
+        if let Some(path) = &self.link_table {
+            if !path.is_file() {
+                return Err(format!("Link table not found: {}", path.display()));
+            }
         }
 
         if self.buffer_size == 0 {
@@ -130,6 +393,30 @@ impl CollectorConfig {
             return Err("Channel buffer size must be greater than 0".to_string());
         }
 
+        if self.overflow_policy == OverflowPolicy::Sample(0) {
+            return Err("Overflow sample rate must be greater than 0".to_string());
+        }
+
+        if self.max_profiles == Some(0) || self.max_connections == Some(0) {
+            return Err("Profile and connection limits must be greater than 0".to_string());
+        }
+
+        if self.idle_ttl_secs.is_some() && self.sweep_interval_secs == 0 {
+            return Err("Sweep interval must be at least 1 second".to_string());
+        }
+
+        if self.restart.initial_backoff_ms == 0 {
+            return Err("Initial restart backoff must be greater than 0".to_string());
+        }
+
+        if i64::try_from(self.analyzer.nat_window_secs)
+            .ok()
+            .and_then(chrono::Duration::try_seconds)
+            .is_none()
+        {
+            return Err("NAT window is too long".to_string());
+        }
+
         if self.analyzer.min_quality < 0.0 || self.analyzer.min_quality > 1.0 {
             return Err("Minimum quality must be between 0.0 and 1.0".to_string());
         }
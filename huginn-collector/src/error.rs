@@ -15,12 +15,27 @@ pub enum CollectorError {
     #[error("Channel error: {0}")]
     Channel(String),
 
+    #[error("Signature database error: {0}")]
+    SignatureDatabase(String),
+
     #[error("Huginn core error: {0}")]
     Core(#[from] huginn_core::HuginnError),
 
     #[error("IO error: {0}")]
     Io(#[from] std::io::Error),
 
+    #[error("Shutdown error: {0}")]
+    Shutdown(String),
+
+    #[error("Not supported: {0}")]
+    Unsupported(String),
+
+    #[error("Profile store error: {0}")]
+    Store(String),
+
+    #[error("SQLite error: {0}")]
+    Sqlite(#[from] rusqlite::Error),
+
     #[error("Join error: {0}")]
     Join(#[from] tokio::task::JoinError),
 
@@ -43,4 +58,24 @@ impl CollectorError {
     pub fn channel<S: Into<String>>(msg: S) -> Self {
         Self::Channel(msg.into())
     }
+
+    /// Create a new shutdown error
+    pub fn shutdown<S: Into<String>>(msg: S) -> Self {
+        Self::Shutdown(msg.into())
+    }
+
+    /// Create a new error for an operation the current setup doesn't support
+    pub fn unsupported<S: Into<String>>(msg: S) -> Self {
+        Self::Unsupported(msg.into())
+    }
+
+    /// Create a new profile store error
+    pub fn store<S: Into<String>>(msg: S) -> Self {
+        Self::Store(msg.into())
+    }
+
+    /// Create a new signature database error
+    pub fn signature_database<S: Into<String>>(msg: S) -> Self {
+        Self::SignatureDatabase(msg.into())
+    }
 }
@@ -4,15 +4,25 @@
 //! Provides network packet capture and real-time traffic analysis capabilities.
 
 pub mod bridge;
+mod cache;
+mod capture;
 pub mod collector;
 pub mod config;
 pub mod error;
+pub mod signatures;
+pub mod sqlite;
+pub mod store;
+pub mod supervisor;
 
 // Re-export main types
-pub use bridge::ChannelBridge;
+pub use bridge::{BridgeStats, ChannelBridge, OverflowPolicy};
 pub use collector::{CollectorHandle, NetworkCollector, NetworkCollectorBuilder};
-pub use config::CollectorConfig;
+pub use config::{CaptureSource, CollectorConfig, DEFAULT_MAX_CONNECTIONS};
 pub use error::{CollectorError, Result};
+pub use signatures::SignatureReload;
+pub use sqlite::SqliteStore;
+pub use store::{Cidr, MemoryStore, ProfileChanges, ProfileFilter, ProfileStore};
+pub use supervisor::{CaptureState, CollectorStatus, RestartPolicy};
 
 /// Version of huginn-collector
 pub const VERSION: &str = env!("CARGO_PKG_VERSION");
@@ -20,6 +30,22 @@ pub const VERSION: &str = env!("CARGO_PKG_VERSION");
 #[cfg(test)]
 mod tests {
     use super::*;
+    use huginn_net::fingerprint_result::{
+        FingerprintResult, HttpRequestOutput, HttpResponseOutput,
+    };
+    use huginn_net::http::HttpDiagnosis;
+    use huginn_net::process::IpPort;
+    use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+    use std::sync::{Arc, Mutex};
+
+    /// Smallest p0f-format database: one TCP signature
+    const P0F_SIGNATURES: &str = "classes = win,unix,other
+
+[tcp:request]
+
+label = s:unix:Linux:3.11 and newer
+sig   = *:64:0:*:mss*20,10:mss,sok,ts,nop,ws:df,id+:0
+";
 
     #[test]
     fn test_version_is_set() {
@@ -30,15 +56,17 @@ mod tests {
     #[test]
     fn test_collector_config_default() {
         let config = CollectorConfig::default();
-        assert!(!config.interface.is_empty());
+        assert!(config.source.is_live());
+        assert!(!config.interface().unwrap().is_empty());
         assert!(config.buffer_size > 0);
         assert!(config.channel_buffer_size > 0);
+        assert_eq!(config.max_connections, Some(DEFAULT_MAX_CONNECTIONS));
     }
 
     #[test]
     fn test_collector_config_new() {
         let config = CollectorConfig::new("eth0".to_string());
-        assert_eq!(config.interface, "eth0");
+        assert_eq!(config.interface(), Some("eth0"));
         assert!(config.buffer_size > 0);
         assert!(config.channel_buffer_size > 0);
     }
@@ -62,6 +90,716 @@ mod tests {
 
         let invalid_config = CollectorConfig::new("".to_string());
         assert!(invalid_config.validate().is_err());
+
+        let mut endless_window = CollectorConfig::default();
+        endless_window.analyzer.nat_window_secs = u64::MAX;
+        assert!(endless_window.validate().is_err());
+    }
+
+    #[test]
+    fn test_collector_config_pcap_source() {
+        let config = CollectorConfig::from_pcap("/nonexistent/capture.pcapng");
+        assert!(!config.source.is_live());
+        assert_eq!(config.interface(), None);
+        assert_eq!(
+            config.source.to_string(),
+            "pcap file /nonexistent/capture.pcapng"
+        );
+
+        let error = config.validate().unwrap_err();
+        assert!(error.contains("Pcap file not found"));
+
+        let empty = CollectorConfig::from_pcap("");
+        assert!(empty.validate().is_err());
+    }
+
+    #[test]
+    fn test_signature_db_validation() {
+        let config = CollectorConfig::default().with_signature_db("/nonexistent/p0f.fp");
+        let error = config.validate().unwrap_err();
+        assert!(error.contains("Signature database not found"));
+
+        assert!(signatures::load_database(None).is_ok());
+        let error = signatures::load_database(Some(std::path::Path::new("/nonexistent/p0f.fp")))
+            .unwrap_err();
+        assert!(matches!(error, CollectorError::SignatureDatabase(_)));
+    }
+
+    const CHROME_ON_WINDOWS: &str = "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36";
+
+    fn empty_result() -> FingerprintResult {
+        FingerprintResult {
+            syn: None,
+            syn_ack: None,
+            mtu: None,
+            uptime: None,
+            http_request: None,
+            http_response: None,
+            tls_client: None,
+        }
+    }
+
+    fn endpoint(addr: &str) -> IpPort {
+        socket(addr.parse().unwrap())
+    }
+
+    fn header(name: &str, value: &str) -> huginn_net::http::Header {
+        huginn_net::http::Header {
+            optional: false,
+            name: name.to_string(),
+            value: Some(value.to_string()),
+        }
+    }
+
+    fn socket(addr: std::net::SocketAddr) -> IpPort {
+        IpPort {
+            ip: addr.ip(),
+            port: addr.port(),
+        }
+    }
+
+    /// A `GET /` from `client` to `server`
+    fn http_request(client: &str, server: &str, user_agent: &str) -> capture::Captured {
+        let result = FingerprintResult {
+            http_request: Some(HttpRequestOutput {
+                source: endpoint(client),
+                destination: endpoint(server),
+                lang: None,
+                diagnosis: HttpDiagnosis::None,
+                browser_matched: None,
+                sig: huginn_net::ObservableHttpRequest {
+                    lang: None,
+                    user_agent: Some(user_agent.to_string()),
+                    version: huginn_net::http::Version::V11,
+                    horder: vec![
+                        header("Host", "example.com"),
+                        header("User-Agent", user_agent),
+                        header("Accept", "*/*"),
+                    ],
+                    habsent: Vec::new(),
+                    expsw: String::new(),
+                },
+            }),
+            ..empty_result()
+        };
+        capture::Captured {
+            result,
+            wire: huginn_core::WireDetails {
+                http_method: Some("GET".to_string()),
+                ..Default::default()
+            },
+            captured_at: None,
+        }
+    }
+
+    /// The reply of `server` to a request from `client`
+    fn http_response(client: &str, server: &str, status_code: u16) -> capture::Captured {
+        let result = FingerprintResult {
+            http_response: Some(HttpResponseOutput {
+                source: endpoint(server),
+                destination: endpoint(client),
+                diagnosis: HttpDiagnosis::None,
+                web_server_matched: None,
+                sig: huginn_net::ObservableHttpResponse {
+                    version: huginn_net::http::Version::V11,
+                    horder: vec![header("Server", "nginx")],
+                    habsent: Vec::new(),
+                    expsw: String::new(),
+                },
+            }),
+            ..empty_result()
+        };
+        capture::Captured {
+            result,
+            wire: huginn_core::WireDetails {
+                http_status: Some(status_code),
+                ..Default::default()
+            },
+            captured_at: None,
+        }
+    }
+
+    /// Stands in for huginn-net replaying a file: sends its results, then returns
+    struct ScriptedCapture(Mutex<Vec<capture::Captured>>);
+
+    impl capture::Capture for ScriptedCapture {
+        fn run(
+            &self,
+            _source: &CaptureSource,
+            _database: &huginn_net::db::Database,
+            _buffer_size: usize,
+            sender: std::sync::mpsc::Sender<capture::Captured>,
+            _stop: &AtomicBool,
+        ) -> std::result::Result<(), String> {
+            for result in self.0.lock().unwrap().drain(..) {
+                sender.send(result).map_err(|e| e.to_string())?;
+            }
+            Ok(())
+        }
+    }
+
+    /// Replay `results` through a collector and wait until all of them were processed
+    async fn replay(name: &str, results: Vec<capture::Captured>) -> CollectorHandle {
+        let path =
+            std::env::temp_dir().join(format!("huginn-{}-{}.pcap", name, std::process::id()));
+        std::fs::write(&path, b"").unwrap();
+        let mut collector = NetworkCollectorBuilder::from_pcap(&path).build().unwrap();
+        std::fs::remove_file(path).unwrap();
+
+        collector.capture = Arc::new(ScriptedCapture(Mutex::new(results)));
+        let handle = collector.start().unwrap();
+        handle.wait_for_capture().await.unwrap();
+        handle
+    }
+
+    #[tokio::test]
+    async fn test_http_response_keeps_the_request() {
+        let (client, server) = ("10.0.0.5:40001", "93.184.216.34:80");
+        let handle = replay(
+            "http",
+            vec![
+                http_request(client, server, CHROME_ON_WINDOWS),
+                http_response(client, server, 200),
+            ],
+        )
+        .await;
+
+        let http = handle
+            .get_profile("10.0.0.5")
+            .await
+            .unwrap()
+            .unwrap()
+            .http
+            .unwrap();
+        let request = http.request.unwrap();
+        assert_eq!(request.method.as_deref(), Some("GET"));
+        assert_eq!(request.user_agent.as_deref(), Some(CHROME_ON_WINDOWS));
+        assert!(request.ja4h.is_some());
+        assert!(http.user_agent.is_some());
+        assert_eq!(http.response.unwrap().status.as_deref(), Some("200"));
+
+        assert!(handle.stop().await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_connections_are_polled_incrementally() {
+        let server = "93.184.216.34:80";
+        let handle = replay(
+            "connections",
+            vec![
+                http_request("10.0.0.5:40001", server, CHROME_ON_WINDOWS),
+                http_request("10.0.0.6:40002", server, CHROME_ON_WINDOWS),
+            ],
+        )
+        .await;
+
+        let mut keys = handle.get_connection_keys().await.unwrap();
+        keys.sort();
+        assert_eq!(
+            keys,
+            [
+                "10.0.0.5:40001-93.184.216.34:80",
+                "10.0.0.6:40002-93.184.216.34:80"
+            ]
+        );
+
+        let newest = handle
+            .get_connections()
+            .await
+            .unwrap()
+            .values()
+            .map(|connection| connection.last_updated)
+            .max()
+            .unwrap();
+        assert!(!handle
+            .get_connections_since(newest)
+            .await
+            .unwrap()
+            .is_empty());
+        assert!(handle
+            .get_connections_since(newest + chrono::Duration::seconds(1))
+            .await
+            .unwrap()
+            .is_empty());
+
+        assert!(handle.stop().await.is_ok());
+    }
+
+    /// Ethernet/IPv4/TCP frame; a SYN carries Linux's options
+    fn tcp_frame(client: &str, server: &str, flags: u8, payload: &[u8]) -> Vec<u8> {
+        let (client, server): (std::net::SocketAddrV4, std::net::SocketAddrV4) =
+            (client.parse().unwrap(), server.parse().unwrap());
+        let options: &[u8] = if flags == 0x02 {
+            // mss 1460, sok, ts, nop, ws 7
+            &[
+                2, 4, 0x05, 0xb4, 4, 2, 8, 10, 0, 0, 0, 1, 0, 0, 0, 0, 1, 3, 3, 7,
+            ]
+        } else {
+            &[]
+        };
+
+        let mut frame = vec![0u8; 12];
+        frame.extend_from_slice(&0x0800u16.to_be_bytes());
+        let mut ip = [0u8; 20];
+        ip[0] = 0x45;
+        ip[2..4].copy_from_slice(&((40 + options.len() + payload.len()) as u16).to_be_bytes());
+        ip[6] = 0x40;
+        ip[8] = 64;
+        ip[9] = 6;
+        ip[12..16].copy_from_slice(&client.ip().octets());
+        ip[16..20].copy_from_slice(&server.ip().octets());
+        frame.extend_from_slice(&ip);
+
+        let mut tcp = [0u8; 20];
+        tcp[0..2].copy_from_slice(&client.port().to_be_bytes());
+        tcp[2..4].copy_from_slice(&server.port().to_be_bytes());
+        tcp[12] = ((20 + options.len()) as u8 / 4) << 4;
+        tcp[13] = flags;
+        tcp[14..16].copy_from_slice(&64240u16.to_be_bytes());
+        frame.extend_from_slice(&tcp);
+        frame.extend_from_slice(options);
+        frame.extend_from_slice(payload);
+        frame
+    }
+
+    /// Write `frames` to a pcap file, each recorded at its offset from 2024-03-01T12:00:00Z
+    fn write_pcap(name: &str, frames: &[(u64, Vec<u8>)]) -> std::path::PathBuf {
+        let path =
+            std::env::temp_dir().join(format!("huginn-{}-{}.pcap", name, std::process::id()));
+        let mut writer =
+            pcap_file::pcap::PcapWriter::new(std::fs::File::create(&path).unwrap()).unwrap();
+        for (offset, frame) in frames {
+            let timestamp = std::time::Duration::from_secs(RECORDED_AT + offset);
+            writer
+                .write_packet(&pcap_file::pcap::PcapPacket::new(
+                    timestamp,
+                    frame.len() as u32,
+                    frame,
+                ))
+                .unwrap();
+        }
+        path
+    }
+
+    /// Write `frames` to a pcapng file with microsecond timestamps, like `write_pcap`
+    fn write_pcapng(name: &str, frames: &[(u64, Vec<u8>)]) -> std::path::PathBuf {
+        fn block(kind: u32, body: &[u8]) -> Vec<u8> {
+            let len = (12 + body.len() as u32).to_le_bytes();
+            [&kind.to_le_bytes()[..], &len, body, &len].concat()
+        }
+
+        // Little-endian section header of version 1.0 with an unknown length
+        let mut file = block(
+            0x0a0d0d0a,
+            &[
+                0x4d, 0x3c, 0x2b, 0x1a, 1, 0, 0, 0, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+            ],
+        );
+        // Ethernet interface without options
+        file.extend(block(1, &[1, 0, 0, 0, 0, 0, 0, 0]));
+        for (offset, frame) in frames {
+            let micros = (RECORDED_AT + offset) * 1_000_000;
+            let len = frame.len() as u32;
+            let mut body: Vec<u8> = [0, (micros >> 32) as u32, micros as u32, len, len]
+                .iter()
+                .flat_map(|field| field.to_le_bytes())
+                .collect();
+            body.extend_from_slice(frame);
+            body.resize(body.len().next_multiple_of(4), 0);
+            file.extend(block(6, &body));
+        }
+
+        let path =
+            std::env::temp_dir().join(format!("huginn-{}-{}.pcapng", name, std::process::id()));
+        std::fs::write(&path, file).unwrap();
+        path
+    }
+
+    /// 2024-03-01T12:00:00Z, when the packets of `write_pcap` were recorded
+    const RECORDED_AT: u64 = 1_709_294_400;
+
+    fn recorded_at(offset: u64) -> chrono::DateTime<chrono::Utc> {
+        chrono::DateTime::from_timestamp((RECORDED_AT + offset) as i64, 0).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_replayed_packets_keep_their_recording_time() {
+        let (client, server) = ("10.0.0.5:40001", "93.184.216.34:80");
+        let path = write_pcap(
+            "recorded",
+            &[
+                (0, tcp_frame(client, server, 0x02, b"")),
+                (
+                    2,
+                    tcp_frame(
+                        client,
+                        server,
+                        0x18,
+                        b"GET /index.html HTTP/1.1\r\nHost: example.com\r\nAccept: */*\r\n\r\n",
+                    ),
+                ),
+            ],
+        );
+        let handle = NetworkCollectorBuilder::from_pcap(&path)
+            .build()
+            .unwrap()
+            .start()
+            .unwrap();
+        handle.wait_for_capture().await.unwrap();
+        std::fs::remove_file(path).unwrap();
+
+        let profile = handle.get_profile("10.0.0.5").await.unwrap().unwrap();
+        assert_eq!(profile.metadata.first_seen, recorded_at(0));
+        assert_eq!(profile.metadata.last_updated, recorded_at(2));
+        let request = profile.http.unwrap().request.unwrap();
+        assert_eq!(request.method.as_deref(), Some("GET"));
+        assert_eq!(request.uri.as_deref(), Some("/index.html"));
+
+        assert!(handle.stop().await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_pcapng_files_are_replayed() {
+        let (client, server) = ("10.0.0.5:40001", "93.184.216.34:80");
+        let path = write_pcapng(
+            "recorded-ng",
+            &[
+                (0, tcp_frame(client, server, 0x02, b"")),
+                (
+                    2,
+                    tcp_frame(
+                        client,
+                        server,
+                        0x18,
+                        b"GET /index.html HTTP/1.1\r\nHost: example.com\r\nAccept: */*\r\n\r\n",
+                    ),
+                ),
+            ],
+        );
+        let handle = NetworkCollectorBuilder::from_pcap(&path)
+            .build()
+            .unwrap()
+            .start()
+            .unwrap();
+        handle.wait_for_capture().await.unwrap();
+        std::fs::remove_file(path).unwrap();
+
+        let profile = handle.get_profile("10.0.0.5").await.unwrap().unwrap();
+        assert_eq!(profile.metadata.first_seen, recorded_at(0));
+        assert_eq!(profile.metadata.last_updated, recorded_at(2));
+        let request = profile.http.unwrap().request.unwrap();
+        assert_eq!(request.uri.as_deref(), Some("/index.html"));
+
+        assert!(handle.stop().await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_replay_goes_idle_against_the_recording() {
+        let server = "93.184.216.34:80";
+        let path = write_pcap(
+            "recording-idle",
+            &[
+                (0, tcp_frame("10.0.0.5:40001", server, 0x02, b"")),
+                (600, tcp_frame("10.0.0.6:40001", server, 0x02, b"")),
+            ],
+        );
+        let handle = NetworkCollectorBuilder::from_pcap(&path)
+            .idle_ttl(std::time::Duration::from_secs(60))
+            .sweep_interval(std::time::Duration::from_secs(1))
+            .build()
+            .unwrap()
+            .start()
+            .unwrap();
+        handle.wait_for_capture().await.unwrap();
+        std::fs::remove_file(path).unwrap();
+
+        // Ten minutes before the end of the recording is idle; the end itself is not,
+        // however long ago it was recorded
+        let deadline = std::time::Instant::now() + std::time::Duration::from_secs(5);
+        while handle.get_profile("10.0.0.5").await.unwrap().is_some() {
+            assert!(
+                std::time::Instant::now() < deadline,
+                "profile never expired"
+            );
+            tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(1100)).await;
+        assert!(handle.get_profile("10.0.0.6").await.unwrap().is_some());
+
+        assert!(handle.stop().await.is_ok());
+    }
+
+    /// Stands in for huginn-net on an idle interface: no packet ever arrives,
+    /// so it only returns when asked to stop
+    #[derive(Default)]
+    struct IdleCapture {
+        started: AtomicU32,
+        stopped: AtomicU32,
+    }
+
+    impl IdleCapture {
+        fn started(&self) -> u32 {
+            self.started.load(Ordering::SeqCst)
+        }
+
+        fn stopped(&self) -> u32 {
+            self.stopped.load(Ordering::SeqCst)
+        }
+    }
+
+    impl capture::Capture for IdleCapture {
+        fn run(
+            &self,
+            _source: &CaptureSource,
+            _database: &huginn_net::db::Database,
+            _buffer_size: usize,
+            _sender: std::sync::mpsc::Sender<capture::Captured>,
+            stop: &AtomicBool,
+        ) -> std::result::Result<(), String> {
+            self.started.fetch_add(1, Ordering::SeqCst);
+            while !stop.load(Ordering::Relaxed) {
+                std::thread::sleep(capture::READ_TIMEOUT);
+            }
+            self.stopped.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        }
+    }
+
+    /// Start a live collector on `idle` and wait until its capture is running
+    async fn start_idle(
+        builder: NetworkCollectorBuilder,
+        idle: &Arc<IdleCapture>,
+    ) -> CollectorHandle {
+        let mut collector = builder.build().unwrap();
+        collector.capture = idle.clone();
+        let handle = collector.start().unwrap();
+
+        while idle.started() == 0 {
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        handle
+    }
+
+    #[tokio::test]
+    async fn test_stop_returns_promptly_without_packets() {
+        let idle = Arc::new(IdleCapture::default());
+        let handle = start_idle(
+            NetworkCollectorBuilder::new("huginn-idle0".to_string()),
+            &idle,
+        )
+        .await;
+        assert_eq!(handle.status().state, CaptureState::Running);
+
+        let started = std::time::Instant::now();
+        assert!(handle
+            .stop_within(std::time::Duration::from_secs(5))
+            .await
+            .is_ok());
+        assert!(started.elapsed() < capture::READ_TIMEOUT * 5);
+        assert_eq!(idle.stopped(), 1);
+    }
+
+    /// Stands in for huginn-net on a busy interface: sends results until that fails
+    struct BusyCapture;
+
+    impl capture::Capture for BusyCapture {
+        fn run(
+            &self,
+            _source: &CaptureSource,
+            _database: &huginn_net::db::Database,
+            _buffer_size: usize,
+            sender: std::sync::mpsc::Sender<capture::Captured>,
+            _stop: &AtomicBool,
+        ) -> std::result::Result<(), String> {
+            let mut port = 1024;
+            while sender
+                .send(http_request(
+                    &format!("10.0.0.5:{}", port),
+                    "93.184.216.34:80",
+                    CHROME_ON_WINDOWS,
+                ))
+                .is_ok()
+            {
+                port = port % 60000 + 1024;
+            }
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_stop_under_traffic_is_clean() {
+        let mut collector = NetworkCollectorBuilder::new("huginn-busy0".to_string())
+            .channel_buffer_size(1)
+            .build()
+            .unwrap();
+        collector.capture = Arc::new(BusyCapture);
+        let handle = collector.start().unwrap();
+
+        while handle.bridge_stats().forwarded < 10 {
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        assert!(handle.stop().await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_idle_sweep_follows_the_collector_clock() {
+        let start = chrono::DateTime::parse_from_rfc3339("2024-03-01T12:00:00Z")
+            .unwrap()
+            .with_timezone(&chrono::Utc);
+        let store = MemoryStore::new();
+        store
+            .put(
+                "10.0.0.5",
+                huginn_core::TrafficProfile::new_at("10.0.0.5".parse().unwrap(), 40001, start),
+            )
+            .unwrap();
+        let clock = Arc::new(huginn_core::FixedClock::new(
+            start + chrono::Duration::seconds(30),
+        ));
+
+        let idle = Arc::new(IdleCapture::default());
+        let handle = start_idle(
+            NetworkCollectorBuilder::new("huginn-idle0".to_string())
+                .store(store)
+                .clock(clock.clone())
+                .idle_ttl(std::time::Duration::from_secs(60))
+                .sweep_interval(std::time::Duration::from_secs(1)),
+            &idle,
+        )
+        .await;
+
+        // The first sweep runs right away, when the profile is 30s old by the clock
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+        assert_eq!(handle.get_profile_count().await.unwrap(), 1);
+
+        clock.advance(chrono::Duration::seconds(60));
+        let deadline = std::time::Instant::now() + std::time::Duration::from_secs(5);
+        while handle.get_profile_count().await.unwrap() > 0 {
+            assert!(
+                std::time::Instant::now() < deadline,
+                "profile never expired"
+            );
+            tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        }
+
+        assert!(handle.stop().await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_signature_reload_replaces_the_capture() {
+        let path = std::env::temp_dir().join(format!("huginn-reload-{}.fp", std::process::id()));
+        std::fs::write(&path, P0F_SIGNATURES).unwrap();
+
+        let idle = Arc::new(IdleCapture::default());
+        let handle = start_idle(
+            NetworkCollectorBuilder::new("huginn-idle0".to_string()).signature_db(&path),
+            &idle,
+        )
+        .await;
+
+        let reload = handle.reload_signatures().await.unwrap();
+        assert_eq!(reload.generation, 2);
+        assert_eq!(reload.path.as_deref(), Some(path.as_path()));
+        // The old generation was joined before the reload returned
+        assert_eq!(idle.stopped(), 1);
+
+        // A broken file leaves the running capture and its database alone
+        std::fs::write(&path, "not a signature database").unwrap();
+        let error = handle.reload_signatures().await.unwrap_err();
+        assert!(matches!(error, CollectorError::SignatureDatabase(_)));
+        assert_eq!(idle.stopped(), 1);
+
+        assert!(handle.stop().await.is_ok());
+        assert_eq!((idle.started(), idle.stopped()), (2, 2));
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_signature_reload_is_rejected_for_pcap_replays() {
+        let path = std::env::temp_dir().join(format!("huginn-reload-{}.pcap", std::process::id()));
+        std::fs::write(&path, b"").unwrap();
+
+        let handle = NetworkCollectorBuilder::from_pcap(&path)
+            .build()
+            .unwrap()
+            .start()
+            .unwrap();
+        let error = handle.reload_signatures().await.unwrap_err();
+        assert!(matches!(error, CollectorError::Unsupported(_)));
+
+        assert!(handle.stop().await.is_ok());
+        std::fs::remove_file(path).unwrap();
+    }
+
+    /// A live capture that returns cleanly right away, as if the interface went away
+    struct EndingCapture;
+
+    impl capture::Capture for EndingCapture {
+        fn run(
+            &self,
+            _source: &CaptureSource,
+            _database: &huginn_net::db::Database,
+            _buffer_size: usize,
+            _sender: std::sync::mpsc::Sender<capture::Captured>,
+            _stop: &AtomicBool,
+        ) -> std::result::Result<(), String> {
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_live_capture_ending_on_its_own_is_restarted() {
+        let mut collector = NetworkCollectorBuilder::new("huginn-gone0".to_string())
+            .restart_policy(RestartPolicy {
+                initial_backoff_ms: 10,
+                max_backoff_ms: 20,
+                max_restarts: Some(1),
+            })
+            .build()
+            .unwrap();
+        collector.capture = Arc::new(EndingCapture);
+        let handle = collector.start().unwrap();
+
+        let deadline = std::time::Instant::now() + std::time::Duration::from_secs(5);
+        while handle.status().state != CaptureState::Failed {
+            assert!(std::time::Instant::now() < deadline, "capture never failed");
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+
+        let status = handle.status();
+        assert_eq!(status.restarts, 1);
+        assert_eq!(
+            status.last_error.as_deref(),
+            Some("live capture ended unexpectedly")
+        );
+        assert!(handle.stop().await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_failed_capture_is_restarted_then_reported() {
+        let handle = NetworkCollectorBuilder::new("huginn-missing0".to_string())
+            .restart_policy(RestartPolicy {
+                initial_backoff_ms: 10,
+                max_backoff_ms: 20,
+                max_restarts: Some(2),
+            })
+            .build()
+            .unwrap()
+            .start()
+            .unwrap();
+
+        let deadline = std::time::Instant::now() + std::time::Duration::from_secs(5);
+        while handle.status().state != CaptureState::Failed {
+            assert!(std::time::Instant::now() < deadline, "capture never failed");
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+
+        let status = handle.status();
+        assert_eq!(status.restarts, 2);
+        assert!(status.last_error.is_some());
+        assert!(handle.is_running(), "profiles stay queryable");
+
+        assert!(handle.stop().await.is_ok());
     }
 
     #[test]
@@ -71,7 +809,7 @@ mod tests {
             .with_channel_buffer_size(2000)
             .with_verbose(true);
 
-        assert_eq!(config.interface, "wlan0");
+        assert_eq!(config.interface(), Some("wlan0"));
         assert_eq!(config.buffer_size, 200);
         assert_eq!(config.channel_buffer_size, 2000);
         assert!(config.verbose);
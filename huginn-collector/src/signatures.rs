@@ -0,0 +1,29 @@
+use crate::error::{CollectorError, Result};
+use huginn_net::db::Database;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Outcome of swapping the signature database of a running collector
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SignatureReload {
+    /// Database file now in use (`None` means the built-in signatures)
+    pub path: Option<PathBuf>,
+    /// Capture generation running with the new database (the initial capture is 1)
+    pub generation: u64,
+}
+
+/// Load a signature database in p0f format, or the built-in one when no path is given
+pub fn load_database(path: Option<&Path>) -> Result<Database> {
+    let path = match path {
+        Some(path) => path,
+        None => return Ok(Database::default()),
+    };
+
+    let content = std::fs::read_to_string(path).map_err(|e| {
+        CollectorError::signature_database(format!("Failed to read {}: {}", path.display(), e))
+    })?;
+
+    content.parse::<Database>().map_err(|e| {
+        CollectorError::signature_database(format!("Invalid {}: {}", path.display(), e))
+    })
+}
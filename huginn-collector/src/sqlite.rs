@@ -0,0 +1,495 @@
+use crate::error::{CollectorError, Result};
+use crate::store::{
+    mapped, profile_ja4, profile_ja4h, profile_ja4t, ProfileChanges, ProfileFilter, ProfileStore,
+};
+use chrono::{DateTime, Utc};
+use huginn_core::TrafficProfile;
+use rusqlite::types::Value;
+use rusqlite::{params, params_from_iter, Connection, OptionalExtension};
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, MutexGuard};
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS profiles (
+    key          TEXT PRIMARY KEY,
+    ip           BLOB NOT NULL,
+    first_seen   INTEGER NOT NULL,
+    last_updated INTEGER NOT NULL,
+    ja4          TEXT,
+    ja4h         TEXT,
+    ja4t         TEXT,
+    profile      TEXT NOT NULL,
+    history      TEXT NOT NULL,
+    sequence     INTEGER NOT NULL
+);
+CREATE INDEX IF NOT EXISTS profiles_ip ON profiles (ip);
+CREATE INDEX IF NOT EXISTS profiles_last_updated ON profiles (last_updated);
+CREATE INDEX IF NOT EXISTS profiles_ja4 ON profiles (ja4);
+CREATE INDEX IF NOT EXISTS profiles_ja4h ON profiles (ja4h);
+CREATE INDEX IF NOT EXISTS profiles_ja4t ON profiles (ja4t);
+";
+
+/// Databases created before the change sequence lack its column; their rows
+/// count as the first change, so a poller starting from 0 still reads them
+const SEQUENCE_COLUMN: &str = "
+ALTER TABLE profiles ADD COLUMN sequence INTEGER NOT NULL DEFAULT 1;
+";
+
+const SEQUENCE_INDEX: &str = "
+CREATE INDEX IF NOT EXISTS profiles_sequence ON profiles (sequence);
+";
+
+/// Profiles persisted in an embedded SQLite database
+///
+/// Each profile is stored as JSON next to indexed columns for its IP (as a
+/// 16-byte v4-mapped address, so CIDR queries are range scans), first/last
+/// seen times (microseconds since the epoch) and JA4/JA4H/JA4T fingerprints.
+/// The NAT and uptime histories go in their own column and are only read back
+/// by `get`, which is what the collector merges new results into.
+/// Every write stamps its row with the next change number for
+/// `changed_since`; numbers carry on from the highest one stored when the
+/// database is reopened.
+/// Calls block on SQLite; the database runs in WAL mode so readers don't wait
+/// for the collector's writes.
+pub struct SqliteStore {
+    connection: Mutex<Connection>,
+    /// Latest change number handed out; only touched with the connection locked
+    sequence: AtomicU64,
+}
+
+impl SqliteStore {
+    /// Open (or create) the database at `path`
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let connection = Connection::open(path)?;
+        connection.pragma_update(None, "journal_mode", "WAL")?;
+        connection.pragma_update(None, "synchronous", "NORMAL")?;
+        Self::with_connection(connection)
+    }
+
+    /// Create a database that lives in memory only (mainly for tests)
+    pub fn in_memory() -> Result<Self> {
+        Self::with_connection(Connection::open_in_memory()?)
+    }
+
+    fn with_connection(connection: Connection) -> Result<Self> {
+        connection.execute_batch(SCHEMA)?;
+        let has_sequence = connection
+            .prepare("SELECT sequence FROM profiles LIMIT 0")
+            .is_ok();
+        if !has_sequence {
+            connection.execute_batch(SEQUENCE_COLUMN)?;
+        }
+        connection.execute_batch(SEQUENCE_INDEX)?;
+
+        let sequence: i64 = connection.query_row(
+            "SELECT COALESCE(MAX(sequence), 0) FROM profiles",
+            [],
+            |row| row.get(0),
+        )?;
+        Ok(Self {
+            connection: Mutex::new(connection),
+            sequence: AtomicU64::new(u64::try_from(sequence).unwrap_or_default()),
+        })
+    }
+
+    fn connection(&self) -> MutexGuard<'_, Connection> {
+        self.connection.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+fn micros(time: DateTime<Utc>) -> i64 {
+    time.timestamp_micros()
+}
+
+fn decode<T: serde::de::DeserializeOwned>(key: &str, json: &str) -> Result<T> {
+    serde_json::from_str(json)
+        .map_err(|e| CollectorError::store(format!("Stored profile {} is corrupt: {}", key, e)))
+}
+
+fn encode<T: serde::Serialize>(key: &str, value: &T) -> Result<String> {
+    serde_json::to_string(value)
+        .map_err(|e| CollectorError::store(format!("Cannot encode profile {}: {}", key, e)))
+}
+
+fn deleted_keys(connection: &Connection, sql: &str, param: i64) -> Result<Vec<String>> {
+    let mut statement = connection.prepare(sql)?;
+    let keys = statement
+        .query_map([param], |row| row.get(0))?
+        .collect::<rusqlite::Result<_>>()?;
+    Ok(keys)
+}
+
+impl ProfileStore for SqliteStore {
+    fn get(&self, key: &str) -> Result<Option<TrafficProfile>> {
+        let row: Option<(String, String)> = self
+            .connection()
+            .query_row(
+                "SELECT profile, history FROM profiles WHERE key = ?1",
+                [key],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()?;
+        let Some((json, history)) = row else {
+            return Ok(None);
+        };
+        let mut profile: TrafficProfile = decode(key, &json)?;
+        profile.restore_tracking_history(decode(key, &history)?);
+        Ok(Some(profile))
+    }
+
+    fn put(&self, key: &str, profile: TrafficProfile) -> Result<()> {
+        let json = encode(key, &profile)?;
+        let history = encode(key, &profile.tracking_history())?;
+        let connection = self.connection();
+        let sequence = self.sequence.load(Ordering::Relaxed) + 1;
+        connection.execute(
+            "INSERT INTO profiles (key, ip, first_seen, last_updated, ja4, ja4h, ja4t, profile, history, sequence)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)
+             ON CONFLICT (key) DO UPDATE SET
+                 ip = excluded.ip,
+                 first_seen = excluded.first_seen,
+                 last_updated = excluded.last_updated,
+                 ja4 = excluded.ja4,
+                 ja4h = excluded.ja4h,
+                 ja4t = excluded.ja4t,
+                 profile = excluded.profile,
+                 history = excluded.history,
+                 sequence = excluded.sequence",
+            params![
+                key,
+                mapped(profile.ip).to_be_bytes().to_vec(),
+                micros(profile.metadata.first_seen),
+                micros(profile.metadata.last_updated),
+                profile_ja4(&profile),
+                profile_ja4h(&profile),
+                profile_ja4t(&profile),
+                json,
+                history,
+                i64::try_from(sequence).unwrap_or(i64::MAX),
+            ],
+        )?;
+        self.sequence.store(sequence, Ordering::Relaxed);
+        Ok(())
+    }
+
+    fn remove(&self, key: &str) -> Result<bool> {
+        let removed = self
+            .connection()
+            .execute("DELETE FROM profiles WHERE key = ?1", [key])?;
+        Ok(removed > 0)
+    }
+
+    fn clear(&self) -> Result<()> {
+        self.connection().execute("DELETE FROM profiles", [])?;
+        Ok(())
+    }
+
+    fn len(&self) -> Result<usize> {
+        let count: i64 =
+            self.connection()
+                .query_row("SELECT COUNT(*) FROM profiles", [], |row| row.get(0))?;
+        Ok(usize::try_from(count).unwrap_or_default())
+    }
+
+    fn keys(&self) -> Result<Vec<String>> {
+        let connection = self.connection();
+        let mut statement = connection.prepare("SELECT key FROM profiles")?;
+        let keys = statement
+            .query_map([], |row| row.get(0))?
+            .collect::<rusqlite::Result<_>>()?;
+        Ok(keys)
+    }
+
+    fn query(&self, filter: &ProfileFilter) -> Result<Vec<(String, TrafficProfile)>> {
+        // Narrow down with the indexed columns; `ProfileFilter::matches` has the final say
+        let mut conditions = Vec::new();
+        let mut values = Vec::new();
+        if let Some(cidr) = filter.cidr {
+            let (low, high) = cidr.range();
+            conditions.push("ip BETWEEN ? AND ?");
+            values.push(Value::Blob(low.to_be_bytes().to_vec()));
+            values.push(Value::Blob(high.to_be_bytes().to_vec()));
+        }
+        if let Some(since) = filter.since {
+            conditions.push("last_updated >= ?");
+            values.push(Value::Integer(micros(since)));
+        }
+        if let Some(until) = filter.until {
+            conditions.push("first_seen <= ?");
+            values.push(Value::Integer(micros(until)));
+        }
+        for (column, fingerprint) in [
+            ("ja4 = ?", &filter.ja4),
+            ("ja4h = ?", &filter.ja4h),
+            ("ja4t = ?", &filter.ja4t),
+        ] {
+            if let Some(fingerprint) = fingerprint {
+                conditions.push(column);
+                values.push(Value::Text(fingerprint.clone()));
+            }
+        }
+
+        let mut sql = "SELECT key, profile FROM profiles".to_string();
+        if !conditions.is_empty() {
+            sql.push_str(" WHERE ");
+            sql.push_str(&conditions.join(" AND "));
+        }
+        sql.push_str(" ORDER BY last_updated DESC, key");
+
+        let connection = self.connection();
+        let mut statement = connection.prepare(&sql)?;
+        let mut rows = statement.query(params_from_iter(values))?;
+
+        let mut profiles = Vec::new();
+        while let Some(row) = rows.next()? {
+            if filter.limit.is_some_and(|limit| profiles.len() >= limit) {
+                break;
+            }
+            let key: String = row.get(0)?;
+            let json: String = row.get(1)?;
+            let profile: TrafficProfile = decode(&key, &json)?;
+            if filter.matches(&profile) {
+                profiles.push((key, profile));
+            }
+        }
+        Ok(profiles)
+    }
+
+    fn evict_lru(&self, max: usize) -> Result<Vec<String>> {
+        deleted_keys(
+            &self.connection(),
+            "DELETE FROM profiles WHERE key IN (
+                 SELECT key FROM profiles ORDER BY last_updated
+                 LIMIT max((SELECT COUNT(*) FROM profiles) - ?1, 0)
+             ) RETURNING key",
+            i64::try_from(max).unwrap_or(i64::MAX),
+        )
+    }
+
+    fn expire_idle(&self, cutoff: DateTime<Utc>) -> Result<Vec<String>> {
+        deleted_keys(
+            &self.connection(),
+            "DELETE FROM profiles WHERE last_updated < ?1 RETURNING key",
+            micros(cutoff),
+        )
+    }
+
+    fn newest_update(&self) -> Result<Option<DateTime<Utc>>> {
+        let newest: Option<i64> =
+            self.connection()
+                .query_row("SELECT MAX(last_updated) FROM profiles", [], |row| {
+                    row.get(0)
+                })?;
+        Ok(newest.and_then(DateTime::from_timestamp_micros))
+    }
+
+    fn changed_since(&self, sequence: u64) -> Result<ProfileChanges> {
+        let connection = self.connection();
+        let mut statement =
+            connection.prepare("SELECT key, profile FROM profiles WHERE sequence > ?1")?;
+        let mut rows = statement.query([i64::try_from(sequence).unwrap_or(i64::MAX)])?;
+
+        let mut profiles = Vec::new();
+        while let Some(row) = rows.next()? {
+            let key: String = row.get(0)?;
+            let json: String = row.get(1)?;
+            let profile = decode(&key, &json)?;
+            profiles.push((key, profile));
+        }
+        Ok(ProfileChanges {
+            sequence: self.sequence.load(Ordering::Relaxed),
+            profiles,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn profile(ip: &str, last_updated: i64) -> TrafficProfile {
+        let at = Utc.timestamp_opt(1_700_000_000 + last_updated, 0).unwrap();
+        TrafficProfile::new_at(ip.parse().unwrap(), 40001, at)
+    }
+
+    #[test]
+    fn test_profiles_survive_reopening() {
+        let path = std::env::temp_dir().join(format!("huginn-store-{}.db", std::process::id()));
+        {
+            let store = SqliteStore::open(&path).unwrap();
+            let mut stored = profile("10.0.0.5", 0);
+            stored.link_type = Some("PPPoE".to_string());
+            store.put("10.0.0.5", stored).unwrap();
+        }
+
+        let store = SqliteStore::open(&path).unwrap();
+        let restored = store.get("10.0.0.5").unwrap().unwrap();
+        assert_eq!(restored.link_type.as_deref(), Some("PPPoE"));
+        assert_eq!(store.len().unwrap(), 1);
+
+        // Change numbers carry on where the previous run stopped
+        let seen = store.changed_since(0).unwrap();
+        assert_eq!(seen.sequence, 1);
+        store.put("10.0.0.6", profile("10.0.0.6", 0)).unwrap();
+        let changes = store.changed_since(seen.sequence).unwrap();
+        assert_eq!(changes.sequence, 2);
+        assert_eq!(changes.profiles.len(), 1);
+
+        drop(store);
+        for suffix in ["", "-wal", "-shm"] {
+            let _ = std::fs::remove_file(format!("{}{}", path.display(), suffix));
+        }
+    }
+
+    #[test]
+    fn test_queries_match_the_memory_store() {
+        let store = SqliteStore::in_memory().unwrap();
+        for (ip, last_updated) in [
+            ("10.0.0.1", 100),
+            ("10.0.0.2", 300),
+            ("10.9.0.1", 200),
+            ("2001:db8::1", 50),
+        ] {
+            store.put(ip, profile(ip, last_updated)).unwrap();
+        }
+
+        let keys = |filter: ProfileFilter| -> Vec<String> {
+            store
+                .query(&filter)
+                .unwrap()
+                .into_iter()
+                .map(|(key, _)| key)
+                .collect()
+        };
+
+        assert_eq!(
+            keys(ProfileFilter {
+                cidr: Some("10.0.0.0/8".parse().unwrap()),
+                ..Default::default()
+            }),
+            ["10.0.0.2", "10.9.0.1", "10.0.0.1"]
+        );
+        assert_eq!(
+            keys(ProfileFilter {
+                cidr: Some("2001:db8::/32".parse().unwrap()),
+                ..Default::default()
+            }),
+            ["2001:db8::1"]
+        );
+        assert_eq!(
+            keys(ProfileFilter {
+                since: Some(Utc.timestamp_opt(1_700_000_150, 0).unwrap()),
+                limit: Some(1),
+                ..Default::default()
+            }),
+            ["10.0.0.2"]
+        );
+        assert!(keys(ProfileFilter {
+            ja4: Some("t13d1516h2_8daaf6152771_02713d6af862".to_string()),
+            ..Default::default()
+        })
+        .is_empty());
+
+        let mut all_keys = store.keys().unwrap();
+        all_keys.sort();
+        assert_eq!(
+            all_keys,
+            ["10.0.0.1", "10.0.0.2", "10.9.0.1", "2001:db8::1"]
+        );
+
+        assert_eq!(store.evict_lru(3).unwrap(), ["2001:db8::1"]);
+        let cutoff = Utc.timestamp_opt(1_700_000_150, 0).unwrap();
+        assert_eq!(store.expire_idle(cutoff).unwrap(), ["10.0.0.1"]);
+        assert_eq!(
+            store.newest_update().unwrap(),
+            Some(Utc.timestamp_opt(1_700_000_300, 0).unwrap())
+        );
+        assert!(store.remove("10.0.0.2").unwrap());
+        assert!(!store.remove("10.0.0.2").unwrap());
+    }
+
+    #[test]
+    fn test_changes_follow_writes_not_update_times() {
+        let store = SqliteStore::in_memory().unwrap();
+        store.put("10.0.0.1", profile("10.0.0.1", 300)).unwrap();
+        let seen = store.changed_since(0).unwrap();
+        assert_eq!(seen.profiles.len(), 1);
+
+        // A late result carries an older update time than anything seen so far
+        store.put("10.0.0.2", profile("10.0.0.2", 100)).unwrap();
+        let changes = store.changed_since(seen.sequence).unwrap();
+        let keys: Vec<&str> = changes
+            .profiles
+            .iter()
+            .map(|(key, _)| key.as_str())
+            .collect();
+        assert_eq!(keys, ["10.0.0.2"]);
+
+        // Removing the newest row never hands its change number out again
+        assert!(store.remove("10.0.0.2").unwrap());
+        store.put("10.0.0.3", profile("10.0.0.3", 50)).unwrap();
+        let after_remove = store.changed_since(changes.sequence).unwrap();
+        assert!(after_remove.sequence > changes.sequence);
+        assert_eq!(after_remove.profiles.len(), 1);
+        assert!(store
+            .changed_since(after_remove.sequence)
+            .unwrap()
+            .profiles
+            .is_empty());
+    }
+
+    #[test]
+    fn test_tracking_history_survives_the_store() {
+        let analyzer = huginn_core::HuginnAnalyzer::new();
+        let store = SqliteStore::in_memory().unwrap();
+        let start = Utc.timestamp_opt(1_700_000_000, 0).unwrap();
+
+        // One host reboots, then a second one shows up behind the same IP
+        for (signature, uptime_mins, at_mins) in [
+            (
+                "4:64+0:0:1460:mss*20,10:mss,sok,ts,nop,ws:df,id+:0",
+                Some(3000),
+                0,
+            ),
+            (
+                "4:64+0:0:1460:mss*20,10:mss,sok,ts,nop,ws:df,id+:0",
+                Some(3010),
+                10,
+            ),
+            (
+                "4:64+0:0:1460:mss*20,10:mss,sok,ts,nop,ws:df,id+:0",
+                Some(2),
+                30,
+            ),
+            (
+                "4:128+0:0:1460:mss*44,8:mss,nop,ws,nop,nop,sok:df,id+:0",
+                None,
+                40,
+            ),
+        ] {
+            let observed_at = start + chrono::Duration::minutes(at_mins);
+            let mut profile = store
+                .get("10.0.0.5")
+                .unwrap()
+                .unwrap_or_else(|| profile("10.0.0.5", 0));
+            let signals = huginn_core::HostSignals {
+                syn_signature: Some(signature.to_string()),
+                ttl_distance: None,
+                boot_time: uptime_mins.map(|mins| observed_at - chrono::Duration::minutes(mins)),
+                clock_hz: uptime_mins.map(|_| 1000.0),
+                clock_modulo_days: None,
+                ja4: None,
+                observed_at,
+            };
+            analyzer.track_uptime(&mut profile, &signals);
+            analyzer.track_hosts(&mut profile, signals);
+            store.put("10.0.0.5", profile).unwrap();
+        }
+
+        let profile = store.get("10.0.0.5").unwrap().unwrap();
+        assert_eq!(profile.uptime_history.reboots, 1);
+        assert_eq!(profile.nat.estimated_hosts, 2);
+    }
+}
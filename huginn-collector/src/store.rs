@@ -0,0 +1,513 @@
+use crate::cache::{evict_lru, expire_idle};
+use crate::error::Result;
+use chrono::{DateTime, Utc};
+use huginn_core::TrafficProfile;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::net::IpAddr;
+use std::str::FromStr;
+use std::sync::RwLock;
+
+/// Where the collector keeps its per-IP traffic profiles
+///
+/// The collector is the only writer; readers such as `huginn-api` may share
+/// the store and query it directly. Methods take `&self`, so implementations
+/// handle their own locking.
+pub trait ProfileStore: Send + Sync {
+    /// Get a profile by key, with its [`huginn_core::TrackingHistory`]
+    fn get(&self, key: &str) -> Result<Option<TrafficProfile>>;
+
+    /// Insert or replace a profile
+    fn put(&self, key: &str, profile: TrafficProfile) -> Result<()>;
+
+    /// Remove a profile, returning whether it existed
+    fn remove(&self, key: &str) -> Result<bool>;
+
+    /// Remove every profile
+    fn clear(&self) -> Result<()>;
+
+    /// Number of stored profiles
+    fn len(&self) -> Result<usize>;
+
+    /// Whether the store holds no profiles
+    fn is_empty(&self) -> Result<bool> {
+        Ok(self.len()? == 0)
+    }
+
+    /// Profiles matching `filter`, most recently updated first
+    ///
+    /// Stores may leave the tracking history out of these profiles.
+    fn query(&self, filter: &ProfileFilter) -> Result<Vec<(String, TrafficProfile)>>;
+
+    /// Keys of every stored profile
+    fn keys(&self) -> Result<Vec<String>> {
+        Ok(self
+            .query(&ProfileFilter::default())?
+            .into_iter()
+            .map(|(key, _)| key)
+            .collect())
+    }
+
+    /// Every stored profile
+    fn all(&self) -> Result<HashMap<String, TrafficProfile>> {
+        Ok(self.query(&ProfileFilter::default())?.into_iter().collect())
+    }
+
+    /// Remove the least recently updated profiles until at most `max` remain
+    ///
+    /// Returns the removed keys.
+    fn evict_lru(&self, max: usize) -> Result<Vec<String>>;
+
+    /// Remove profiles last updated before `cutoff`, returning their keys
+    fn expire_idle(&self, cutoff: DateTime<Utc>) -> Result<Vec<String>>;
+
+    /// Newest `metadata.last_updated` in the store
+    fn newest_update(&self) -> Result<Option<DateTime<Utc>>>;
+
+    /// Profiles written after change number `sequence`, and the latest change number
+    ///
+    /// Every `put` takes the next number, so a poller sees each write once even
+    /// when it carries an older `last_updated` (replayed or out-of-order results).
+    /// As with `query`, stores may leave the tracking history out.
+    fn changed_since(&self, sequence: u64) -> Result<ProfileChanges>;
+}
+
+/// Profiles written since a change number, see [`ProfileStore::changed_since`]
+#[derive(Debug, Clone, Default)]
+pub struct ProfileChanges {
+    /// Latest change number in the store; pass it to the next call
+    pub sequence: u64,
+    /// Profiles written after the requested change number
+    pub profiles: Vec<(String, TrafficProfile)>,
+}
+
+/// IPv4 or IPv6 network, e.g. `10.0.0.0/8`; a bare address is a single host
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Cidr {
+    network: IpAddr,
+    prefix: u8,
+}
+
+impl Cidr {
+    /// Whether `ip` is inside the network
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        let (low, high) = self.range();
+        (low..=high).contains(&mapped(ip))
+    }
+
+    /// First and last address of the network, as IPv6 (IPv4 is v4-mapped)
+    pub(crate) fn range(&self) -> (u128, u128) {
+        let prefix = match self.network {
+            IpAddr::V4(_) => u32::from(self.prefix) + 96,
+            IpAddr::V6(_) => u32::from(self.prefix),
+        };
+        let host_mask = u128::MAX.checked_shr(prefix).unwrap_or(0);
+        let network = mapped(self.network) & !host_mask;
+        (network, network | host_mask)
+    }
+}
+
+/// An address as a 128-bit number (IPv4 is v4-mapped)
+pub(crate) fn mapped(ip: IpAddr) -> u128 {
+    match ip {
+        IpAddr::V4(ip) => u128::from(ip.to_ipv6_mapped()),
+        IpAddr::V6(ip) => u128::from(ip),
+    }
+}
+
+impl FromStr for Cidr {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let (address, prefix) = match s.split_once('/') {
+            Some((address, prefix)) => (address, Some(prefix)),
+            None => (s, None),
+        };
+        let network: IpAddr = address
+            .parse()
+            .map_err(|_| format!("invalid address in '{}'", s))?;
+        let max = if network.is_ipv4() { 32 } else { 128 };
+        let prefix = match prefix {
+            Some(prefix) => prefix
+                .parse::<u8>()
+                .ok()
+                .filter(|prefix| *prefix <= max)
+                .ok_or_else(|| format!("invalid prefix length in '{}'", s))?,
+            None => max,
+        };
+        Ok(Self { network, prefix })
+    }
+}
+
+impl TryFrom<String> for Cidr {
+    type Error = String;
+
+    fn try_from(value: String) -> std::result::Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<Cidr> for String {
+    fn from(cidr: Cidr) -> Self {
+        cidr.to_string()
+    }
+}
+
+impl fmt::Display for Cidr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.network, self.prefix)
+    }
+}
+
+/// Which profiles a store query returns; unset fields match everything
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ProfileFilter {
+    /// IP address or CIDR network the profile's IP falls in
+    pub cidr: Option<Cidr>,
+    /// Only profiles updated at or after this time
+    pub since: Option<DateTime<Utc>>,
+    /// Only profiles first seen at or before this time
+    pub until: Option<DateTime<Utc>>,
+    /// TLS JA4 fingerprint
+    pub ja4: Option<String>,
+    /// JA3 hash (case-insensitive) or full JA3 string
+    pub ja3: Option<String>,
+    /// HTTP JA4H fingerprint
+    pub ja4h: Option<String>,
+    /// TCP JA4T fingerprint of the SYN
+    pub ja4t: Option<String>,
+    /// Maximum number of profiles returned
+    pub limit: Option<usize>,
+}
+
+impl ProfileFilter {
+    /// Whether `profile` passes every set filter (`limit` aside)
+    pub fn matches(&self, profile: &TrafficProfile) -> bool {
+        if self.cidr.is_some_and(|cidr| !cidr.contains(profile.ip)) {
+            return false;
+        }
+        if self
+            .since
+            .is_some_and(|since| profile.metadata.last_updated < since)
+            || self
+                .until
+                .is_some_and(|until| profile.metadata.first_seen > until)
+        {
+            return false;
+        }
+
+        if let Some(ja4) = &self.ja4 {
+            if profile_ja4(profile) != Some(ja4.as_str()) {
+                return false;
+            }
+        }
+        if let Some(ja3) = &self.ja3 {
+            let matches_ja3 = |hash: &str, string: &str| {
+                !hash.is_empty() && (hash.eq_ignore_ascii_case(ja3) || string == ja3)
+            };
+            let tls_match = profile
+                .tls
+                .as_ref()
+                .is_some_and(|tls| matches_ja3(&tls.ja3_hash, &tls.ja3));
+            let raw_match = profile
+                .raw_data
+                .tls_client
+                .as_ref()
+                .is_some_and(|tls| matches_ja3(&tls.ja3_hash, &tls.ja3));
+            if !tls_match && !raw_match {
+                return false;
+            }
+        }
+        if let Some(ja4h) = &self.ja4h {
+            if profile_ja4h(profile) != Some(ja4h.as_str()) {
+                return false;
+            }
+        }
+        if let Some(ja4t) = &self.ja4t {
+            if profile_ja4t(profile) != Some(ja4t.as_str()) {
+                return false;
+            }
+        }
+
+        true
+    }
+}
+
+/// TLS JA4 of a profile, from the analysis or the raw ClientHello
+pub(crate) fn profile_ja4(profile: &TrafficProfile) -> Option<&str> {
+    profile
+        .tls
+        .as_ref()
+        .map(|tls| tls.ja4.as_str())
+        .or_else(|| {
+            profile
+                .raw_data
+                .tls_client
+                .as_ref()
+                .map(|tls| tls.ja4.as_str())
+        })
+        .filter(|ja4| !ja4.is_empty())
+}
+
+/// HTTP JA4H of a profile's latest request
+pub(crate) fn profile_ja4h(profile: &TrafficProfile) -> Option<&str> {
+    profile.raw_data.http_request.as_ref()?.ja4h.as_deref()
+}
+
+/// TCP JA4T of a profile's latest SYN
+pub(crate) fn profile_ja4t(profile: &TrafficProfile) -> Option<&str> {
+    profile.raw_data.syn.as_ref()?.ja4t.as_deref()
+}
+
+/// Sort query results most recently updated first (ties by key) and apply the limit
+fn newest_first(
+    mut profiles: Vec<(String, TrafficProfile)>,
+    limit: Option<usize>,
+) -> Vec<(String, TrafficProfile)> {
+    profiles.sort_by(|(a_key, a), (b_key, b)| {
+        b.metadata
+            .last_updated
+            .cmp(&a.metadata.last_updated)
+            .then_with(|| a_key.cmp(b_key))
+    });
+    if let Some(limit) = limit {
+        profiles.truncate(limit);
+    }
+    profiles
+}
+
+/// Profiles kept in memory and lost on restart (the default)
+#[derive(Debug, Default)]
+pub struct MemoryStore {
+    profiles: RwLock<MemoryProfiles>,
+}
+
+#[derive(Debug, Default)]
+struct MemoryProfiles {
+    profiles: HashMap<String, TrafficProfile>,
+    /// Change number of each profile's latest write
+    changes: HashMap<String, u64>,
+    /// Latest change number handed out
+    sequence: u64,
+}
+
+impl MemoryProfiles {
+    fn forget(&mut self, keys: Vec<String>) -> Vec<String> {
+        for key in &keys {
+            self.changes.remove(key);
+        }
+        keys
+    }
+}
+
+impl MemoryStore {
+    /// Create an empty store
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn read(&self) -> std::sync::RwLockReadGuard<'_, MemoryProfiles> {
+        self.profiles.read().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn write(&self) -> std::sync::RwLockWriteGuard<'_, MemoryProfiles> {
+        self.profiles.write().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl ProfileStore for MemoryStore {
+    fn get(&self, key: &str) -> Result<Option<TrafficProfile>> {
+        Ok(self.read().profiles.get(key).cloned())
+    }
+
+    fn put(&self, key: &str, profile: TrafficProfile) -> Result<()> {
+        let mut stored = self.write();
+        stored.sequence += 1;
+        let sequence = stored.sequence;
+        stored.changes.insert(key.to_string(), sequence);
+        stored.profiles.insert(key.to_string(), profile);
+        Ok(())
+    }
+
+    fn remove(&self, key: &str) -> Result<bool> {
+        let mut stored = self.write();
+        stored.changes.remove(key);
+        Ok(stored.profiles.remove(key).is_some())
+    }
+
+    fn clear(&self) -> Result<()> {
+        let mut stored = self.write();
+        stored.changes.clear();
+        stored.profiles.clear();
+        Ok(())
+    }
+
+    fn len(&self) -> Result<usize> {
+        Ok(self.read().profiles.len())
+    }
+
+    fn keys(&self) -> Result<Vec<String>> {
+        Ok(self.read().profiles.keys().cloned().collect())
+    }
+
+    fn query(&self, filter: &ProfileFilter) -> Result<Vec<(String, TrafficProfile)>> {
+        let matching = self
+            .read()
+            .profiles
+            .iter()
+            .filter(|(_, profile)| filter.matches(profile))
+            .map(|(key, profile)| (key.clone(), profile.clone()))
+            .collect();
+        Ok(newest_first(matching, filter.limit))
+    }
+
+    fn all(&self) -> Result<HashMap<String, TrafficProfile>> {
+        Ok(self.read().profiles.clone())
+    }
+
+    fn evict_lru(&self, max: usize) -> Result<Vec<String>> {
+        let mut stored = self.write();
+        let evicted = evict_lru(&mut stored.profiles, max);
+        Ok(stored.forget(evicted))
+    }
+
+    fn expire_idle(&self, cutoff: DateTime<Utc>) -> Result<Vec<String>> {
+        let mut stored = self.write();
+        let expired = expire_idle(&mut stored.profiles, cutoff);
+        Ok(stored.forget(expired))
+    }
+
+    fn newest_update(&self) -> Result<Option<DateTime<Utc>>> {
+        Ok(self
+            .read()
+            .profiles
+            .values()
+            .map(|profile| profile.metadata.last_updated)
+            .max())
+    }
+
+    fn changed_since(&self, sequence: u64) -> Result<ProfileChanges> {
+        let stored = self.read();
+        let profiles = stored
+            .changes
+            .iter()
+            .filter(|(_, changed)| **changed > sequence)
+            .filter_map(|(key, _)| Some((key.clone(), stored.profiles.get(key)?.clone())))
+            .collect();
+        Ok(ProfileChanges {
+            sequence: stored.sequence,
+            profiles,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn profile(ip: &str, first_seen: i64, last_updated: i64) -> TrafficProfile {
+        let at = |secs| Utc.timestamp_opt(1_700_000_000 + secs, 0).unwrap();
+        let mut profile = TrafficProfile::new_at(ip.parse().unwrap(), 40001, at(first_seen));
+        profile.metadata.last_updated = at(last_updated);
+        profile
+    }
+
+    #[test]
+    fn test_cidr_parses_and_matches() {
+        let cidr: Cidr = "10.1.0.0/16".parse().unwrap();
+        assert!(cidr.contains("10.1.200.3".parse().unwrap()));
+        assert!(!cidr.contains("10.2.0.1".parse().unwrap()));
+        assert!(!cidr.contains("::ffff:10.2.0.1".parse().unwrap()));
+
+        let host: Cidr = "192.168.1.10".parse().unwrap();
+        assert_eq!(host.to_string(), "192.168.1.10/32");
+        assert!(host.contains("192.168.1.10".parse().unwrap()));
+        assert!(!host.contains("192.168.1.11".parse().unwrap()));
+
+        let v6: Cidr = "2001:db8::/32".parse().unwrap();
+        assert!(v6.contains("2001:db8:1::5".parse().unwrap()));
+        assert!("0.0.0.0/0"
+            .parse::<Cidr>()
+            .unwrap()
+            .contains("8.8.8.8".parse().unwrap()));
+
+        assert!("10.0.0.0/33".parse::<Cidr>().is_err());
+        assert!("not-an-ip".parse::<Cidr>().is_err());
+    }
+
+    #[test]
+    fn test_memory_store_queries_by_network_and_time() {
+        let store = MemoryStore::new();
+        store.put("10.0.0.1", profile("10.0.0.1", 0, 100)).unwrap();
+        store.put("10.0.0.2", profile("10.0.0.2", 50, 300)).unwrap();
+        store.put("10.9.0.1", profile("10.9.0.1", 0, 200)).unwrap();
+
+        let keys = |filter: &ProfileFilter| -> Vec<String> {
+            store
+                .query(filter)
+                .unwrap()
+                .into_iter()
+                .map(|(key, _)| key)
+                .collect()
+        };
+
+        let subnet = ProfileFilter {
+            cidr: Some("10.0.0.0/24".parse().unwrap()),
+            ..Default::default()
+        };
+        assert_eq!(keys(&subnet), ["10.0.0.2", "10.0.0.1"]);
+
+        let window = ProfileFilter {
+            since: Some(Utc.timestamp_opt(1_700_000_150, 0).unwrap()),
+            until: Some(Utc.timestamp_opt(1_700_000_010, 0).unwrap()),
+            ..Default::default()
+        };
+        assert_eq!(keys(&window), ["10.9.0.1"]);
+
+        let newest = ProfileFilter {
+            limit: Some(1),
+            ..Default::default()
+        };
+        assert_eq!(keys(&newest), ["10.0.0.2"]);
+
+        assert_eq!(store.evict_lru(2).unwrap(), ["10.0.0.1"]);
+        assert_eq!(
+            store.newest_update().unwrap(),
+            Some(Utc.timestamp_opt(1_700_000_300, 0).unwrap())
+        );
+    }
+
+    #[test]
+    fn test_memory_store_reports_writes_in_change_order() {
+        let store = MemoryStore::new();
+        store.put("10.0.0.1", profile("10.0.0.1", 0, 300)).unwrap();
+        let seen = store.changed_since(0).unwrap();
+        assert_eq!(seen.profiles.len(), 1);
+
+        // A late result carries an older update time than anything seen so far
+        store.put("10.0.0.2", profile("10.0.0.2", 0, 100)).unwrap();
+        let changes = store.changed_since(seen.sequence).unwrap();
+        let keys: Vec<&str> = changes
+            .profiles
+            .iter()
+            .map(|(key, _)| key.as_str())
+            .collect();
+        assert_eq!(keys, ["10.0.0.2"]);
+
+        // Removing profiles never hands a change number out twice
+        store.clear().unwrap();
+        store.put("10.0.0.3", profile("10.0.0.3", 0, 50)).unwrap();
+        let after_clear = store.changed_since(changes.sequence).unwrap();
+        assert!(after_clear.sequence > changes.sequence);
+        assert_eq!(after_clear.profiles.len(), 1);
+        assert!(store
+            .changed_since(after_clear.sequence)
+            .unwrap()
+            .profiles
+            .is_empty());
+    }
+}
@@ -0,0 +1,288 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// How often a backoff sleep checks whether the capture was asked to stop
+const STOP_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// How the supervisor restarts a live capture that failed
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RestartPolicy {
+    /// Delay (milliseconds) before the first restart
+    pub initial_backoff_ms: u64,
+    /// Upper bound (milliseconds) for the doubling delay
+    pub max_backoff_ms: u64,
+    /// Give up after this many consecutive failures (`None` retries forever)
+    pub max_restarts: Option<u32>,
+}
+
+impl Default for RestartPolicy {
+    fn default() -> Self {
+        Self {
+            initial_backoff_ms: 500,
+            max_backoff_ms: 30_000,
+            max_restarts: None,
+        }
+    }
+}
+
+impl RestartPolicy {
+    fn initial_backoff(&self) -> Duration {
+        Duration::from_millis(self.initial_backoff_ms)
+    }
+
+    fn max_backoff(&self) -> Duration {
+        Duration::from_millis(self.max_backoff_ms.max(self.initial_backoff_ms))
+    }
+}
+
+/// Exponential backoff between capture restarts
+///
+/// A capture that ran for at least the maximum backoff before failing counts
+/// as healthy, so the next delay starts from the initial backoff again.
+#[derive(Debug)]
+pub(crate) struct Backoff {
+    policy: RestartPolicy,
+    next: Duration,
+    failures: u32,
+}
+
+impl Backoff {
+    pub(crate) fn new(policy: RestartPolicy) -> Self {
+        Self {
+            next: policy.initial_backoff(),
+            policy,
+            failures: 0,
+        }
+    }
+
+    /// Delay before restarting a capture that failed after running `ran_for`
+    ///
+    /// Returns `None` once the policy's restart limit is used up.
+    pub(crate) fn next_delay(&mut self, ran_for: Duration) -> Option<Duration> {
+        if ran_for >= self.policy.max_backoff() {
+            self.next = self.policy.initial_backoff();
+            self.failures = 0;
+        }
+
+        self.failures += 1;
+        if self
+            .policy
+            .max_restarts
+            .is_some_and(|max| self.failures > max)
+        {
+            return None;
+        }
+
+        let delay = self.next;
+        self.next = (self.next * 2).min(self.policy.max_backoff());
+        Some(delay)
+    }
+}
+
+/// State of the packet capture
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CaptureState {
+    /// huginn-net is capturing
+    Running,
+    /// The capture failed and is waiting to be restarted
+    Restarting,
+    /// The capture failed and will not be restarted
+    Failed,
+    /// The capture source is exhausted (end of a pcap file)
+    Finished,
+    /// The collector was stopped
+    Stopped,
+}
+
+/// Health of the packet capture, as reported by the supervisor
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CollectorStatus {
+    /// Current capture state
+    pub state: CaptureState,
+    /// Capture generation (bumped on every signature reload)
+    pub generation: u64,
+    /// Number of times the capture has been restarted after an error
+    pub restarts: u32,
+    /// Most recent capture error
+    pub last_error: Option<String>,
+    /// When the most recent capture error happened
+    pub last_error_at: Option<DateTime<Utc>>,
+    /// When the next restart is due (only while restarting)
+    pub next_restart_at: Option<DateTime<Utc>>,
+    /// When the capture entered its current state
+    pub since: DateTime<Utc>,
+}
+
+impl CollectorStatus {
+    pub(crate) fn new(generation: u64) -> Self {
+        Self {
+            state: CaptureState::Running,
+            generation,
+            restarts: 0,
+            last_error: None,
+            last_error_at: None,
+            next_restart_at: None,
+            since: Utc::now(),
+        }
+    }
+
+    /// Whether the capture is delivering (or waiting to deliver) packets
+    pub fn is_healthy(&self) -> bool {
+        matches!(self.state, CaptureState::Running | CaptureState::Finished)
+    }
+
+    fn enter(&mut self, state: CaptureState) {
+        self.state = state;
+        self.next_restart_at = None;
+        self.since = Utc::now();
+    }
+}
+
+/// Shared status that ignores updates from superseded capture generations
+#[derive(Debug)]
+pub(crate) struct StatusCell(Mutex<CollectorStatus>);
+
+impl StatusCell {
+    pub(crate) fn new(generation: u64) -> Self {
+        Self(Mutex::new(CollectorStatus::new(generation)))
+    }
+
+    pub(crate) fn get(&self) -> CollectorStatus {
+        self.0.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    /// Apply `update` unless a newer generation has taken over
+    fn update(&self, generation: u64, update: impl FnOnce(&mut CollectorStatus)) {
+        let mut status = self.0.lock().unwrap_or_else(|e| e.into_inner());
+        if status.generation <= generation {
+            status.generation = generation;
+            update(&mut status);
+        }
+    }
+
+    pub(crate) fn running(&self, generation: u64) {
+        self.update(generation, |status| status.enter(CaptureState::Running));
+    }
+
+    pub(crate) fn finished(&self, generation: u64) {
+        self.update(generation, |status| status.enter(CaptureState::Finished));
+    }
+
+    pub(crate) fn stopped(&self) {
+        let mut status = self.0.lock().unwrap_or_else(|e| e.into_inner());
+        status.enter(CaptureState::Stopped);
+    }
+
+    pub(crate) fn restarting(&self, generation: u64, error: String, delay: Duration) {
+        self.update(generation, |status| {
+            status.enter(CaptureState::Restarting);
+            status.restarts += 1;
+            status.last_error = Some(error);
+            status.last_error_at = Some(status.since);
+            status.next_restart_at = chrono::Duration::from_std(delay)
+                .ok()
+                .map(|delay| status.since + delay);
+        });
+    }
+
+    pub(crate) fn failed(&self, generation: u64, error: String) {
+        self.update(generation, |status| {
+            status.enter(CaptureState::Failed);
+            status.last_error = Some(error);
+            status.last_error_at = Some(status.since);
+        });
+    }
+}
+
+/// Sleep for `delay`, returning early (with `false`) once `stop` is set
+pub(crate) fn sleep_unless_stopped(delay: Duration, stop: &AtomicBool) -> bool {
+    let deadline = std::time::Instant::now() + delay;
+    loop {
+        if stop.load(Ordering::Relaxed) {
+            return false;
+        }
+        let now = std::time::Instant::now();
+        if now >= deadline {
+            return true;
+        }
+        std::thread::sleep(STOP_POLL_INTERVAL.min(deadline - now));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy(max_restarts: Option<u32>) -> RestartPolicy {
+        RestartPolicy {
+            initial_backoff_ms: 100,
+            max_backoff_ms: 1000,
+            max_restarts,
+        }
+    }
+
+    #[test]
+    fn test_backoff_doubles_up_to_the_limit() {
+        let mut backoff = Backoff::new(policy(Some(6)));
+        let delays: Vec<_> = (0..7)
+            .map(|_| backoff.next_delay(Duration::ZERO).map(|d| d.as_millis()))
+            .collect();
+
+        assert_eq!(
+            delays,
+            [
+                Some(100),
+                Some(200),
+                Some(400),
+                Some(800),
+                Some(1000),
+                Some(1000),
+                None
+            ]
+        );
+    }
+
+    #[test]
+    fn test_long_running_capture_resets_backoff() {
+        let mut backoff = Backoff::new(policy(Some(2)));
+        assert_eq!(
+            backoff.next_delay(Duration::ZERO),
+            Some(Duration::from_millis(100))
+        );
+        assert_eq!(
+            backoff.next_delay(Duration::ZERO),
+            Some(Duration::from_millis(200))
+        );
+
+        // Healthy for longer than the max backoff: start over
+        assert_eq!(
+            backoff.next_delay(Duration::from_secs(5)),
+            Some(Duration::from_millis(100))
+        );
+    }
+
+    #[test]
+    fn test_status_ignores_superseded_generations() {
+        let status = StatusCell::new(1);
+        status.restarting(1, "interface down".to_string(), Duration::from_secs(1));
+
+        let current = status.get();
+        assert_eq!(current.state, CaptureState::Restarting);
+        assert_eq!(current.restarts, 1);
+        assert_eq!(current.last_error.as_deref(), Some("interface down"));
+        assert!(current.next_restart_at.is_some());
+        assert!(!current.is_healthy());
+
+        status.running(2);
+        status.failed(1, "late error from the old capture".to_string());
+        let current = status.get();
+        assert_eq!(current.state, CaptureState::Running);
+        assert_eq!(current.generation, 2);
+        assert!(current.next_restart_at.is_none());
+    }
+}
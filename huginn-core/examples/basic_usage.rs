@@ -21,6 +21,7 @@ fn main() {
         enable_http: true,
         enable_tls: true,
         min_quality: 0.5,
+        ..Default::default()
     };
 
     let _analyzer_with_config = HuginnAnalyzer::with_config(config);
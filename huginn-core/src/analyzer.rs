@@ -1,18 +1,36 @@
+use crate::clock::{Clock, SystemClock};
+use crate::connection::ConnectionKey;
+use crate::consistency::{same_browser_family, ConsistencyChecker};
+use crate::enrichment::ProfileEnricher;
 use crate::error::{HuginnError, Result};
 use crate::events::{EventDispatcher, TrafficEvent};
+use crate::ja3::Ja3;
+use crate::ja4db::Ja4Database;
+use crate::ja4h::Ja4h;
+use crate::ja4t::Ja4t;
+use crate::link::LinkTable;
+use crate::nat::HostSignals;
 use crate::profile::{
     HttpAnalysis, HttpDetails, MtuData, NetworkEndpoint, OsDetection, SynAckPacketData,
-    SynPacketData, TcpAnalysis, TcpDetails, TlsAnalysis, TlsClientData, TlsDetails, TrafficProfile,
-    UptimeData,
+    SynPacketData, TcpAnalysis, TcpDetails, TcpOptionKind, TcpQuirk, TcpTtl, TcpWindowSize,
+    TlsAnalysis, TlsClientData, TlsDetails, TrafficProfile, TtlKind, UptimeData, WindowSizeKind,
 };
-use chrono::Utc;
+use crate::reboot::UptimeChange;
+use crate::risk::{RiskScore, RiskWeights};
+use crate::user_agent::UserAgent;
+use crate::wire::WireDetails;
+use chrono::{DateTime, Utc};
 use huginn_net::fingerprint_result::*;
-use huginn_net::tcp::{IpVersion, PayloadSize, WindowSize};
-use huginn_net::ObservableTcp;
+use huginn_net::http::{HttpDiagnosis, Version as HttpVersion};
+use huginn_net::tcp::{IpVersion, PayloadSize, Quirk, TcpOption, WindowSize};
+use huginn_net::tls::TlsVersion;
 use huginn_net::Ttl;
-use std::net::IpAddr;
+use huginn_net::{ObservableTcp, ObservableTlsClient};
+use std::net::{IpAddr, SocketAddr};
+use std::panic::{self, AssertUnwindSafe};
 use std::str::FromStr;
-use tracing::{debug, info};
+use std::sync::Arc;
+use tracing::{debug, info, warn};
 
 /// Configuration for the Huginn analyzer
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
@@ -25,6 +43,16 @@ pub struct AnalyzerConfig {
     pub enable_tls: bool,
     /// Minimum quality threshold for results
     pub min_quality: f64,
+    /// How long (seconds) host signals count towards NAT detection
+    #[serde(default = "default_nat_window_secs")]
+    pub nat_window_secs: u64,
+    /// Points each signal adds to the bot/automation risk score
+    #[serde(default)]
+    pub risk_weights: RiskWeights,
+}
+
+fn default_nat_window_secs() -> u64 {
+    3600
 }
 
 impl Default for AnalyzerConfig {
@@ -34,23 +62,35 @@ impl Default for AnalyzerConfig {
             enable_http: true,
             enable_tls: true,
             min_quality: 0.0,
+            nat_window_secs: default_nat_window_secs(),
+            risk_weights: RiskWeights::default(),
         }
     }
 }
 
+/// Built-in analysis stage working on one layer of a fingerprint result
+type Stage = fn(
+    &HuginnAnalyzer,
+    &FingerprintResult,
+    &WireDetails,
+    &mut TrafficProfile,
+    DateTime<Utc>,
+) -> Result<()>;
+
 /// Main analyzer that converts huginn-net results to our data structures
 pub struct HuginnAnalyzer {
     config: AnalyzerConfig,
     event_dispatcher: EventDispatcher,
+    clock: Arc<dyn Clock>,
+    ja4_database: Option<Arc<Ja4Database>>,
+    link_table: Arc<LinkTable>,
+    enrichers: Vec<Arc<dyn ProfileEnricher>>,
 }
 
 impl HuginnAnalyzer {
     /// Create a new analyzer with default configuration
     pub fn new() -> Self {
-        Self {
-            config: AnalyzerConfig::default(),
-            event_dispatcher: EventDispatcher::new(),
-        }
+        Self::with_config(AnalyzerConfig::default())
     }
 
     /// Create a new analyzer with custom configuration
@@ -58,6 +98,10 @@ impl HuginnAnalyzer {
         Self {
             config,
             event_dispatcher: EventDispatcher::new(),
+            clock: Arc::new(SystemClock),
+            ja4_database: None,
+            link_table: Arc::new(LinkTable::default()),
+            enrichers: Vec::new(),
         }
     }
 
@@ -66,8 +110,64 @@ impl HuginnAnalyzer {
         &mut self.event_dispatcher
     }
 
-    /// Analyze a fingerprint result and return a traffic profile
+    /// Replace the clock used when no observation time is supplied
+    pub fn set_clock<C: Clock + 'static>(&mut self, clock: C) {
+        self.clock = Arc::new(clock);
+    }
+
+    /// Label TLS clients using the given JA4 database
+    pub fn set_ja4_database(&mut self, database: Ja4Database) {
+        self.ja4_database = Some(Arc::new(database));
+    }
+
+    /// Classify links using the given MTU table instead of the built-in one
+    pub fn set_link_table(&mut self, table: LinkTable) {
+        self.link_table = Arc::new(table);
+    }
+
+    /// Register an enrichment stage, run after the built-in stages
+    pub fn add_enricher<E: ProfileEnricher + 'static>(&mut self, enricher: E) {
+        self.add_shared_enricher(Arc::new(enricher));
+    }
+
+    /// Register an enrichment stage that is shared with other analyzers
+    pub fn add_shared_enricher(&mut self, enricher: Arc<dyn ProfileEnricher>) {
+        self.enrichers.push(enricher);
+    }
+
+    /// Current time according to the analyzer clock
+    pub fn now(&self) -> DateTime<Utc> {
+        self.clock.now()
+    }
+
+    /// Analyze a fingerprint result observed "now" (according to the analyzer clock)
     pub fn analyze(&self, result: FingerprintResult) -> Result<Option<TrafficProfile>> {
+        self.analyze_at(result, self.clock.now())
+    }
+
+    /// Analyze a fingerprint result that was captured at `observed_at`
+    ///
+    /// Every timestamp in the resulting profile (raw packet data, metadata and
+    /// emitted events) is the observation time, so replayed captures keep their
+    /// original timeline.
+    pub fn analyze_at(
+        &self,
+        result: FingerprintResult,
+        observed_at: DateTime<Utc>,
+    ) -> Result<Option<TrafficProfile>> {
+        self.analyze_packet(result, &WireDetails::default(), observed_at)
+    }
+
+    /// Analyze a fingerprint result together with the details of its packet
+    ///
+    /// `wire` supplies what huginn-net does not report (such as the HTTP method
+    /// and status code); without it those fields stay empty.
+    pub fn analyze_packet(
+        &self,
+        result: FingerprintResult,
+        wire: &WireDetails,
+        observed_at: DateTime<Utc>,
+    ) -> Result<Option<TrafficProfile>> {
         // Debug logging to understand what huginn-net is sending
         info!(
             "🔍 ANALYZING FingerprintResult: SYN:{} SYN-ACK:{} HTTP-REQ:{} HTTP-RES:{} TLS:{} MTU:{} UPTIME:{}",
@@ -145,19 +245,97 @@ impl HuginnAnalyzer {
             }
         };
 
-        // Create traffic profile (use port 0 as default since we group by IP only)
-        let mut profile = TrafficProfile::new(ip, 0);
+        // Use the client port of the connection this result belongs to (if known)
+        let port = self
+            .connection_key(&result)
+            .map(|key| key.client.port())
+            .unwrap_or(0);
+
+        let mut profile = TrafficProfile::new_at(ip, port, observed_at);
 
         // Store the source IP in raw data for reference
         profile.raw_data.source_ip = Some(ip.to_string());
 
-        // Process SYN packets (client data)
+        // Run the built-in stages; a failing stage is reported and skipped so the
+        // other layers of the same result still make it into the profile
+        let stages: [(&str, Stage); 8] = [
+            ("syn", Self::stage_syn),
+            ("syn_ack", Self::stage_syn_ack),
+            ("http_request", Self::stage_http_request),
+            ("http_response", Self::stage_http_response),
+            ("tls_client", Self::stage_tls_client),
+            ("mtu", Self::stage_mtu),
+            ("link", Self::stage_link),
+            ("uptime", Self::stage_uptime),
+        ];
+        for (name, stage) in stages {
+            if let Err(e) = stage(self, &result, wire, &mut profile, observed_at) {
+                self.stage_failed(&profile, name, &e.to_string());
+            }
+        }
+
+        // Note: source_ip field doesn't exist in FingerprintResult
+        // Will be determined from the individual packet data
+
+        // Only return profile if it has some data
+        if profile.is_empty() {
+            debug!("Profile is empty, not creating");
+            Ok(None)
+        } else {
+            self.run_enrichers(&result, &mut profile);
+            debug!(
+                "Created profile for {}:{} with data: {}",
+                profile.ip,
+                profile.port,
+                profile.summary()
+            );
+            // Note: ProfileCreated events are handled by the collector layer
+            Ok(Some(profile))
+        }
+    }
+
+    /// Run the registered enrichers, each on its own copy of the profile
+    pub(crate) fn run_enrichers(&self, result: &FingerprintResult, profile: &mut TrafficProfile) {
+        for enricher in &self.enrichers {
+            let mut enriched = profile.clone();
+            let outcome =
+                panic::catch_unwind(AssertUnwindSafe(|| enricher.enrich(result, &mut enriched)));
+            match outcome {
+                Ok(Ok(())) => *profile = enriched,
+                Ok(Err(e)) => self.stage_failed(profile, enricher.name(), &e.to_string()),
+                Err(_) => self.stage_failed(profile, enricher.name(), "enricher panicked"),
+            }
+        }
+    }
+
+    /// Report a stage that failed without aborting the analysis
+    fn stage_failed(&self, profile: &TrafficProfile, stage: &str, error: &str) {
+        warn!(
+            "Analysis stage '{}' failed for {}:{}: {}",
+            stage, profile.ip, profile.port, error
+        );
+        self.event_dispatcher.dispatch(TrafficEvent::AnalysisError {
+            ip: profile.ip,
+            port: profile.port,
+            error: format!("{}: {}", stage, error),
+            timestamp: profile.timestamp,
+        });
+    }
+
+    /// Stage: SYN packets (client data)
+    fn stage_syn(
+        &self,
+        result: &FingerprintResult,
+        _wire: &WireDetails,
+        profile: &mut TrafficProfile,
+        observed_at: DateTime<Utc>,
+    ) -> Result<()> {
         if let Some(syn) = &result.syn {
             info!(
                 "📥 Processing SYN packet from {}:{} (CLIENT)",
                 syn.source.ip, syn.source.port
             );
-            let syn_data = self.process_syn_packet(syn)?;
+            let syn_data = self.process_syn_packet(syn, observed_at)?;
             profile.raw_data.syn = Some(syn_data);
 
             // Create legacy TCP client analysis for backwards compatibility
@@ -169,12 +347,22 @@ impl HuginnAnalyzer {
                     );
                     profile.update_tcp_client(tcp_analysis.clone());
                     profile.update_tcp(tcp_analysis); // Also update general tcp field for backwards compatibility
-                    self.emit_tcp_event(&profile, syn);
+                    self.emit_tcp_event(profile, syn);
                 }
             }
         }
 
-        // Process SYN-ACK packets (server data)
+        Ok(())
+    }
+
+    /// Stage: SYN-ACK packets (server data)
+    fn stage_syn_ack(
+        &self,
+        result: &FingerprintResult,
+        _wire: &WireDetails,
+        profile: &mut TrafficProfile,
+        observed_at: DateTime<Utc>,
+    ) -> Result<()> {
         if let Some(syn_ack) = &result.syn_ack {
             info!(
                 "📤 Processing SYN-ACK packet from {}:{} to {}:{} (SERVER)",
@@ -183,7 +371,7 @@ impl HuginnAnalyzer {
                 syn_ack.destination.ip,
                 syn_ack.destination.port
             );
-            let syn_ack_data = self.process_syn_ack_packet(syn_ack)?;
+            let syn_ack_data = self.process_syn_ack_packet(syn_ack, observed_at)?;
             profile.raw_data.syn_ack = Some(syn_ack_data);
 
             // Create legacy TCP server analysis for backwards compatibility
@@ -195,30 +383,50 @@ impl HuginnAnalyzer {
                     );
                     profile.update_tcp_server(tcp_analysis.clone());
                     // DON'T update general tcp field here to avoid overwriting client data
-                    self.emit_tcp_event_syn_ack(&profile, syn_ack);
+                    self.emit_tcp_event_syn_ack(profile, syn_ack);
                 }
             }
         }
 
-        // Process HTTP requests (client data)
+        Ok(())
+    }
+
+    /// Stage: HTTP requests (client data)
+    fn stage_http_request(
+        &self,
+        result: &FingerprintResult,
+        wire: &WireDetails,
+        profile: &mut TrafficProfile,
+        _observed_at: DateTime<Utc>,
+    ) -> Result<()> {
         if let Some(http_req) = &result.http_request {
             info!(
                 "🌐📥 Processing HTTP request from {}:{} (CLIENT)",
                 http_req.source.ip, http_req.source.port
             );
-            let http_req_data = self.process_http_request(http_req)?;
+            let http_req_data = self.process_http_request(http_req, wire)?;
             profile.raw_data.http_request = Some(http_req_data);
 
             // Also create legacy HTTP analysis for backwards compatibility
             if self.config.enable_http {
-                if let Some(http_analysis) = self.analyze_http_request(http_req)? {
+                if let Some(http_analysis) = self.analyze_http_request(http_req, wire)? {
                     profile.update_http(http_analysis);
-                    self.emit_http_event(&profile, http_req);
+                    self.emit_http_event(profile, http_req);
                 }
             }
         }
 
-        // Process HTTP responses (server data)
+        Ok(())
+    }
+
+    /// Stage: HTTP responses (server data)
+    fn stage_http_response(
+        &self,
+        result: &FingerprintResult,
+        wire: &WireDetails,
+        profile: &mut TrafficProfile,
+        _observed_at: DateTime<Utc>,
+    ) -> Result<()> {
         if let Some(http_res) = &result.http_response {
             info!(
                 "🌐📤 Processing HTTP response from {}:{} to {}:{} (SERVER)",
@@ -227,67 +435,230 @@ impl HuginnAnalyzer {
                 http_res.destination.ip,
                 http_res.destination.port
             );
-            let http_res_data = self.process_http_response(http_res)?;
+            let http_res_data = self.process_http_response(http_res, wire)?;
             profile.raw_data.http_response = Some(http_res_data);
+
+            if self.config.enable_http {
+                if let Some(http_analysis) = self.analyze_http_response(http_res, wire)? {
+                    profile.update_http(http_analysis);
+                    self.emit_http_response_event(profile, http_res);
+                }
+            }
         }
 
-        // Process TLS client data
+        Ok(())
+    }
+
+    /// Stage: TLS client data
+    fn stage_tls_client(
+        &self,
+        result: &FingerprintResult,
+        wire: &WireDetails,
+        profile: &mut TrafficProfile,
+        observed_at: DateTime<Utc>,
+    ) -> Result<()> {
         if let Some(tls_client) = &result.tls_client {
             info!(
                 "🔒 Processing TLS client from {}:{}",
                 tls_client.source.ip, tls_client.source.port
             );
-            let tls_data = self.process_tls_client(tls_client)?;
+            let tls_data = self.process_tls_client(tls_client, wire, observed_at)?;
             profile.raw_data.tls_client = Some(tls_data);
 
             // Also create legacy TLS analysis for backwards compatibility
             if self.config.enable_tls {
-                if let Some(tls_analysis) = self.analyze_tls_client(tls_client)? {
+                if let Some(tls_analysis) = self.analyze_tls_client(tls_client, wire)? {
                     profile.update_tls(tls_analysis);
-                    self.emit_tls_event(&profile, tls_client);
+                    self.emit_tls_event(profile, tls_client);
                 }
             }
         }
 
-        // Process MTU data
+        Ok(())
+    }
+
+    /// Stage: MTU data
+    fn stage_mtu(
+        &self,
+        result: &FingerprintResult,
+        _wire: &WireDetails,
+        profile: &mut TrafficProfile,
+        observed_at: DateTime<Utc>,
+    ) -> Result<()> {
         if let Some(mtu) = &result.mtu {
             info!(
                 "📏 Processing MTU data from {}:{}",
                 mtu.source.ip, mtu.source.port
             );
-            let mtu_data = self.process_mtu_data(mtu)?;
+            let mtu_data = self.process_mtu_data(mtu, observed_at)?;
             profile.raw_data.mtu = Some(mtu_data);
         }
 
-        // Process uptime data
+        Ok(())
+    }
+
+    /// Stage: link type from the MTU, or from the SYN MSS when there is no MTU
+    fn stage_link(
+        &self,
+        _result: &FingerprintResult,
+        _wire: &WireDetails,
+        profile: &mut TrafficProfile,
+        _observed_at: DateTime<Utc>,
+    ) -> Result<()> {
+        let classification = match (&profile.raw_data.mtu, &profile.raw_data.syn) {
+            (Some(mtu), _) => self.link_table.classify(mtu.mtu_value),
+            (None, Some(syn)) => syn.details.mss.and_then(|mss| {
+                self.link_table
+                    .classify_mss(mss, syn.details.version == "IPv6")
+            }),
+            (None, None) => None,
+        };
+
+        if let Some(link) = classification {
+            debug!(
+                "Link of {} looks like {} (MTU {})",
+                profile.ip, link.link_type, link.mtu
+            );
+            profile.link_type = Some(link.link_type);
+            profile.likely_vpn = link.likely_vpn;
+        }
+
+        Ok(())
+    }
+
+    /// Stage: Uptime data
+    fn stage_uptime(
+        &self,
+        result: &FingerprintResult,
+        _wire: &WireDetails,
+        profile: &mut TrafficProfile,
+        observed_at: DateTime<Utc>,
+    ) -> Result<()> {
         if let Some(uptime) = &result.uptime {
             info!(
                 "⏱️ Processing uptime data from {}:{}",
                 uptime.source.ip, uptime.source.port
             );
-            let uptime_data = self.process_uptime_data(uptime)?;
+            let uptime_data = self.process_uptime_data(uptime, observed_at)?;
             profile.raw_data.uptime = Some(uptime_data);
         }
 
-        // Note: source_ip field doesn't exist in FingerprintResult
-        // Will be determined from the individual packet data
+        Ok(())
+    }
 
-        // Only return profile if it has some data
-        if profile.is_empty() {
-            debug!("Profile is empty, not creating");
-            Ok(None)
-        } else {
-            debug!(
-                "Created profile for {}:{} with data: {}",
-                profile.ip,
-                profile.port,
-                profile.summary()
+    /// Re-run the cross-layer consistency checks on a (merged) profile
+    ///
+    /// Layers usually arrive in separate fingerprint results, so this is meant to
+    /// be called on the accumulated profile. Findings that were not present before
+    /// are reported through an `InconsistencyDetected` event.
+    pub fn check_consistency(&self, profile: &mut TrafficProfile) {
+        let findings = ConsistencyChecker::check(profile);
+
+        let new_findings: Vec<_> = findings
+            .iter()
+            .filter(|finding| !profile.consistency.contains(finding))
+            .cloned()
+            .collect();
+
+        profile.consistency = findings;
+
+        if !new_findings.is_empty() {
+            self.event_dispatcher
+                .dispatch(TrafficEvent::InconsistencyDetected {
+                    ip: profile.ip,
+                    port: profile.port,
+                    findings: new_findings,
+                    timestamp: profile.timestamp,
+                });
+        }
+    }
+
+    /// Update the NAT estimate of a (merged) profile with the signals of one result
+    pub fn track_hosts(&self, profile: &mut TrafficProfile, signals: HostSignals) {
+        // A window too long to represent covers every observation
+        let window = i64::try_from(self.config.nat_window_secs)
+            .ok()
+            .and_then(chrono::Duration::try_seconds)
+            .unwrap_or(chrono::Duration::MAX);
+        let was_behind_nat = profile.nat.behind_nat;
+        profile.nat.observe(signals, window);
+
+        if profile.nat.behind_nat && !was_behind_nat {
+            info!(
+                "{} appears to be shared by ~{} hosts",
+                profile.ip, profile.nat.estimated_hosts
             );
-            // Note: ProfileCreated events are handled by the collector layer
-            Ok(Some(profile))
         }
     }
 
+    /// Check the uptime of one result against the (merged) profile's uptime history
+    ///
+    /// A drop in uptime sets `last_reboot_estimate` and emits a `HostRebooted`
+    /// event. Call this before [`Self::track_hosts`] so the clock from before the
+    /// reboot is not counted as a second host.
+    pub fn track_uptime(&self, profile: &mut TrafficProfile, signals: &HostSignals) {
+        // With several clocks behind one IP a younger clock is another host
+        if profile.nat.uptime_clocks > 1 {
+            return;
+        }
+
+        match profile.uptime_history.observe(signals) {
+            Some(UptimeChange::Reboot {
+                previous_boot,
+                boot_time,
+            }) => {
+                profile.last_reboot_estimate = Some(boot_time);
+                profile.nat.forget_clocks_before(boot_time);
+                self.event_dispatcher.dispatch(TrafficEvent::HostRebooted {
+                    ip: profile.ip,
+                    port: profile.port,
+                    previous_boot,
+                    boot_time,
+                    timestamp: signals.observed_at,
+                });
+            }
+            Some(UptimeChange::Jump {
+                expected_boot,
+                boot_time,
+            }) => {
+                warn!(
+                    "Uptime of {} jumped: boot time {} but expected {}",
+                    profile.ip, boot_time, expected_boot
+                );
+            }
+            Some(UptimeChange::Consistent) | None => {}
+        }
+    }
+
+    /// Record the client hop distance of one result in the (merged) profile
+    ///
+    /// Call this before [`Self::track_hosts`]. Changes are not recorded while the
+    /// NAT window holds several TCP stacks or clocks.
+    pub fn track_hops(&self, profile: &mut TrafficProfile, signals: &HostSignals) {
+        let Some(distance) = signals.ttl_distance else {
+            return;
+        };
+        // Hosts at different distances behind one IP would look like a flapping route
+        if profile.nat.syn_signatures > 1 || profile.nat.uptime_clocks > 1 {
+            return;
+        }
+
+        if let Some(anomaly) = profile.hops.observe(distance, signals.observed_at) {
+            info!(
+                "Hop distance of {} changed from {} to {} ({:?})",
+                profile.ip, anomaly.previous, anomaly.current, anomaly.kind
+            );
+        }
+    }
+
+    /// Recompute the bot/automation risk score of a (merged) profile
+    ///
+    /// Call this after the consistency, hop and NAT tracking so every signal
+    /// reflects the accumulated profile.
+    pub fn score_risk(&self, profile: &mut TrafficProfile) {
+        profile.risk = RiskScore::compute(profile, &self.config.risk_weights);
+    }
+
     /// Extract primary IP from fingerprint result (for profile key - grouped by IP only)
     fn extract_primary_ip(&self, result: &FingerprintResult) -> Result<IpAddr> {
         if let Some(syn) = &result.syn {
@@ -338,10 +709,67 @@ impl HuginnAnalyzer {
         }
     }
 
+    /// Extract the connection 4-tuple from a fingerprint result
+    ///
+    /// The client is the side that sent the SYN / HTTP request / TLS ClientHello,
+    /// the server is the side that answered with the SYN-ACK / HTTP response.
+    pub fn connection_key(&self, result: &FingerprintResult) -> Option<ConnectionKey> {
+        let (client, server) = if let Some(syn) = &result.syn {
+            (
+                Self::socket_addr(&syn.source.ip, syn.source.port)?,
+                Self::socket_addr(&syn.destination.ip, syn.destination.port)?,
+            )
+        } else if let Some(syn_ack) = &result.syn_ack {
+            (
+                Self::socket_addr(&syn_ack.destination.ip, syn_ack.destination.port)?,
+                Self::socket_addr(&syn_ack.source.ip, syn_ack.source.port)?,
+            )
+        } else if let Some(http_req) = &result.http_request {
+            (
+                Self::socket_addr(&http_req.source.ip, http_req.source.port)?,
+                Self::socket_addr(&http_req.destination.ip, http_req.destination.port)?,
+            )
+        } else if let Some(http_res) = &result.http_response {
+            (
+                Self::socket_addr(&http_res.destination.ip, http_res.destination.port)?,
+                Self::socket_addr(&http_res.source.ip, http_res.source.port)?,
+            )
+        } else if let Some(tls_client) = &result.tls_client {
+            (
+                Self::socket_addr(&tls_client.source.ip, tls_client.source.port)?,
+                Self::socket_addr(&tls_client.destination.ip, tls_client.destination.port)?,
+            )
+        } else if let Some(mtu) = &result.mtu {
+            (
+                Self::socket_addr(&mtu.source.ip, mtu.source.port)?,
+                Self::socket_addr(&mtu.destination.ip, mtu.destination.port)?,
+            )
+        } else if let Some(uptime) = &result.uptime {
+            (
+                Self::socket_addr(&uptime.source.ip, uptime.source.port)?,
+                Self::socket_addr(&uptime.destination.ip, uptime.destination.port)?,
+            )
+        } else {
+            return None;
+        };
+
+        Some(ConnectionKey::new(client, server))
+    }
+
+    fn socket_addr<T: ToString>(ip: &T, port: u16) -> Option<SocketAddr> {
+        IpAddr::from_str(&ip.to_string())
+            .ok()
+            .map(|ip| SocketAddr::new(ip, port))
+    }
+
     // New methods for processing raw fingerprint data
 
     /// Process SYN packet data
-    fn process_syn_packet(&self, syn: &SynTCPOutput) -> Result<SynPacketData> {
+    fn process_syn_packet(
+        &self,
+        syn: &SynTCPOutput,
+        observed_at: DateTime<Utc>,
+    ) -> Result<SynPacketData> {
         let os_detected = syn.os_matched.as_ref().map(|m| OsDetection {
             os: self.extract_os_string(&m.os),
             quality: m.quality as f64,
@@ -355,13 +783,18 @@ impl HuginnAnalyzer {
             },
             os_detected,
             signature: syn.sig.to_string(),
+            ja4t: Self::ja4t(&syn.sig).map(|ja4t| ja4t.to_string()),
             details: self.convert_tcp_details(&syn.sig),
-            timestamp: Utc::now(),
+            timestamp: observed_at,
         })
     }
 
     /// Process SYN-ACK packet data
-    fn process_syn_ack_packet(&self, syn_ack: &SynAckTCPOutput) -> Result<SynAckPacketData> {
+    fn process_syn_ack_packet(
+        &self,
+        syn_ack: &SynAckTCPOutput,
+        observed_at: DateTime<Utc>,
+    ) -> Result<SynAckPacketData> {
         let os_detected = syn_ack.os_matched.as_ref().map(|m| OsDetection {
             os: self.extract_os_string(&m.os),
             quality: m.quality as f64,
@@ -379,8 +812,9 @@ impl HuginnAnalyzer {
             },
             os_detected,
             signature: syn_ack.sig.to_string(),
+            ja4ts: Self::ja4t(&syn_ack.sig).map(|ja4ts| ja4ts.to_string()),
             details: self.convert_tcp_details(&syn_ack.sig),
-            timestamp: Utc::now(),
+            timestamp: observed_at,
         })
     }
 
@@ -388,20 +822,39 @@ impl HuginnAnalyzer {
     fn process_http_request(
         &self,
         http_req: &HttpRequestOutput,
+        wire: &WireDetails,
     ) -> Result<crate::profile::HttpRequestData> {
         let horder_strings: Vec<String> =
             http_req.sig.horder.iter().map(|h| h.to_string()).collect();
 
+        let ja4h = wire.http_method.as_deref().map(|method| {
+            Ja4h::from_request(
+                method,
+                Self::ja4h_version(&http_req.sig.version),
+                http_req
+                    .sig
+                    .horder
+                    .iter()
+                    .map(|h| (h.name.as_str(), h.value.as_deref())),
+                wire.http_cookie.as_deref(),
+            )
+        });
+
         Ok(crate::profile::HttpRequestData {
-            user_agent: self.extract_header_value_from_horder(&horder_strings, "user-agent"),
+            // huginn-net keeps the User-Agent but drops its value from horder
+            user_agent: http_req.sig.user_agent.clone(),
             accept: self.extract_header_value_from_horder(&horder_strings, "accept"),
             accept_language: self
                 .extract_header_value_from_horder(&horder_strings, "accept-language"),
             accept_encoding: self
                 .extract_header_value_from_horder(&horder_strings, "accept-encoding"),
             connection: self.extract_header_value_from_horder(&horder_strings, "connection"),
-            method: Some("GET".to_string()),
-            host: self.extract_header_value_from_horder(&horder_strings, "host"),
+            method: wire.http_method.clone(),
+            uri: wire.http_uri.clone(),
+            version: http_req.sig.version.to_string(),
+            ja4h_raw: ja4h.as_ref().map(|ja4h| ja4h.raw.clone()),
+            ja4h: ja4h.map(|ja4h| ja4h.hash),
+            host: wire.http_host.clone(),
             signature: http_req.sig.to_string(),
             quality: http_req
                 .browser_matched
@@ -415,6 +868,7 @@ impl HuginnAnalyzer {
     fn process_http_response(
         &self,
         http_res: &HttpResponseOutput,
+        wire: &WireDetails,
     ) -> Result<crate::profile::HttpResponseData> {
         let horder_strings: Vec<String> =
             http_res.sig.horder.iter().map(|h| h.to_string()).collect();
@@ -426,7 +880,13 @@ impl HuginnAnalyzer {
                 .extract_header_value_from_horder(&horder_strings, "content-length"),
             set_cookie: self.extract_header_value_from_horder(&horder_strings, "set-cookie"),
             cache_control: self.extract_header_value_from_horder(&horder_strings, "cache-control"),
-            status: Some("200".to_string()),
+            status: wire.http_status.map(|code| code.to_string()),
+            version: http_res.sig.version.to_string(),
+            web_server: http_res
+                .web_server_matched
+                .as_ref()
+                .map(|m| self.extract_web_server_string(&m.web_server)),
+            diagnosis: http_res.diagnosis.to_string(),
             signature: http_res.sig.to_string(),
             quality: http_res
                 .web_server_matched
@@ -437,7 +897,12 @@ impl HuginnAnalyzer {
     }
 
     /// Process TLS client data
-    fn process_tls_client(&self, tls_client: &TlsClientOutput) -> Result<TlsClientData> {
+    fn process_tls_client(
+        &self,
+        tls_client: &TlsClientOutput,
+        wire: &WireDetails,
+        observed_at: DateTime<Utc>,
+    ) -> Result<TlsClientData> {
         let details = TlsDetails {
             version: tls_client.sig.version.to_string(),
             sni: tls_client.sig.sni.as_ref().map(|s| s.to_string()),
@@ -446,7 +911,12 @@ impl HuginnAnalyzer {
             extensions: tls_client.sig.extensions.clone(),
             signature_algorithms: tls_client.sig.signature_algorithms.clone(),
             elliptic_curves: tls_client.sig.elliptic_curves.clone(),
+            elliptic_curve_point_formats: wire.tls_point_formats.clone().unwrap_or_default(),
         };
+        let (ja3, ja3_hash) = self
+            .ja3(&tls_client.sig, wire)
+            .map(|ja3| (ja3.string, ja3.hash))
+            .unwrap_or_default();
 
         Ok(TlsClientData {
             source: NetworkEndpoint {
@@ -455,25 +925,32 @@ impl HuginnAnalyzer {
             },
             ja4: tls_client.sig.ja4.full.value().to_string(),
             ja4_raw: tls_client.sig.ja4.raw.value().to_string(),
+            ja3,
+            ja3_hash,
             details,
-            timestamp: Utc::now(),
+            timestamp: observed_at,
         })
     }
 
     /// Process MTU data
-    fn process_mtu_data(&self, mtu: &MTUOutput) -> Result<MtuData> {
+    fn process_mtu_data(&self, mtu: &MTUOutput, observed_at: DateTime<Utc>) -> Result<MtuData> {
         Ok(MtuData {
             source: NetworkEndpoint {
                 ip: mtu.source.ip.to_string(),
                 port: mtu.source.port,
             },
+            link: Some(mtu.link.to_string()).filter(|link| !link.is_empty()),
             mtu_value: mtu.mtu,
-            timestamp: Utc::now(),
+            timestamp: observed_at,
         })
     }
 
     /// Process uptime data
-    fn process_uptime_data(&self, uptime: &UptimeOutput) -> Result<UptimeData> {
+    fn process_uptime_data(
+        &self,
+        uptime: &UptimeOutput,
+        observed_at: DateTime<Utc>,
+    ) -> Result<UptimeData> {
         // Calculate total seconds from available fields
         let total_seconds = (uptime.days as u64 * 24 * 3600)
             + (uptime.hours as u64 * 3600)
@@ -485,7 +962,12 @@ impl HuginnAnalyzer {
                 port: uptime.source.port,
             },
             uptime_seconds: total_seconds,
-            timestamp: Utc::now(),
+            days: uptime.days,
+            hours: uptime.hours,
+            minutes: uptime.min,
+            modulo_days: uptime.up_mod_days,
+            frequency_hz: uptime.freq,
+            timestamp: observed_at,
         })
     }
 
@@ -550,7 +1032,11 @@ impl HuginnAnalyzer {
     }
 
     /// Analyze HTTP request
-    fn analyze_http_request(&self, http_req: &HttpRequestOutput) -> Result<Option<HttpAnalysis>> {
+    fn analyze_http_request(
+        &self,
+        http_req: &HttpRequestOutput,
+        wire: &WireDetails,
+    ) -> Result<Option<HttpAnalysis>> {
         let quality = http_req
             .browser_matched
             .as_ref()
@@ -586,38 +1072,78 @@ impl HuginnAnalyzer {
             expected_software: http_req.sig.expsw.clone(),
         };
 
-        // Extract request-specific data from headers
-        let horder_strings: Vec<String> =
-            http_req.sig.horder.iter().map(|h| h.to_string()).collect();
-
-        let request_data = crate::profile::HttpRequestData {
-            user_agent: self.extract_header_value_from_horder(&horder_strings, "user-agent"),
-            accept: self.extract_header_value_from_horder(&horder_strings, "accept"),
-            accept_language: self
-                .extract_header_value_from_horder(&horder_strings, "accept-language"),
-            accept_encoding: self
-                .extract_header_value_from_horder(&horder_strings, "accept-encoding"),
-            connection: self.extract_header_value_from_horder(&horder_strings, "connection"),
-            method: Some("GET".to_string()), // Default, could be extracted from signature
-            host: self.extract_header_value_from_horder(&horder_strings, "host"),
-            signature: http_req.sig.to_string(),
-            quality,
-        };
+        let request_data = self.process_http_request(http_req, wire)?;
+        let user_agent = request_data.user_agent.as_deref().map(UserAgent::parse);
 
         Ok(Some(HttpAnalysis {
+            diagnosis: Self::http_diagnosis(http_req, &browser, user_agent.as_ref()),
             browser,
             quality,
             language: http_req.lang.as_ref().map(|l| l.to_string()),
-            diagnosis: http_req.diagnosis.to_string(),
             signature: http_req.sig.to_string(),
+            user_agent,
             details,
             request: Some(request_data),
             response: None,
         }))
     }
 
+    /// Analyze HTTP response
+    fn analyze_http_response(
+        &self,
+        http_res: &HttpResponseOutput,
+        wire: &WireDetails,
+    ) -> Result<Option<HttpAnalysis>> {
+        let quality = http_res
+            .web_server_matched
+            .as_ref()
+            .map(|m| m.quality as f64)
+            .unwrap_or(0.0);
+
+        if quality < self.config.min_quality {
+            return Ok(None);
+        }
+
+        let details = HttpDetails {
+            version: http_res.sig.version.to_string(),
+            header_order: http_res
+                .sig
+                .horder
+                .iter()
+                .map(|h| h.to_string())
+                .collect::<Vec<_>>()
+                .join(", "),
+            headers_absent: http_res
+                .sig
+                .habsent
+                .iter()
+                .map(|h| h.to_string())
+                .collect::<Vec<_>>()
+                .join(", "),
+            expected_software: http_res.sig.expsw.clone(),
+        };
+
+        let response_data = self.process_http_response(http_res, wire)?;
+
+        Ok(Some(HttpAnalysis {
+            browser: "Unknown".to_string(),
+            quality,
+            language: None,
+            diagnosis: http_res.diagnosis.to_string(),
+            signature: http_res.sig.to_string(),
+            user_agent: None,
+            details,
+            request: None,
+            response: Some(response_data),
+        }))
+    }
+
     /// Analyze TLS client
-    fn analyze_tls_client(&self, tls_client: &TlsClientOutput) -> Result<Option<TlsAnalysis>> {
+    fn analyze_tls_client(
+        &self,
+        tls_client: &TlsClientOutput,
+        wire: &WireDetails,
+    ) -> Result<Option<TlsAnalysis>> {
         let details = TlsDetails {
             version: tls_client.sig.version.to_string(),
             sni: tls_client.sig.sni.as_ref().map(|s| s.to_string()),
@@ -626,13 +1152,27 @@ impl HuginnAnalyzer {
             extensions: tls_client.sig.extensions.clone(),
             signature_algorithms: tls_client.sig.signature_algorithms.clone(),
             elliptic_curves: tls_client.sig.elliptic_curves.clone(),
+            elliptic_curve_point_formats: wire.tls_point_formats.clone().unwrap_or_default(),
         };
+        let (ja3, ja3_hash) = self
+            .ja3(&tls_client.sig, wire)
+            .map(|ja3| (ja3.string, ja3.hash))
+            .unwrap_or_default();
+
+        let ja4 = tls_client.sig.ja4.full.value();
+        let client_application = self
+            .ja4_database
+            .as_ref()
+            .and_then(|database| database.lookup(ja4));
 
         Ok(Some(TlsAnalysis {
-            ja4: tls_client.sig.ja4.full.value().to_string(),
+            ja4: ja4.to_string(),
             ja4_raw: tls_client.sig.ja4.raw.value().to_string(),
             ja4_original: tls_client.sig.ja4_original.full.value().to_string(),
             ja4_original_raw: tls_client.sig.ja4_original.raw.value().to_string(),
+            client_application,
+            ja3,
+            ja3_hash,
             details,
         }))
     }
@@ -660,8 +1200,20 @@ impl HuginnAnalyzer {
         parts.join(" ")
     }
 
+    fn extract_web_server_string(&self, web_server: &WebServer) -> String {
+        let mut parts = vec![web_server.name.clone()];
+        if let Some(family) = &web_server.family {
+            parts.push(family.clone());
+        }
+        if let Some(variant) = &web_server.variant {
+            parts.push(variant.clone());
+        }
+        parts.join(" ")
+    }
+
     /// Extract header value from horder field
-    /// horder contains strings like "user-agent=[Mozilla/5.0...]" or "content-type=[application/json]"
+    /// horder contains strings like "Accept=[*/*]"; huginn-net leaves the value
+    /// out for some headers (e.g. Host, User-Agent, Cookie, Server)
     fn extract_header_value_from_horder(
         &self,
         horder: &[String],
@@ -684,6 +1236,62 @@ impl HuginnAnalyzer {
         None
     }
 
+    /// Diagnosis of an HTTP request, without huginn-net's false "dishonest"
+    ///
+    /// huginn-net 1.4 compares the OS named in the User-Agent with the browser
+    /// label of the matched signature, so every browser whose headers match a
+    /// signature comes out dishonest. The verdict is kept only when the
+    /// User-Agent claims another browser than the headers look like.
+    fn http_diagnosis(
+        http_req: &HttpRequestOutput,
+        browser: &str,
+        user_agent: Option<&UserAgent>,
+    ) -> String {
+        let claimed = user_agent.and_then(UserAgent::claimed_browser);
+        match (&http_req.diagnosis, claimed) {
+            (HttpDiagnosis::Dishonest, Some(claimed)) if same_browser_family(claimed, browser) => {
+                HttpDiagnosis::Generic.to_string()
+            }
+            (diagnosis, _) => diagnosis.to_string(),
+        }
+    }
+
+    /// Two-digit HTTP version used in the JA4H `a` section
+    fn ja4h_version(version: &HttpVersion) -> &'static str {
+        match version {
+            HttpVersion::V10 => "10",
+            HttpVersion::V11 => "11",
+            _ => "00",
+        }
+    }
+
+    /// JA3 fingerprint of a ClientHello
+    ///
+    /// huginn-net does not report the EC point formats JA3 needs, so there is
+    /// none unless they were read from the captured packet.
+    fn ja3(&self, sig: &ObservableTlsClient, wire: &WireDetails) -> Option<Ja3> {
+        let point_formats = wire.tls_point_formats.as_deref()?;
+        Some(Ja3::from_client_hello(
+            Self::ja3_version(&sig.version),
+            &sig.cipher_suites,
+            &sig.extensions,
+            &sig.elliptic_curves,
+            point_formats,
+        ))
+    }
+
+    /// ClientHello `legacy_version`; TLS 1.3 clients advertise 1.2 (771) there
+    fn ja3_version(version: &TlsVersion) -> u16 {
+        match version {
+            TlsVersion::V1_3 | TlsVersion::V1_2 => 771,
+            TlsVersion::V1_1 => 770,
+            TlsVersion::V1_0 => 769,
+            TlsVersion::Ssl3_0 => 768,
+            TlsVersion::Ssl2_0 => 2,
+            TlsVersion::Unknown(value) => *value,
+        }
+    }
+
     fn extract_distance(&self, ttl: &Ttl) -> u8 {
         match ttl {
             Ttl::Distance(_, hops) => *hops,
@@ -691,6 +1299,39 @@ impl HuginnAnalyzer {
         }
     }
 
+    /// Build the JA4T/JA4TS fingerprint of a SYN or SYN-ACK
+    ///
+    /// huginn-net may report the window as a multiple of the MSS or MTU; the raw
+    /// value is recovered from the MSS (MTU = MSS + IP/TCP headers). Windows only
+    /// known modulo a value can't be recovered.
+    fn ja4t(sig: &ObservableTcp) -> Option<Ja4t> {
+        let headers: u32 = match sig.version {
+            IpVersion::V6 => 60,
+            _ => 40,
+        };
+        let window_size = match sig.wsize {
+            WindowSize::Value(value) => Some(value as u32),
+            WindowSize::Mss(multiplier) => sig.mss.map(|mss| mss as u32 * multiplier as u32),
+            WindowSize::Mtu(multiplier) => sig
+                .mss
+                .map(|mss| (mss as u32 + headers) * multiplier as u32),
+            WindowSize::Mod(_) | WindowSize::Any => None,
+        }?;
+
+        let options = sig
+            .olayout
+            .iter()
+            .map(|option| Self::convert_tcp_option(option).number())
+            .collect();
+
+        Some(Ja4t {
+            window_size: u16::try_from(window_size).ok()?,
+            options,
+            mss: sig.mss,
+            window_scale: sig.wscale,
+        })
+    }
+
     fn convert_tcp_details(&self, sig: &ObservableTcp) -> TcpDetails {
         TcpDetails {
             version: match sig.version {
@@ -699,33 +1340,54 @@ impl HuginnAnalyzer {
                 IpVersion::Any => "Unknown".to_string(),
             },
             initial_ttl: match sig.ittl {
-                Ttl::Distance(_, hops) => format!("Distance*{}", hops),
-                Ttl::Value(value) => format!("Value*{}", value),
-                Ttl::Bad(value) => format!("Bad*{}", value),
-                Ttl::Guess(value) => format!("Guess*{}", value),
+                Ttl::Value(value) => TcpTtl {
+                    kind: TtlKind::Value,
+                    value,
+                    distance: None,
+                },
+                Ttl::Distance(value, hops) => TcpTtl {
+                    kind: TtlKind::Distance,
+                    value,
+                    distance: Some(hops),
+                },
+                Ttl::Guess(value) => TcpTtl {
+                    kind: TtlKind::Guess,
+                    value,
+                    distance: None,
+                },
+                Ttl::Bad(value) => TcpTtl {
+                    kind: TtlKind::Bad,
+                    value,
+                    distance: None,
+                },
             },
             options_length: sig.olen,
             mss: sig.mss,
             window_size: match sig.wsize {
-                WindowSize::Mod(val) => format!("MOD*{}", val),
-                WindowSize::Mss(val) => format!("MSS*{}", val),
-                WindowSize::Mtu(val) => format!("MTU*{}", val),
-                WindowSize::Value(val) => format!("Value*{}", val),
-                WindowSize::Any => "Any".to_string(),
+                WindowSize::Value(value) => TcpWindowSize {
+                    kind: WindowSizeKind::Value,
+                    value: Some(value),
+                },
+                WindowSize::Mss(multiplier) => TcpWindowSize {
+                    kind: WindowSizeKind::Mss,
+                    value: Some(multiplier as u16),
+                },
+                WindowSize::Mtu(multiplier) => TcpWindowSize {
+                    kind: WindowSizeKind::Mtu,
+                    value: Some(multiplier as u16),
+                },
+                WindowSize::Mod(modulus) => TcpWindowSize {
+                    kind: WindowSizeKind::Mod,
+                    value: Some(modulus),
+                },
+                WindowSize::Any => TcpWindowSize {
+                    kind: WindowSizeKind::Any,
+                    value: None,
+                },
             },
             window_scale: sig.wscale,
-            options_layout: sig
-                .olayout
-                .iter()
-                .map(|opt| format!("{:?}", opt))
-                .collect::<Vec<_>>()
-                .join(","),
-            quirks: sig
-                .quirks
-                .iter()
-                .map(|quirk| format!("{:?}", quirk))
-                .collect::<Vec<_>>()
-                .join(","),
+            options: sig.olayout.iter().map(Self::convert_tcp_option).collect(),
+            quirks: sig.quirks.iter().map(Self::convert_tcp_quirk).collect(),
             payload_class: match sig.pclass {
                 PayloadSize::Zero => "0".to_string(),
                 PayloadSize::NonZero => "+".to_string(),
@@ -734,6 +1396,41 @@ impl HuginnAnalyzer {
         }
     }
 
+    fn convert_tcp_option(option: &TcpOption) -> TcpOptionKind {
+        match option {
+            TcpOption::Eol(padding) => TcpOptionKind::Eol(*padding),
+            TcpOption::Nop => TcpOptionKind::Nop,
+            TcpOption::Mss => TcpOptionKind::Mss,
+            TcpOption::Ws => TcpOptionKind::Ws,
+            TcpOption::Sok => TcpOptionKind::Sok,
+            TcpOption::Sack => TcpOptionKind::Sack,
+            TcpOption::TS => TcpOptionKind::Ts,
+            TcpOption::Unknown(kind) => TcpOptionKind::Unknown(*kind),
+        }
+    }
+
+    fn convert_tcp_quirk(quirk: &Quirk) -> TcpQuirk {
+        match quirk {
+            Quirk::Df => TcpQuirk::Df,
+            Quirk::NonZeroID => TcpQuirk::NonZeroId,
+            Quirk::ZeroID => TcpQuirk::ZeroId,
+            Quirk::Ecn => TcpQuirk::Ecn,
+            Quirk::MustBeZero => TcpQuirk::MustBeZero,
+            Quirk::FlowID => TcpQuirk::FlowId,
+            Quirk::SeqNumZero => TcpQuirk::SeqNumZero,
+            Quirk::AckNumNonZero => TcpQuirk::AckNumNonZero,
+            Quirk::AckNumZero => TcpQuirk::AckNumZero,
+            Quirk::NonZeroURG => TcpQuirk::NonZeroUrg,
+            Quirk::Urg => TcpQuirk::Urg,
+            Quirk::Push => TcpQuirk::Push,
+            Quirk::OwnTimestampZero => TcpQuirk::OwnTimestampZero,
+            Quirk::PeerTimestampNonZero => TcpQuirk::PeerTimestampNonZero,
+            Quirk::TrailinigNonZero => TcpQuirk::TrailingNonZero,
+            Quirk::ExcessiveWindowScaling => TcpQuirk::ExcessiveWindowScaling,
+            Quirk::OptBad => TcpQuirk::OptBad,
+        }
+    }
+
     // Event emission methods
     fn emit_tcp_event(&self, profile: &TrafficProfile, _syn: &SynTCPOutput) {
         if let Some(tcp) = &profile.tcp {
@@ -742,7 +1439,7 @@ impl HuginnAnalyzer {
                 port: profile.port,
                 os: tcp.os.clone(),
                 quality: tcp.quality,
-                timestamp: Utc::now(),
+                timestamp: profile.timestamp,
             });
         }
     }
@@ -754,7 +1451,7 @@ impl HuginnAnalyzer {
                 port: profile.port,
                 os: tcp.os.clone(),
                 quality: tcp.quality,
-                timestamp: Utc::now(),
+                timestamp: profile.timestamp,
             });
         }
     }
@@ -766,18 +1463,37 @@ impl HuginnAnalyzer {
                 port: profile.port,
                 browser: http.browser.clone(),
                 quality: http.quality,
-                timestamp: Utc::now(),
+                timestamp: profile.timestamp,
             });
         }
     }
 
+    fn emit_http_response_event(&self, profile: &TrafficProfile, _http_res: &HttpResponseOutput) {
+        let response = profile
+            .http
+            .as_ref()
+            .and_then(|http| http.response.as_ref());
+        if let Some(response) = response {
+            self.event_dispatcher
+                .dispatch(TrafficEvent::HttpResponseAnalyzed {
+                    ip: profile.ip,
+                    port: profile.port,
+                    web_server: response.web_server.clone(),
+                    status: response.status.clone(),
+                    diagnosis: response.diagnosis.clone(),
+                    quality: response.quality,
+                    timestamp: profile.timestamp,
+                });
+        }
+    }
+
     fn emit_tls_event(&self, profile: &TrafficProfile, _tls_client: &TlsClientOutput) {
         if let Some(tls) = &profile.tls {
             self.event_dispatcher.dispatch(TrafficEvent::TlsAnalyzed {
                 ip: profile.ip,
                 port: profile.port,
                 ja4: tls.ja4.clone(),
-                timestamp: Utc::now(),
+                timestamp: profile.timestamp,
             });
         }
     }
@@ -788,3 +1504,126 @@ impl Default for HuginnAnalyzer {
         Self::new()
     }
 }
+
+#[cfg(test)]
+impl HuginnAnalyzer {
+    /// Profile of a client that connects and sends `request`, analyzed from
+    /// what huginn-net and the wire tracker make of the frames
+    pub(crate) fn analyze_request(&self, request: &[u8]) -> TrafficProfile {
+        use crate::wire::{tcp_frame, WireTracker};
+        use pnet_packet::tcp::TcpFlags;
+
+        let (client, server) = (([10, 0, 0, 5], 40001), ([93, 184, 216, 34], 80));
+        let database = huginn_net::db::Database::default();
+        let mut huginn = huginn_net::HuginnNet::new(Some(&database), 16, None);
+        let mut tracker = WireTracker::default();
+
+        let mut profile = None;
+        for frame in [
+            tcp_frame(client, server, TcpFlags::SYN, b""),
+            tcp_frame(client, server, TcpFlags::PSH | TcpFlags::ACK, request),
+        ] {
+            let wire = tracker.observe(&frame);
+            profile = self
+                .analyze_packet(huginn.analyze_tcp(&frame), &wire, self.now())
+                .unwrap();
+        }
+        let mut profile = profile.expect("huginn-net reports the request");
+        self.check_consistency(&mut profile);
+        self.score_risk(&mut profile);
+        profile
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::user_agent::AgentCategory;
+    use huginn_net::tcp::Ttl;
+
+    fn syn(version: IpVersion, wsize: WindowSize, mss: Option<u16>) -> ObservableTcp {
+        ObservableTcp {
+            version,
+            ittl: Ttl::Value(64),
+            olen: 0,
+            mss,
+            wsize,
+            wscale: Some(7),
+            olayout: vec![TcpOption::Mss, TcpOption::Nop, TcpOption::Ws],
+            quirks: vec![],
+            pclass: PayloadSize::Zero,
+        }
+    }
+
+    fn window(version: IpVersion, wsize: WindowSize, mss: Option<u16>) -> Option<u16> {
+        HuginnAnalyzer::ja4t(&syn(version, wsize, mss)).map(|ja4t| ja4t.window_size)
+    }
+
+    #[test]
+    fn test_ja4t_recovers_the_raw_window() {
+        assert_eq!(
+            window(IpVersion::V4, WindowSize::Mss(44), Some(1460)),
+            Some(64240)
+        );
+        assert_eq!(
+            window(IpVersion::V4, WindowSize::Mtu(4), Some(1460)),
+            Some(6000)
+        );
+        assert_eq!(
+            window(IpVersion::V6, WindowSize::Mtu(4), Some(1440)),
+            Some(6000)
+        );
+        assert_eq!(
+            window(IpVersion::V4, WindowSize::Value(29200), Some(1460)),
+            Some(29200)
+        );
+        assert_eq!(
+            window(IpVersion::V4, WindowSize::Value(29200), None),
+            Some(29200)
+        );
+
+        let ja4t = HuginnAnalyzer::ja4t(&syn(IpVersion::V4, WindowSize::Mss(44), Some(1460)));
+        assert_eq!(ja4t.unwrap().to_string(), "64240_2-1-3_1460_7");
+    }
+
+    #[test]
+    fn test_ja4t_needs_the_mss_for_multiples() {
+        assert_eq!(window(IpVersion::V4, WindowSize::Mss(44), None), None);
+        assert_eq!(window(IpVersion::V4, WindowSize::Mtu(4), None), None);
+        assert_eq!(
+            window(IpVersion::V4, WindowSize::Mod(1024), Some(1460)),
+            None
+        );
+        // Larger than a 16-bit window field
+        assert_eq!(window(IpVersion::V4, WindowSize::Mss(50), Some(1460)), None);
+    }
+
+    #[test]
+    fn test_user_agent_and_host_come_from_the_request() {
+        let profile = HuginnAnalyzer::new().analyze_request(
+            b"GET /robots.txt HTTP/1.1\r\nHost: example.com\r\nUser-Agent: curl/8.5.0\r\nAccept: */*\r\n\r\n",
+        );
+
+        // huginn-net lists both headers in horder without their values
+        let request = profile.raw_data.http_request.as_ref().unwrap();
+        assert_eq!(request.user_agent.as_deref(), Some("curl/8.5.0"));
+        assert_eq!(request.host.as_deref(), Some("example.com"));
+
+        let user_agent = profile.http.as_ref().unwrap().user_agent.as_ref().unwrap();
+        assert_eq!(user_agent.category, AgentCategory::Tool);
+    }
+
+    #[test]
+    fn test_ja4h_hashes_the_cookies_of_the_request() {
+        let profile = HuginnAnalyzer::new().analyze_request(
+            b"GET / HTTP/1.1\r\nHost: example.com\r\nCookie: sid=abc; lang=en\r\nAccept: */*\r\n\r\n",
+        );
+
+        let request = profile.raw_data.http_request.unwrap();
+        assert_eq!(
+            request.ja4h_raw.as_deref(),
+            Some("ge11cn020000_Host,Accept_lang,sid_lang=en,sid=abc")
+        );
+        assert!(!request.ja4h.unwrap().ends_with("_000000000000"));
+    }
+}
@@ -0,0 +1,172 @@
+use crate::error::{HuginnError, Result};
+use crate::profile::{HttpAnalysis, RawFingerprintData, TcpAnalysis, TlsAnalysis, TrafficProfile};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::net::{IpAddr, SocketAddr};
+use std::str::FromStr;
+
+/// Key identifying a single TCP flow (client ip:port, server ip:port)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct ConnectionKey {
+    /// Client (connection initiator) endpoint
+    pub client: SocketAddr,
+    /// Server (connection acceptor) endpoint
+    pub server: SocketAddr,
+}
+
+impl ConnectionKey {
+    /// Create a new connection key
+    pub fn new(client: SocketAddr, server: SocketAddr) -> Self {
+        Self { client, server }
+    }
+
+    /// Client IP address of this connection
+    pub fn client_ip(&self) -> IpAddr {
+        self.client.ip()
+    }
+}
+
+impl fmt::Display for ConnectionKey {
+    /// Formats as `client-server`, e.g. `192.168.1.10:51234-93.184.216.34:443`
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}-{}", self.client, self.server)
+    }
+}
+
+impl FromStr for ConnectionKey {
+    type Err = HuginnError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        // Socket addresses never contain '-', so the first one is the separator
+        let (client, server) = s
+            .split_once('-')
+            .ok_or_else(|| HuginnError::invalid_data(format!("Invalid connection key: {}", s)))?;
+
+        let client = client
+            .parse()
+            .map_err(|e| HuginnError::invalid_data(format!("Invalid client endpoint: {}", e)))?;
+        let server = server
+            .parse()
+            .map_err(|e| HuginnError::invalid_data(format!("Invalid server endpoint: {}", e)))?;
+
+        Ok(Self { client, server })
+    }
+}
+
+/// Everything observed on a single connection, keyed by its 4-tuple
+///
+/// Unlike [`TrafficProfile`], which rolls data up per client IP, an observation
+/// never mixes data from two different flows.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConnectionObservation {
+    /// Connection 4-tuple
+    pub key: ConnectionKey,
+    /// Raw fingerprint data seen on this connection
+    pub raw_data: RawFingerprintData,
+    /// TCP analysis from the client SYN
+    pub tcp_client: Option<TcpAnalysis>,
+    /// TCP analysis from the server SYN-ACK
+    pub tcp_server: Option<TcpAnalysis>,
+    /// HTTP analysis results
+    pub http: Option<HttpAnalysis>,
+    /// TLS analysis results
+    pub tls: Option<TlsAnalysis>,
+    /// First time this connection was seen
+    pub first_seen: DateTime<Utc>,
+    /// Last time this connection was updated
+    pub last_updated: DateTime<Utc>,
+    /// Number of fingerprint results recorded for this connection
+    pub packet_count: u64,
+}
+
+impl ConnectionObservation {
+    /// Create a new observation from the profile produced for one fingerprint result
+    pub fn from_profile(key: ConnectionKey, profile: &TrafficProfile) -> Self {
+        let mut observation = Self {
+            key,
+            raw_data: RawFingerprintData::default(),
+            tcp_client: None,
+            tcp_server: None,
+            http: None,
+            tls: None,
+            first_seen: profile.metadata.first_seen,
+            last_updated: profile.metadata.last_updated,
+            packet_count: 0,
+        };
+        observation.record(profile);
+        observation
+    }
+
+    /// Record the data of a newly analyzed profile for this connection
+    pub fn record(&mut self, profile: &TrafficProfile) {
+        if profile.tcp_client.is_some() {
+            self.tcp_client = profile.tcp_client.clone();
+        }
+        if profile.tcp_server.is_some() {
+            self.tcp_server = profile.tcp_server.clone();
+        }
+        if let Some(http) = &profile.http {
+            match &mut self.http {
+                Some(existing) => existing.merge(http.clone()),
+                None => self.http = Some(http.clone()),
+            }
+        }
+        if profile.tls.is_some() {
+            self.tls = profile.tls.clone();
+        }
+
+        let raw = &profile.raw_data;
+        if raw.syn.is_some() {
+            self.raw_data.syn = raw.syn.clone();
+        }
+        if raw.syn_ack.is_some() {
+            self.raw_data.syn_ack = raw.syn_ack.clone();
+        }
+        if raw.http_request.is_some() {
+            self.raw_data.http_request = raw.http_request.clone();
+        }
+        if raw.http_response.is_some() {
+            self.raw_data.http_response = raw.http_response.clone();
+        }
+        if raw.tls_client.is_some() {
+            self.raw_data.tls_client = raw.tls_client.clone();
+        }
+        if raw.mtu.is_some() {
+            self.raw_data.mtu = raw.mtu.clone();
+        }
+        if raw.uptime.is_some() {
+            self.raw_data.uptime = raw.uptime.clone();
+        }
+        if raw.source_ip.is_some() {
+            self.raw_data.source_ip = raw.source_ip.clone();
+        }
+
+        self.first_seen = self.first_seen.min(profile.metadata.first_seen);
+        self.last_updated = self.last_updated.max(profile.metadata.last_updated);
+        self.packet_count += profile.metadata.packet_count;
+    }
+
+    /// Get a summary string of available data
+    pub fn summary(&self) -> String {
+        let mut parts = Vec::new();
+        if self.tcp_client.is_some() {
+            parts.push("TCP-Client");
+        }
+        if self.tcp_server.is_some() {
+            parts.push("TCP-Server");
+        }
+        if self.http.is_some() {
+            parts.push("HTTP");
+        }
+        if self.tls.is_some() {
+            parts.push("TLS");
+        }
+
+        if parts.is_empty() {
+            "No data".to_string()
+        } else {
+            parts.join(" + ")
+        }
+    }
+}
@@ -0,0 +1,431 @@
+use crate::profile::{HttpAnalysis, TcpAnalysis, TlsAnalysis, TrafficProfile};
+use crate::user_agent::{OsFamily, UserAgent};
+use serde::{Deserialize, Serialize};
+
+/// Kind of cross-layer inconsistency
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FindingKind {
+    /// User-Agent OS does not match the OS guessed from the TCP SYN
+    UserAgentOsMismatch,
+    /// User-Agent claims a browser but the TLS ClientHello looks like a library/tool
+    TlsClientMismatch,
+    /// User-Agent claims a modern browser but negotiates an outdated TLS version
+    OutdatedTlsVersion,
+    /// User-Agent does not match the HTTP header layout (p0f diagnosis or browser match)
+    HttpSignatureMismatch,
+}
+
+impl FindingKind {
+    /// Stable snake_case name of this kind
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FindingKind::UserAgentOsMismatch => "user_agent_os_mismatch",
+            FindingKind::TlsClientMismatch => "tls_client_mismatch",
+            FindingKind::OutdatedTlsVersion => "outdated_tls_version",
+            FindingKind::HttpSignatureMismatch => "http_signature_mismatch",
+        }
+    }
+}
+
+/// How strongly a finding suggests spoofing
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    Low,
+    Medium,
+    High,
+}
+
+/// A single cross-layer inconsistency found on a profile
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ConsistencyFinding {
+    /// What kind of mismatch this is
+    pub kind: FindingKind,
+    /// How suspicious the mismatch is
+    pub severity: Severity,
+    /// What the client claims (usually derived from the User-Agent)
+    pub claimed: String,
+    /// What the lower layer actually looks like
+    pub observed: String,
+    /// Human readable explanation
+    pub description: String,
+}
+
+impl OsFamily {
+    /// OS family from a p0f-style label such as "Linux unix 2.2.x-3.x" or "Windows win 7 or 8"
+    fn from_tcp_label(label: &str) -> Option<Self> {
+        let label = label.to_lowercase();
+        if label.contains("windows") {
+            Some(OsFamily::Windows)
+        } else if label.contains("android") {
+            Some(OsFamily::Android)
+        } else if label.starts_with("ios") || label.contains(" ios") {
+            Some(OsFamily::Ios)
+        } else if label.contains("mac os") || label.contains("macos") {
+            Some(OsFamily::MacOs)
+        } else if label.contains("linux") {
+            Some(OsFamily::Linux)
+        } else if label.contains("bsd") {
+            Some(OsFamily::Bsd)
+        } else {
+            None
+        }
+    }
+
+    /// Whether a client claiming `self` can legitimately produce a `stack` TCP fingerprint
+    fn compatible_with_stack(self, stack: OsFamily) -> bool {
+        match (self, stack) {
+            (a, b) if a == b => true,
+            // Android and ChromeOS run a Linux kernel; p0f usually reports them as Linux
+            (OsFamily::Android, OsFamily::Linux) | (OsFamily::ChromeOs, OsFamily::Linux) => true,
+            // iOS and macOS share the same TCP stack
+            (OsFamily::Ios, OsFamily::MacOs) | (OsFamily::MacOs, OsFamily::Ios) => true,
+            _ => false,
+        }
+    }
+}
+
+/// Whether a p0f HTTP browser label belongs to the same engine family as the claimed browser
+pub(crate) fn same_browser_family(claimed: &str, matched: &str) -> bool {
+    let matched = matched.to_lowercase();
+    match claimed {
+        // Chromium derivatives share Chrome's header layout
+        "Chrome" | "Edge" | "Opera" | "Samsung Internet" => {
+            matched.contains("chrom") || matched.contains("edge")
+        }
+        "Firefox" => matched.contains("firefox"),
+        "Safari" => matched.contains("safari"),
+        _ => true,
+    }
+}
+
+/// The `a` section of a JA4 fingerprint, e.g. `t13d1516h2`
+struct Ja4Prefix {
+    version: String,
+    extension_count: u8,
+    alpn: String,
+}
+
+impl Ja4Prefix {
+    fn parse(ja4: &str) -> Option<Self> {
+        let prefix = ja4.split('_').next()?;
+        if prefix.len() != 10 || !prefix.is_ascii() {
+            return None;
+        }
+        Some(Self {
+            version: prefix[1..3].to_string(),
+            extension_count: prefix[6..8].parse().ok()?,
+            alpn: prefix[8..10].to_string(),
+        })
+    }
+
+    /// No ALPN or fewer extensions than any mainstream browser sends
+    fn looks_like_library(&self) -> bool {
+        self.alpn == "00" || self.extension_count < MIN_BROWSER_EXTENSIONS
+    }
+}
+
+/// Whether a JA4 looks like a TLS library or tool rather than a browser
+///
+/// `None` when the fingerprint cannot be parsed.
+pub(crate) fn ja4_looks_like_library(ja4: &str) -> Option<bool> {
+    Ja4Prefix::parse(ja4).map(|prefix| prefix.looks_like_library())
+}
+
+/// Minimum number of TLS extensions sent by current mainstream browsers
+const MIN_BROWSER_EXTENSIONS: u8 = 13;
+
+/// Compares what a client claims at the HTTP layer with what the TCP and TLS layers reveal
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ConsistencyChecker;
+
+impl ConsistencyChecker {
+    /// Run all cross-layer checks on a profile
+    pub fn check(profile: &TrafficProfile) -> Vec<ConsistencyFinding> {
+        let tcp = profile.tcp_client.as_ref().or(profile.tcp.as_ref());
+        Self::check_layers(tcp, profile.http.as_ref(), profile.tls.as_ref())
+    }
+
+    /// Run all cross-layer checks on individual layer analyses
+    pub fn check_layers(
+        tcp: Option<&TcpAnalysis>,
+        http: Option<&HttpAnalysis>,
+        tls: Option<&TlsAnalysis>,
+    ) -> Vec<ConsistencyFinding> {
+        let mut findings = Vec::new();
+
+        let Some(http) = http else {
+            return findings;
+        };
+        let Some(raw_user_agent) = http
+            .request
+            .as_ref()
+            .and_then(|request| request.user_agent.as_deref())
+        else {
+            return findings;
+        };
+        // Profiles stored before User-Agent parsing existed only carry the raw header
+        let parsed;
+        let user_agent = match &http.user_agent {
+            Some(user_agent) => user_agent,
+            None => {
+                parsed = UserAgent::parse(raw_user_agent);
+                &parsed
+            }
+        };
+
+        if let Some(tcp) = tcp {
+            findings.extend(Self::check_os(user_agent, tcp));
+        }
+        findings.extend(Self::check_http_signature(raw_user_agent, user_agent, http));
+        if let Some(tls) = tls {
+            findings.extend(Self::check_tls(user_agent, tls));
+        }
+
+        findings
+    }
+
+    fn check_os(user_agent: &UserAgent, tcp: &TcpAnalysis) -> Option<ConsistencyFinding> {
+        let claimed = user_agent.os_family?;
+        let stack = OsFamily::from_tcp_label(&tcp.os)?;
+
+        if claimed.compatible_with_stack(stack) {
+            return None;
+        }
+
+        Some(ConsistencyFinding {
+            kind: FindingKind::UserAgentOsMismatch,
+            severity: if tcp.quality >= 0.8 {
+                Severity::High
+            } else {
+                Severity::Medium
+            },
+            claimed: claimed.name().to_string(),
+            observed: tcp.os.clone(),
+            description: format!(
+                "User-Agent claims {} but the TCP SYN looks like {}",
+                claimed.name(),
+                stack.name()
+            ),
+        })
+    }
+
+    fn check_http_signature(
+        raw_user_agent: &str,
+        user_agent: &UserAgent,
+        http: &HttpAnalysis,
+    ) -> Option<ConsistencyFinding> {
+        if http.diagnosis.to_lowercase().contains("dishonest") {
+            return Some(ConsistencyFinding {
+                kind: FindingKind::HttpSignatureMismatch,
+                severity: Severity::High,
+                claimed: raw_user_agent.to_string(),
+                observed: http.browser.clone(),
+                description: "HTTP header layout does not match the claimed User-Agent".to_string(),
+            });
+        }
+
+        let claimed = user_agent.claimed_browser()?;
+        if http.browser == "Unknown" || same_browser_family(claimed, &http.browser) {
+            return None;
+        }
+
+        Some(ConsistencyFinding {
+            kind: FindingKind::HttpSignatureMismatch,
+            severity: Severity::Medium,
+            claimed: claimed.to_string(),
+            observed: http.browser.clone(),
+            description: format!(
+                "User-Agent claims {} but the HTTP headers look like {}",
+                claimed, http.browser
+            ),
+        })
+    }
+
+    fn check_tls(user_agent: &UserAgent, tls: &TlsAnalysis) -> Vec<ConsistencyFinding> {
+        let mut findings = Vec::new();
+
+        let Some(claimed) = user_agent.claimed_browser() else {
+            return findings;
+        };
+        let Some(prefix) = Ja4Prefix::parse(&tls.ja4) else {
+            return findings;
+        };
+
+        if matches!(prefix.version.as_str(), "s3" | "10" | "11") {
+            findings.push(ConsistencyFinding {
+                kind: FindingKind::OutdatedTlsVersion,
+                severity: Severity::Medium,
+                claimed: claimed.to_string(),
+                observed: tls.ja4.clone(),
+                description: format!(
+                    "User-Agent claims {} but the ClientHello offers at most TLS {}",
+                    claimed, prefix.version
+                ),
+            });
+        }
+
+        if prefix.looks_like_library() {
+            findings.push(ConsistencyFinding {
+                kind: FindingKind::TlsClientMismatch,
+                severity: Severity::High,
+                claimed: claimed.to_string(),
+                observed: tls.ja4.clone(),
+                description: format!(
+                    "User-Agent claims {} but the TLS ClientHello ({} extensions, ALPN {}) looks like a library or tool",
+                    claimed, prefix.extension_count, prefix.alpn
+                ),
+            });
+        }
+
+        findings
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::profile::{
+        HttpDetails, HttpRequestData, TcpDetails, TcpTtl, TcpWindowSize, TlsDetails, TtlKind,
+        WindowSizeKind,
+    };
+
+    fn tcp(os: &str, quality: f64) -> TcpAnalysis {
+        TcpAnalysis {
+            os: os.to_string(),
+            quality,
+            distance: 0,
+            signature: String::new(),
+            details: TcpDetails {
+                version: "IPv4".to_string(),
+                initial_ttl: TcpTtl {
+                    kind: TtlKind::Value,
+                    value: 64,
+                    distance: None,
+                },
+                options_length: 0,
+                mss: Some(1460),
+                window_size: TcpWindowSize {
+                    kind: WindowSizeKind::Mss,
+                    value: Some(44),
+                },
+                window_scale: Some(7),
+                options: vec![],
+                quirks: vec![],
+                payload_class: "0".to_string(),
+            },
+        }
+    }
+
+    fn http(user_agent: &str, browser: &str, diagnosis: &str) -> HttpAnalysis {
+        HttpAnalysis {
+            browser: browser.to_string(),
+            quality: 1.0,
+            language: None,
+            diagnosis: diagnosis.to_string(),
+            signature: String::new(),
+            user_agent: Some(UserAgent::parse(user_agent)),
+            details: HttpDetails {
+                version: "1".to_string(),
+                header_order: String::new(),
+                headers_absent: String::new(),
+                expected_software: String::new(),
+            },
+            request: Some(HttpRequestData {
+                user_agent: Some(user_agent.to_string()),
+                accept: None,
+                accept_language: None,
+                accept_encoding: None,
+                connection: None,
+                method: Some("GET".to_string()),
+                uri: Some("/".to_string()),
+                version: "1".to_string(),
+                ja4h: None,
+                ja4h_raw: None,
+                host: None,
+                signature: String::new(),
+                quality: 1.0,
+            }),
+            response: None,
+        }
+    }
+
+    fn tls(ja4: &str) -> TlsAnalysis {
+        TlsAnalysis {
+            ja4: ja4.to_string(),
+            ja4_raw: String::new(),
+            ja4_original: String::new(),
+            ja4_original_raw: String::new(),
+            client_application: None,
+            ja3: String::new(),
+            ja3_hash: String::new(),
+            details: TlsDetails {
+                version: "13".to_string(),
+                sni: None,
+                alpn: None,
+                cipher_suites: vec![],
+                extensions: vec![],
+                signature_algorithms: vec![],
+                elliptic_curves: vec![],
+                elliptic_curve_point_formats: vec![],
+            },
+        }
+    }
+
+    const WINDOWS_CHROME: &str = "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/126.0.0.0 Safari/537.36";
+    const ANDROID_CHROME: &str = "Mozilla/5.0 (Linux; Android 14; Pixel 8) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/126.0.0.0 Mobile Safari/537.36";
+
+    #[test]
+    fn test_spoofed_windows_chrome_from_python() {
+        let findings = ConsistencyChecker::check_layers(
+            Some(&tcp("Linux unix 2.2.x-3.x", 1.0)),
+            Some(&http(WINDOWS_CHROME, "Unknown", "none")),
+            Some(&tls("t13d1109h1_76e208dd3e22_2dae41c691ec")),
+        );
+
+        let kinds: Vec<_> = findings.iter().map(|f| f.kind).collect();
+        assert!(kinds.contains(&FindingKind::UserAgentOsMismatch));
+        assert!(kinds.contains(&FindingKind::TlsClientMismatch));
+        assert!(findings.iter().all(|f| f.severity == Severity::High));
+    }
+
+    #[test]
+    fn test_consistent_android_chrome() {
+        let findings = ConsistencyChecker::check_layers(
+            Some(&tcp("Linux unix 2.2.x-3.x", 1.0)),
+            Some(&http(ANDROID_CHROME, "Chrome", "none")),
+            Some(&tls("t13d1516h2_8daaf6152771_02713d6af862")),
+        );
+        assert!(findings.is_empty());
+    }
+
+    #[test]
+    fn test_http_signature_mismatch() {
+        let dishonest = ConsistencyChecker::check_layers(
+            None,
+            Some(&http(WINDOWS_CHROME, "Chrome", "Dishonest")),
+            None,
+        );
+        assert_eq!(dishonest.len(), 1);
+        assert_eq!(dishonest[0].kind, FindingKind::HttpSignatureMismatch);
+
+        let other_browser = ConsistencyChecker::check_layers(
+            None,
+            Some(&http(WINDOWS_CHROME, "Firefox 10.x or newer", "none")),
+            None,
+        );
+        assert_eq!(other_browser.len(), 1);
+        assert_eq!(other_browser[0].severity, Severity::Medium);
+    }
+
+    #[test]
+    fn test_tools_are_not_flagged() {
+        let findings = ConsistencyChecker::check_layers(
+            Some(&tcp("Linux unix 2.2.x-3.x", 1.0)),
+            Some(&http("curl/8.5.0", "Unknown", "none")),
+            Some(&tls("t13d3112h2_e8f1e7e78f70_6bebaf5329ac")),
+        );
+        assert!(findings.is_empty());
+    }
+}
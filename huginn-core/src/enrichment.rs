@@ -0,0 +1,41 @@
+use crate::error::Result;
+use crate::profile::TrafficProfile;
+use huginn_net::fingerprint_result::FingerprintResult;
+
+/// Custom analysis stage run on every profile the analyzer produces
+///
+/// Enrichers run after the built-in TCP/HTTP/TLS stages, in registration order.
+/// They may change any part of the profile; values that have no dedicated
+/// field belong in [`TrafficProfile::enrichment`] under the enricher's name.
+///
+/// Each enricher works on a copy of the profile: if it returns an error or
+/// panics, its changes are discarded, an `AnalysisError` event is emitted and
+/// the remaining enrichers still run.
+///
+/// ```
+/// use huginn_core::{FingerprintResult, ProfileEnricher, Result, TrafficProfile};
+///
+/// struct InternalRange;
+///
+/// impl ProfileEnricher for InternalRange {
+///     fn name(&self) -> &str {
+///         "internal_range"
+///     }
+///
+///     fn enrich(&self, _result: &FingerprintResult, profile: &mut TrafficProfile) -> Result<()> {
+///         let internal = match profile.ip {
+///             std::net::IpAddr::V4(ip) => ip.is_private(),
+///             std::net::IpAddr::V6(_) => false,
+///         };
+///         profile.enrichment.insert(self.name().to_string(), internal.into());
+///         Ok(())
+///     }
+/// }
+/// ```
+pub trait ProfileEnricher: Send + Sync {
+    /// Stable name used in logs, error events and as the `enrichment` key
+    fn name(&self) -> &str;
+
+    /// Add data to the profile built from `result`
+    fn enrich(&self, result: &FingerprintResult, profile: &mut TrafficProfile) -> Result<()>;
+}
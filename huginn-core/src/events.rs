@@ -1,3 +1,4 @@
+use crate::consistency::ConsistencyFinding;
 use crate::error::Result;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
@@ -39,6 +40,17 @@ pub enum TrafficEvent {
         timestamp: DateTime<Utc>,
     },
 
+    /// HTTP response analysis completed
+    HttpResponseAnalyzed {
+        ip: IpAddr,
+        port: u16,
+        web_server: Option<String>,
+        status: Option<String>,
+        diagnosis: String,
+        quality: f64,
+        timestamp: DateTime<Utc>,
+    },
+
     /// TLS analysis completed
     TlsAnalyzed {
         ip: IpAddr,
@@ -47,6 +59,23 @@ pub enum TrafficEvent {
         timestamp: DateTime<Utc>,
     },
 
+    /// Cross-layer inconsistency found (possible spoofing)
+    InconsistencyDetected {
+        ip: IpAddr,
+        port: u16,
+        findings: Vec<ConsistencyFinding>,
+        timestamp: DateTime<Utc>,
+    },
+
+    /// Host uptime dropped since the last observation
+    HostRebooted {
+        ip: IpAddr,
+        port: u16,
+        previous_boot: DateTime<Utc>,
+        boot_time: DateTime<Utc>,
+        timestamp: DateTime<Utc>,
+    },
+
     /// Analysis error occurred
     AnalysisError {
         ip: IpAddr,
@@ -114,9 +143,47 @@ impl EventHandler for LoggingEventHandler {
                     quality
                 );
             }
+            TrafficEvent::HttpResponseAnalyzed {
+                ip,
+                port,
+                web_server,
+                status,
+                diagnosis,
+                ..
+            } => {
+                tracing::info!(
+                    "HTTP response for {}:{} - Server: {}, Status: {}, Diagnosis: {}",
+                    ip,
+                    port,
+                    web_server.as_deref().unwrap_or("unknown"),
+                    status.as_deref().unwrap_or("unknown"),
+                    diagnosis
+                );
+            }
             TrafficEvent::TlsAnalyzed { ip, port, ja4, .. } => {
                 tracing::info!("TLS analysis for {}:{} - JA4: {}", ip, port, ja4);
             }
+            TrafficEvent::InconsistencyDetected {
+                ip, port, findings, ..
+            } => {
+                for finding in findings {
+                    tracing::warn!(
+                        "Inconsistency for {}:{} - {:?}: {}",
+                        ip,
+                        port,
+                        finding.severity,
+                        finding.description
+                    );
+                }
+            }
+            TrafficEvent::HostRebooted {
+                ip,
+                port,
+                boot_time,
+                ..
+            } => {
+                tracing::info!("Host {}:{} rebooted around {}", ip, port, boot_time);
+            }
             TrafficEvent::AnalysisError {
                 ip, port, error, ..
             } => {
@@ -169,7 +236,10 @@ impl TrafficEvent {
             TrafficEvent::ProfileUpdated { ip, .. } => *ip,
             TrafficEvent::TcpAnalyzed { ip, .. } => *ip,
             TrafficEvent::HttpAnalyzed { ip, .. } => *ip,
+            TrafficEvent::HttpResponseAnalyzed { ip, .. } => *ip,
             TrafficEvent::TlsAnalyzed { ip, .. } => *ip,
+            TrafficEvent::InconsistencyDetected { ip, .. } => *ip,
+            TrafficEvent::HostRebooted { ip, .. } => *ip,
             TrafficEvent::AnalysisError { ip, .. } => *ip,
         }
     }
@@ -181,7 +251,10 @@ impl TrafficEvent {
             TrafficEvent::ProfileUpdated { port, .. } => *port,
             TrafficEvent::TcpAnalyzed { port, .. } => *port,
             TrafficEvent::HttpAnalyzed { port, .. } => *port,
+            TrafficEvent::HttpResponseAnalyzed { port, .. } => *port,
             TrafficEvent::TlsAnalyzed { port, .. } => *port,
+            TrafficEvent::InconsistencyDetected { port, .. } => *port,
+            TrafficEvent::HostRebooted { port, .. } => *port,
             TrafficEvent::AnalysisError { port, .. } => *port,
         }
     }
@@ -193,7 +266,10 @@ impl TrafficEvent {
             TrafficEvent::ProfileUpdated { timestamp, .. } => *timestamp,
             TrafficEvent::TcpAnalyzed { timestamp, .. } => *timestamp,
             TrafficEvent::HttpAnalyzed { timestamp, .. } => *timestamp,
+            TrafficEvent::HttpResponseAnalyzed { timestamp, .. } => *timestamp,
             TrafficEvent::TlsAnalyzed { timestamp, .. } => *timestamp,
+            TrafficEvent::InconsistencyDetected { timestamp, .. } => *timestamp,
+            TrafficEvent::HostRebooted { timestamp, .. } => *timestamp,
             TrafficEvent::AnalysisError { timestamp, .. } => *timestamp,
         }
     }
@@ -0,0 +1,175 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Changes up to this many hops are treated as ordinary routing changes
+const ROUTE_CHANGE_HOPS: u8 = 3;
+
+/// Upper bound on remembered distance runs per profile
+const MAX_RUNS: usize = 32;
+
+/// Upper bound on remembered anomalies per profile
+const MAX_ANOMALIES: usize = 32;
+
+/// Consecutive observations of the same hop distance
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HopSample {
+    /// Hop distance derived from the client SYN TTL
+    pub distance: u8,
+    /// First observation of this run
+    pub first_seen: DateTime<Utc>,
+    /// Latest observation of this run
+    pub last_seen: DateTime<Utc>,
+    /// Observations in this run
+    pub count: u32,
+}
+
+/// What a change in hop distance most likely means
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HopAnomalyKind {
+    /// Small change that stuck, as after a routing change
+    RouteChange,
+    /// Change too large for a route change: a proxy, VPN or spoofed source
+    LargeJump,
+    /// Went back to the distance seen before the last change
+    Flapping,
+}
+
+/// A sudden change in hop distance
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HopAnomaly {
+    pub kind: HopAnomalyKind,
+    /// Distance before the change
+    pub previous: u8,
+    /// Distance after the change
+    pub current: u8,
+    /// When the new distance was first seen
+    pub observed_at: DateTime<Utc>,
+}
+
+/// Hop distance of a host over time
+///
+/// Observations are stored as runs of the same distance, so a stable host keeps
+/// a single entry. Every switch between runs is flagged as an anomaly.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct HopHistory {
+    /// Latest observed distance
+    pub current: Option<u8>,
+    /// Number of times the distance changed
+    pub changes: u32,
+    /// Runs of the same distance, oldest first
+    pub history: Vec<HopSample>,
+    /// Flagged changes, oldest first
+    pub anomalies: Vec<HopAnomaly>,
+}
+
+impl HopHistory {
+    /// Record a hop distance observed at `observed_at`
+    ///
+    /// Returns the anomaly when the distance differs from the previous one.
+    pub fn observe(&mut self, distance: u8, observed_at: DateTime<Utc>) -> Option<HopAnomaly> {
+        self.current = Some(distance);
+
+        let previous = match self.history.last_mut() {
+            Some(run) if run.distance == distance => {
+                run.last_seen = run.last_seen.max(observed_at);
+                run.count += 1;
+                return None;
+            }
+            Some(run) => run.distance,
+            None => {
+                self.push_run(distance, observed_at);
+                return None;
+            }
+        };
+
+        let flapping = self
+            .history
+            .len()
+            .checked_sub(2)
+            .is_some_and(|index| self.history[index].distance == distance);
+        let kind = if flapping {
+            HopAnomalyKind::Flapping
+        } else if previous.abs_diff(distance) > ROUTE_CHANGE_HOPS {
+            HopAnomalyKind::LargeJump
+        } else {
+            HopAnomalyKind::RouteChange
+        };
+        let anomaly = HopAnomaly {
+            kind,
+            previous,
+            current: distance,
+            observed_at,
+        };
+
+        self.changes += 1;
+        self.push_run(distance, observed_at);
+        self.anomalies.push(anomaly.clone());
+        if self.anomalies.len() > MAX_ANOMALIES {
+            self.anomalies.remove(0);
+        }
+
+        Some(anomaly)
+    }
+
+    fn push_run(&mut self, distance: u8, observed_at: DateTime<Utc>) {
+        self.history.push(HopSample {
+            distance,
+            first_seen: observed_at,
+            last_seen: observed_at,
+            count: 1,
+        });
+        if self.history.len() > MAX_RUNS {
+            self.history.remove(0);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    fn at(mins: i64) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339("2024-03-01T12:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc)
+            + Duration::minutes(mins)
+    }
+
+    #[test]
+    fn test_stable_distance_keeps_one_run() {
+        let mut hops = HopHistory::default();
+        for minute in 0..5 {
+            assert_eq!(hops.observe(7, at(minute)), None);
+        }
+
+        assert_eq!(hops.history.len(), 1);
+        assert_eq!(hops.history[0].count, 5);
+        assert_eq!(hops.history[0].last_seen, at(4));
+        assert!(hops.anomalies.is_empty());
+    }
+
+    #[test]
+    fn test_distance_changes_are_classified() {
+        let mut hops = HopHistory::default();
+        hops.observe(7, at(0));
+
+        let route = hops.observe(8, at(1)).unwrap();
+        assert_eq!(route.kind, HopAnomalyKind::RouteChange);
+        assert_eq!((route.previous, route.current), (7, 8));
+
+        assert_eq!(
+            hops.observe(7, at(2)).map(|a| a.kind),
+            Some(HopAnomalyKind::Flapping)
+        );
+        assert_eq!(
+            hops.observe(19, at(3)).map(|a| a.kind),
+            Some(HopAnomalyKind::LargeJump)
+        );
+
+        assert_eq!(hops.current, Some(19));
+        assert_eq!(hops.changes, 3);
+        assert_eq!(hops.history.len(), 4);
+    }
+}
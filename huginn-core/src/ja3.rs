@@ -0,0 +1,87 @@
+use md5::{Digest, Md5};
+use serde::{Deserialize, Serialize};
+
+/// JA3 fingerprint of a TLS ClientHello
+///
+/// `version,ciphers,extensions,curves,point_formats` with each list joined by
+/// `-`, GREASE values removed, and the MD5 of that string as the hash.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Ja3 {
+    /// JA3 string
+    pub string: String,
+    /// MD5 of the JA3 string (lowercase hex)
+    pub hash: String,
+}
+
+impl Ja3 {
+    /// Compute the fingerprint of a ClientHello
+    ///
+    /// `version` is the ClientHello `legacy_version` (771 for TLS 1.2 and 1.3).
+    pub fn from_client_hello(
+        version: u16,
+        cipher_suites: &[u16],
+        extensions: &[u16],
+        elliptic_curves: &[u16],
+        point_formats: &[u8],
+    ) -> Self {
+        let string = format!(
+            "{},{},{},{},{}",
+            version,
+            Self::join(cipher_suites.iter().copied()),
+            Self::join(extensions.iter().copied()),
+            Self::join(elliptic_curves.iter().copied()),
+            point_formats
+                .iter()
+                .map(|format| format.to_string())
+                .collect::<Vec<_>>()
+                .join("-")
+        );
+        let hash = Md5::digest(string.as_bytes())
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect();
+
+        Self { string, hash }
+    }
+
+    /// Join values with `-`, skipping GREASE
+    fn join(values: impl Iterator<Item = u16>) -> String {
+        values
+            .filter(|value| !is_grease(*value))
+            .map(|value| value.to_string())
+            .collect::<Vec<_>>()
+            .join("-")
+    }
+}
+
+/// GREASE values (RFC 8701) are 0x?a?a with both bytes equal
+pub fn is_grease(value: u16) -> bool {
+    value & 0x0f0f == 0x0a0a && value >> 8 == value & 0xff
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strips_grease_and_hashes() {
+        let ja3 = Ja3::from_client_hello(
+            771,
+            &[0x0a0a, 4865, 4866, 4867],
+            &[0x1a1a, 0, 23, 65281, 10, 11],
+            &[0x2a2a, 29, 23, 24],
+            &[0],
+        );
+
+        assert_eq!(ja3.string, "771,4865-4866-4867,0-23-65281-10-11,29-23-24,0");
+        assert_eq!(ja3.hash, "48618013a8b07e58698ab1c0112f1bae");
+    }
+
+    #[test]
+    fn test_detects_grease() {
+        assert!(is_grease(0x0a0a));
+        assert!(is_grease(0xfafa));
+        assert!(!is_grease(0x0a1a));
+        assert!(!is_grease(4865));
+    }
+}
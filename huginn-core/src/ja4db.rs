@@ -0,0 +1,185 @@
+use crate::error::{HuginnError, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+use std::str::FromStr;
+
+/// Confidence of a label whose full JA4 matched
+const EXACT_MATCH_CONFIDENCE: f64 = 1.0;
+
+/// Confidence of a label matched on TLS version, ALPN and cipher suites only
+/// (same `a_b` JA4 sections, different extensions)
+const PARTIAL_MATCH_CONFIDENCE: f64 = 0.5;
+
+/// Client application a JA4 fingerprint was attributed to
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ClientApplication {
+    /// Application label from the JA4 database (e.g. "curl", "Go net/http")
+    pub name: String,
+    /// How closely the fingerprint matched the label (1.0 = exact JA4)
+    pub confidence: f64,
+}
+
+/// Local JA4 → application label database
+///
+/// The file format is one fingerprint per line, the JA4 followed by its label;
+/// blank lines and lines starting with `#` are ignored:
+///
+/// ```text
+/// # ja4                                  label
+/// t13d1516h2_8daaf6152771_02713d6af862   Chrome 12x
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Ja4Database {
+    /// Labels by full JA4
+    exact: HashMap<String, String>,
+    /// Labels by `a_b` JA4 prefix; `None` when several labels share the prefix
+    partial: HashMap<String, Option<String>>,
+}
+
+impl Ja4Database {
+    /// Create an empty database
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Load a database from a label file
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let content = std::fs::read_to_string(path)?;
+        content.parse().map_err(|e: HuginnError| {
+            HuginnError::configuration(format!("JA4 database {}: {}", path.display(), e))
+        })
+    }
+
+    /// Add a label for a JA4 fingerprint
+    pub fn insert<J: Into<String>, L: Into<String>>(&mut self, ja4: J, label: L) {
+        let ja4 = ja4.into();
+        let label = label.into();
+
+        if let Some(prefix) = Self::prefix(&ja4) {
+            self.partial
+                .entry(prefix.to_string())
+                .and_modify(|existing| {
+                    if existing.as_deref() != Some(label.as_str()) {
+                        *existing = None;
+                    }
+                })
+                .or_insert_with(|| Some(label.clone()));
+        }
+        self.exact.insert(ja4, label);
+    }
+
+    /// Look up the application behind a JA4 fingerprint
+    pub fn lookup(&self, ja4: &str) -> Option<ClientApplication> {
+        if let Some(name) = self.exact.get(ja4) {
+            return Some(ClientApplication {
+                name: name.clone(),
+                confidence: EXACT_MATCH_CONFIDENCE,
+            });
+        }
+
+        let prefix = Self::prefix(ja4)?;
+        self.partial
+            .get(prefix)
+            .and_then(|label| label.clone())
+            .map(|name| ClientApplication {
+                name,
+                confidence: PARTIAL_MATCH_CONFIDENCE,
+            })
+    }
+
+    /// Number of labelled fingerprints
+    pub fn len(&self) -> usize {
+        self.exact.len()
+    }
+
+    /// Whether the database has no labels
+    pub fn is_empty(&self) -> bool {
+        self.exact.is_empty()
+    }
+
+    /// The `a_b` part of a JA4 (everything before the extensions hash)
+    fn prefix(ja4: &str) -> Option<&str> {
+        let (prefix, extensions) = ja4.rsplit_once('_')?;
+        if prefix.contains('_') && !extensions.is_empty() {
+            Some(prefix)
+        } else {
+            None
+        }
+    }
+}
+
+impl FromStr for Ja4Database {
+    type Err = HuginnError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut db = Self::new();
+
+        for (index, line) in s.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let (ja4, label) = line
+                .split_once(char::is_whitespace)
+                .map(|(ja4, label)| (ja4, label.trim()))
+                .filter(|(_, label)| !label.is_empty())
+                .ok_or_else(|| {
+                    HuginnError::invalid_data(format!("line {}: missing label", index + 1))
+                })?;
+
+            if Self::prefix(ja4).is_none() {
+                return Err(HuginnError::invalid_data(format!(
+                    "line {}: invalid JA4 fingerprint: {}",
+                    index + 1,
+                    ja4
+                )));
+            }
+
+            db.insert(ja4, label);
+        }
+
+        Ok(db)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DB: &str = "
+# test labels
+t13d1516h2_8daaf6152771_02713d6af862   Chrome 12x
+t13d1516h2_8daaf6152771_e5627efa2ab1   Chrome 12x
+t13d4907h2_0d8feac7bc37_7395dae3b2f3   curl
+t13d4907h2_0d8feac7bc37_aaaaaaaaaaaa   Go net/http
+";
+
+    #[test]
+    fn test_exact_and_partial_matches() {
+        let db: Ja4Database = DB.parse().unwrap();
+        assert_eq!(db.len(), 4);
+
+        let exact = db.lookup("t13d4907h2_0d8feac7bc37_7395dae3b2f3").unwrap();
+        assert_eq!(exact.name, "curl");
+        assert_eq!(exact.confidence, EXACT_MATCH_CONFIDENCE);
+
+        let partial = db.lookup("t13d1516h2_8daaf6152771_ffffffffffff").unwrap();
+        assert_eq!(partial.name, "Chrome 12x");
+        assert_eq!(partial.confidence, PARTIAL_MATCH_CONFIDENCE);
+
+        // curl and Go share a prefix, so only exact matches are trusted there
+        assert!(db.lookup("t13d4907h2_0d8feac7bc37_ffffffffffff").is_none());
+        assert!(db.lookup("not-a-ja4").is_none());
+    }
+
+    #[test]
+    fn test_rejects_malformed_lines() {
+        assert!("t13d1516h2_8daaf6152771_02713d6af862"
+            .parse::<Ja4Database>()
+            .is_err());
+        assert!("garbage Chrome".parse::<Ja4Database>().is_err());
+    }
+}
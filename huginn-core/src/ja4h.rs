@@ -0,0 +1,168 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+/// Section value used when there is nothing to hash (e.g. no cookies)
+const EMPTY_HASH: &str = "000000000000";
+
+/// JA4H fingerprint of an HTTP request, as defined by the FoxIO JA4+ suite
+///
+/// `a_b_c_d` where `a` is method, version, cookie/referer presence, header
+/// count and language; `b` hashes the header names in order; `c` and `d` hash
+/// the sorted cookie names and cookie name=value pairs.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Ja4h {
+    /// Hashed fingerprint
+    pub hash: String,
+    /// Same fingerprint with the hashed sections in clear text
+    pub raw: String,
+}
+
+impl Ja4h {
+    /// Compute the fingerprint of a request
+    ///
+    /// `version` is the two-digit HTTP version ("10", "11", "20"); `headers` are
+    /// (name, value) pairs in the order they appeared on the wire. huginn-net
+    /// leaves the value of the Cookie header out, so `cookie` supplies it.
+    pub fn from_request<'a, I>(
+        method: &str,
+        version: &str,
+        headers: I,
+        cookie: Option<&'a str>,
+    ) -> Self
+    where
+        I: IntoIterator<Item = (&'a str, Option<&'a str>)>,
+    {
+        let mut names = Vec::new();
+        let mut cookies: Vec<&str> = cookie
+            .unwrap_or_default()
+            .split(';')
+            .map(str::trim)
+            .filter(|cookie| !cookie.is_empty())
+            .collect();
+        let mut has_cookie = false;
+        let mut has_referer = false;
+        let mut language = None;
+
+        for (name, value) in headers {
+            if name.eq_ignore_ascii_case("cookie") {
+                has_cookie = true;
+                continue;
+            }
+            if name.eq_ignore_ascii_case("referer") {
+                has_referer = true;
+                continue;
+            }
+            if name.eq_ignore_ascii_case("accept-language") && language.is_none() {
+                language = value;
+            }
+            names.push(name);
+        }
+
+        let method: String = method.chars().take(2).collect::<String>().to_lowercase();
+        let a = format!(
+            "{:0<2}{:0>2}{}{}{:02}{}",
+            method,
+            version,
+            if has_cookie { 'c' } else { 'n' },
+            if has_referer { 'r' } else { 'n' },
+            names.len().min(99),
+            Self::language(language)
+        );
+
+        let b_raw = names.join(",");
+
+        cookies.sort_unstable();
+        let mut cookie_names: Vec<&str> = cookies
+            .iter()
+            .map(|cookie| cookie.split_once('=').map_or(*cookie, |(name, _)| name))
+            .collect();
+        cookie_names.sort_unstable();
+
+        let c_raw = cookie_names.join(",");
+        let d_raw = cookies.join(",");
+
+        Self {
+            hash: format!(
+                "{}_{}_{}_{}",
+                a,
+                Self::hash12(&b_raw),
+                Self::hash12(&c_raw),
+                Self::hash12(&d_raw)
+            ),
+            raw: format!("{}_{}_{}_{}", a, b_raw, c_raw, d_raw),
+        }
+    }
+
+    /// First four characters of the primary Accept-Language, e.g. "en-US,en;q=0.9" → "enus"
+    fn language(accept_language: Option<&str>) -> String {
+        let primary = accept_language
+            .and_then(|value| value.split([',', ';']).next())
+            .unwrap_or_default();
+        let language: String = primary
+            .chars()
+            .filter(|c| c.is_ascii_alphanumeric())
+            .take(4)
+            .collect::<String>()
+            .to_lowercase();
+        format!("{:0<4}", language)
+    }
+
+    /// Truncated SHA-256 used by all JA4+ fingerprints
+    fn hash12(input: &str) -> String {
+        if input.is_empty() {
+            return EMPTY_HASH.to_string();
+        }
+        Sha256::digest(input.as_bytes())
+            .iter()
+            .take(6)
+            .map(|byte| format!("{:02x}", byte))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fingerprints_request_without_cookies() {
+        let ja4h = Ja4h::from_request(
+            "GET",
+            "11",
+            [
+                ("Host", Some("example.com")),
+                ("User-Agent", Some("curl/8.5.0")),
+                ("Accept", Some("*/*")),
+            ],
+            None,
+        );
+
+        assert_eq!(ja4h.raw, "ge11nn030000_Host,User-Agent,Accept__");
+        assert_eq!(
+            ja4h.hash,
+            "ge11nn030000_fe444ad14866_000000000000_000000000000"
+        );
+    }
+
+    #[test]
+    fn test_cookies_and_referer_are_excluded_from_header_list() {
+        let ja4h = Ja4h::from_request(
+            "POST",
+            "20",
+            [
+                ("Host", None),
+                ("Cookie", None),
+                ("Referer", None),
+                ("Accept-Language", Some("en-US,en;q=0.9")),
+            ],
+            Some("sid=abc; lang=en"),
+        );
+
+        assert_eq!(
+            ja4h.raw,
+            "po20cr02enus_Host,Accept-Language_lang,sid_lang=en,sid=abc"
+        );
+        assert!(ja4h.hash.starts_with("po20cr02enus_"));
+        assert_eq!(ja4h.hash.len(), "po20cr02enus".len() + 3 * 13);
+    }
+}
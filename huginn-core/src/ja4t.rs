@@ -0,0 +1,68 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// JA4T (client SYN) / JA4TS (server SYN-ACK) TCP fingerprint
+///
+/// Formatted as `window_options_mss_wscale`, e.g. `64240_2-1-3-1-1-4_1460_8`,
+/// where options are the TCP option kinds in the order they were sent. Missing
+/// MSS or window scale are written as `00`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Ja4t {
+    /// Raw (unscaled) TCP window size
+    pub window_size: u16,
+    /// TCP option kinds in wire order
+    pub options: Vec<u8>,
+    /// Maximum segment size option value
+    pub mss: Option<u16>,
+    /// Window scale option value
+    pub window_scale: Option<u8>,
+}
+
+impl fmt::Display for Ja4t {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let options = if self.options.is_empty() {
+            "00".to_string()
+        } else {
+            self.options
+                .iter()
+                .map(|kind| kind.to_string())
+                .collect::<Vec<_>>()
+                .join("-")
+        };
+
+        write!(
+            f,
+            "{}_{}_{}_{}",
+            self.window_size,
+            options,
+            self.mss
+                .map_or_else(|| "00".to_string(), |mss| mss.to_string()),
+            self.window_scale
+                .map_or_else(|| "00".to_string(), |scale| scale.to_string())
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_formats_syn_fingerprint() {
+        let ja4t = Ja4t {
+            window_size: 64240,
+            options: vec![2, 1, 3, 1, 1, 4],
+            mss: Some(1460),
+            window_scale: Some(8),
+        };
+        assert_eq!(ja4t.to_string(), "64240_2-1-3-1-1-4_1460_8");
+
+        let bare = Ja4t {
+            window_size: 1024,
+            options: vec![],
+            mss: None,
+            window_scale: None,
+        };
+        assert_eq!(bare.to_string(), "1024_00_00_00");
+    }
+}
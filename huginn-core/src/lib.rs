@@ -4,15 +4,45 @@
 //! Provides common data structures, traits, and utilities for network traffic profiling.
 
 pub mod analyzer;
+pub mod clock;
+pub mod connection;
+pub mod consistency;
+pub mod enrichment;
 pub mod error;
 pub mod events;
+pub mod hops;
+pub mod ja3;
+pub mod ja4db;
+pub mod ja4h;
+pub mod ja4t;
+pub mod link;
+pub mod nat;
 pub mod profile;
+pub mod reboot;
+pub mod risk;
+pub mod user_agent;
+pub mod wire;
 
 // Re-export main types
 pub use analyzer::{AnalyzerConfig, HuginnAnalyzer};
+pub use clock::{Clock, FixedClock, SystemClock};
+pub use connection::{ConnectionKey, ConnectionObservation};
+pub use consistency::{ConsistencyChecker, ConsistencyFinding, FindingKind, Severity};
+pub use enrichment::ProfileEnricher;
 pub use error::{HuginnError, Result};
 pub use events::{EventHandler, LoggingEventHandler, TrafficEvent};
-pub use profile::{HttpAnalysis, TcpAnalysis, TlsAnalysis, TrafficProfile};
+pub use hops::{HopAnomaly, HopAnomalyKind, HopHistory};
+pub use ja3::Ja3;
+pub use ja4db::{ClientApplication, Ja4Database};
+pub use ja4h::Ja4h;
+pub use ja4t::Ja4t;
+pub use link::{LinkClassification, LinkTable};
+pub use nat::{HostSignals, NatAnalysis};
+pub use profile::{HttpAnalysis, TcpAnalysis, TlsAnalysis, TrackingHistory, TrafficProfile};
+pub use reboot::{UptimeChange, UptimeHistory};
+pub use risk::{RiskScore, RiskSignal, RiskWeights};
+pub use user_agent::{AgentCategory, DeviceClass, OsFamily, UserAgent};
+pub use wire::{WireDetails, WireTracker};
 
 // Re-export huginn-net types for convenience
 pub use huginn_net::fingerprint_result::FingerprintResult;
@@ -46,6 +76,7 @@ mod tests {
             enable_http: false,
             enable_tls: true,
             min_quality: 0.8,
+            ..Default::default()
         };
         let analyzer = HuginnAnalyzer::with_config(config);
         // This should not panic
@@ -63,6 +94,435 @@ mod tests {
         assert_eq!(profile.summary(), "No data");
     }
 
+    #[test]
+    fn test_connection_key_roundtrip() {
+        let key = ConnectionKey::new(
+            "192.168.1.10:51234".parse().unwrap(),
+            "93.184.216.34:443".parse().unwrap(),
+        );
+        assert_eq!(key.to_string(), "192.168.1.10:51234-93.184.216.34:443");
+        assert_eq!(key.to_string().parse::<ConnectionKey>().unwrap(), key);
+
+        let v6 = ConnectionKey::new(
+            "[2001:db8::1]:40000".parse().unwrap(),
+            "[2001:db8::2]:80".parse().unwrap(),
+        );
+        assert_eq!(v6.to_string().parse::<ConnectionKey>().unwrap(), v6);
+
+        assert!("192.168.1.10:51234".parse::<ConnectionKey>().is_err());
+    }
+
+    #[test]
+    fn test_connection_observation_keeps_flows_apart() {
+        let key = ConnectionKey::new(
+            "10.0.0.5:40001".parse().unwrap(),
+            "10.0.0.1:443".parse().unwrap(),
+        );
+        let mut profile = TrafficProfile::new(key.client_ip(), key.client.port());
+        profile.update_tls(TlsAnalysis {
+            ja4: "t13d1516h2_8daaf6152771_02713d6af862".to_string(),
+            ja4_raw: String::new(),
+            ja4_original: String::new(),
+            ja4_original_raw: String::new(),
+            client_application: None,
+            ja3: String::new(),
+            ja3_hash: String::new(),
+            details: profile::TlsDetails {
+                version: "13".to_string(),
+                sni: None,
+                alpn: None,
+                cipher_suites: vec![],
+                extensions: vec![],
+                signature_algorithms: vec![],
+                elliptic_curves: vec![],
+                elliptic_curve_point_formats: vec![],
+            },
+        });
+
+        let mut observation = ConnectionObservation::from_profile(key, &profile);
+        assert_eq!(observation.key, key);
+        assert_eq!(observation.summary(), "TLS");
+        assert_eq!(observation.packet_count, 1);
+
+        observation.record(&TrafficProfile::new(key.client_ip(), key.client.port()));
+        assert!(observation.tls.is_some());
+        assert_eq!(observation.packet_count, 1);
+    }
+
+    #[test]
+    fn test_fixed_clock_drives_analyzer_time() {
+        let start = chrono::DateTime::parse_from_rfc3339("2024-03-01T12:00:00Z")
+            .unwrap()
+            .with_timezone(&chrono::Utc);
+        let clock = std::sync::Arc::new(FixedClock::new(start));
+
+        let mut analyzer = HuginnAnalyzer::new();
+        analyzer.set_clock(clock.clone());
+        assert_eq!(analyzer.now(), start);
+
+        clock.advance(chrono::Duration::seconds(90));
+        assert_eq!(analyzer.now(), start + chrono::Duration::seconds(90));
+    }
+
+    #[test]
+    fn test_traffic_profile_uses_observation_time() {
+        let observed = chrono::DateTime::parse_from_rfc3339("2023-11-05T08:30:00Z")
+            .unwrap()
+            .with_timezone(&chrono::Utc);
+        let ip: IpAddr = "10.1.2.3".parse().unwrap();
+        let mut profile = TrafficProfile::new_at(ip, 443, observed);
+
+        assert_eq!(profile.timestamp, observed);
+        assert_eq!(profile.metadata.first_seen, observed);
+
+        profile.update_tls(TlsAnalysis {
+            ja4: String::new(),
+            ja4_raw: String::new(),
+            ja4_original: String::new(),
+            ja4_original_raw: String::new(),
+            client_application: None,
+            ja3: String::new(),
+            ja3_hash: String::new(),
+            details: profile::TlsDetails {
+                version: "13".to_string(),
+                sni: None,
+                alpn: None,
+                cipher_suites: vec![],
+                extensions: vec![],
+                signature_algorithms: vec![],
+                elliptic_curves: vec![],
+                elliptic_curve_point_formats: vec![],
+            },
+        });
+        assert_eq!(profile.metadata.last_updated, observed);
+    }
+
+    #[test]
+    fn test_http_response_keeps_client_fields() {
+        let details = profile::HttpDetails {
+            version: "1".to_string(),
+            header_order: String::new(),
+            headers_absent: String::new(),
+            expected_software: String::new(),
+        };
+        let request = HttpAnalysis {
+            browser: "Firefox".to_string(),
+            quality: 0.5,
+            language: Some("English".to_string()),
+            diagnosis: "none".to_string(),
+            signature: String::new(),
+            user_agent: None,
+            details: details.clone(),
+            request: Some(profile::HttpRequestData {
+                user_agent: None,
+                accept: None,
+                accept_language: None,
+                accept_encoding: None,
+                connection: None,
+                method: Some("POST".to_string()),
+                uri: Some("/login".to_string()),
+                version: "1".to_string(),
+                ja4h: None,
+                ja4h_raw: None,
+                host: None,
+                signature: String::new(),
+                quality: 0.5,
+            }),
+            response: None,
+        };
+        let response = HttpAnalysis {
+            browser: "Unknown".to_string(),
+            quality: 1.0,
+            language: None,
+            diagnosis: "dishonest".to_string(),
+            signature: String::new(),
+            user_agent: None,
+            details,
+            request: None,
+            response: Some(profile::HttpResponseData {
+                server: Some("nginx".to_string()),
+                content_type: None,
+                content_length: None,
+                set_cookie: None,
+                cache_control: None,
+                status: Some("404".to_string()),
+                version: "1".to_string(),
+                web_server: Some("Apache 2.x".to_string()),
+                diagnosis: "dishonest".to_string(),
+                signature: String::new(),
+                quality: 1.0,
+            }),
+        };
+
+        let mut profile = TrafficProfile::new("10.0.0.5".parse().unwrap(), 40001);
+        profile.update_http(request);
+        profile.update_http(response);
+
+        let http = profile.http.unwrap();
+        assert_eq!(http.browser, "Firefox");
+        assert_eq!(http.diagnosis, "none");
+        assert_eq!(http.request.unwrap().method.as_deref(), Some("POST"));
+        assert_eq!(http.response.unwrap().status.as_deref(), Some("404"));
+    }
+
+    #[test]
+    fn test_tcp_details_are_typed() {
+        use profile::{TcpOptionKind, TcpQuirk, TcpTtl, TcpWindowSize, TtlKind, WindowSizeKind};
+
+        let details = profile::TcpDetails {
+            version: "IPv4".to_string(),
+            initial_ttl: TcpTtl {
+                kind: TtlKind::Distance,
+                value: 57,
+                distance: Some(7),
+            },
+            options_length: 0,
+            mss: Some(1460),
+            window_size: TcpWindowSize {
+                kind: WindowSizeKind::Mss,
+                value: Some(44),
+            },
+            window_scale: Some(7),
+            options: vec![
+                TcpOptionKind::Mss,
+                TcpOptionKind::Sok,
+                TcpOptionKind::Ts,
+                TcpOptionKind::Nop,
+                TcpOptionKind::Ws,
+            ],
+            quirks: vec![TcpQuirk::Df, TcpQuirk::NonZeroId],
+            payload_class: "0".to_string(),
+        };
+
+        assert_eq!(details.initial_ttl.to_string(), "57+7");
+        assert_eq!(details.window_size.to_string(), "mss*44");
+        assert_eq!(details.options[2].number(), 8);
+
+        let json = serde_json::to_value(&details).unwrap();
+        assert_eq!(json["initial_ttl"]["kind"], "distance");
+        assert_eq!(json["window_size"]["value"], 44);
+        assert_eq!(json["options"][0], "mss");
+        assert_eq!(json["quirks"][1], "non_zero_id");
+    }
+
+    struct Label;
+
+    impl ProfileEnricher for Label {
+        fn name(&self) -> &str {
+            "label"
+        }
+
+        fn enrich(&self, _result: &FingerprintResult, profile: &mut TrafficProfile) -> Result<()> {
+            profile
+                .enrichment
+                .insert(self.name().to_string(), "office".into());
+            Ok(())
+        }
+    }
+
+    struct Broken;
+
+    impl ProfileEnricher for Broken {
+        fn name(&self) -> &str {
+            "broken"
+        }
+
+        fn enrich(&self, _result: &FingerprintResult, profile: &mut TrafficProfile) -> Result<()> {
+            profile
+                .enrichment
+                .insert("partial".to_string(), true.into());
+            Err(HuginnError::invalid_data("lookup failed"))
+        }
+    }
+
+    struct CountErrors(std::sync::Arc<std::sync::atomic::AtomicUsize>);
+
+    impl EventHandler for CountErrors {
+        fn handle_event(&self, event: TrafficEvent) -> Result<()> {
+            if let TrafficEvent::AnalysisError { error, .. } = event {
+                assert!(error.starts_with("broken:"));
+                self.0.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_failing_enricher_is_isolated() {
+        let errors = std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let mut analyzer = HuginnAnalyzer::new();
+        analyzer
+            .event_dispatcher_mut()
+            .add_handler(CountErrors(errors.clone()));
+        analyzer.add_enricher(Broken);
+        analyzer.add_enricher(Label);
+
+        let result = FingerprintResult {
+            syn: None,
+            syn_ack: None,
+            mtu: None,
+            uptime: None,
+            http_request: None,
+            http_response: None,
+            tls_client: None,
+        };
+        let mut profile = TrafficProfile::new("10.0.0.5".parse().unwrap(), 40001);
+        analyzer.run_enrichers(&result, &mut profile);
+
+        assert_eq!(profile.enrichment.get("label"), Some(&"office".into()));
+        assert!(!profile.enrichment.contains_key("partial"));
+        assert_eq!(errors.load(std::sync::atomic::Ordering::SeqCst), 1);
+    }
+
+    struct CountReboots(std::sync::Arc<std::sync::atomic::AtomicUsize>);
+
+    impl EventHandler for CountReboots {
+        fn handle_event(&self, event: TrafficEvent) -> Result<()> {
+            if let TrafficEvent::HostRebooted { .. } = event {
+                self.0.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_uptime_drop_reports_reboot() {
+        let reboots = std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let mut analyzer = HuginnAnalyzer::new();
+        analyzer
+            .event_dispatcher_mut()
+            .add_handler(CountReboots(reboots.clone()));
+
+        let start = HostSignals::at(0).observed_at;
+        let mut profile = TrafficProfile::new_at("10.0.0.5".parse().unwrap(), 40001, start);
+        for (uptime_mins, at_mins) in [(3000, 0), (3010, 10), (2, 30)] {
+            let signals = HostSignals::at(at_mins).with_uptime(uptime_mins);
+            analyzer.track_uptime(&mut profile, &signals);
+            analyzer.track_hosts(&mut profile, signals);
+        }
+
+        assert_eq!(reboots.load(std::sync::atomic::Ordering::SeqCst), 1);
+        assert_eq!(
+            profile.last_reboot_estimate,
+            Some(start + chrono::Duration::minutes(28))
+        );
+        // The clock from before the reboot does not count as another host
+        assert_eq!(profile.nat.uptime_clocks, 1);
+        assert!(!profile.nat.behind_nat);
+    }
+
+    struct CountInconsistencies(std::sync::Arc<std::sync::atomic::AtomicUsize>);
+
+    impl EventHandler for CountInconsistencies {
+        fn handle_event(&self, event: TrafficEvent) -> Result<()> {
+            if let TrafficEvent::InconsistencyDetected { .. } = event {
+                self.0.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_captured_request_reports_inconsistency() {
+        let inconsistencies = std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let mut analyzer = HuginnAnalyzer::new();
+        analyzer
+            .event_dispatcher_mut()
+            .add_handler(CountInconsistencies(inconsistencies.clone()));
+
+        // A Firefox User-Agent on Chrome's header layout
+        let profile = analyzer.analyze_request(
+            b"GET / HTTP/1.1\r\n\
+              Host: example.com\r\n\
+              Connection: keep-alive\r\n\
+              User-Agent: Mozilla/5.0 (Windows NT 10.0; Win64; x64; rv:128.0) Gecko/20100101 Firefox/128.0\r\n\
+              Accept: */*\r\n\
+              Accept-Encoding: gzip,deflate,sdch\r\n\
+              Accept-Language: en-US,en;q=0.8\r\n\
+              Accept-Charset: utf-8;q=0.7,*;q=0.3\r\n\r\n",
+        );
+
+        let kinds: Vec<_> = profile.consistency.iter().map(|f| f.kind).collect();
+        assert_eq!(kinds, [FindingKind::HttpSignatureMismatch]);
+        assert_eq!(inconsistencies.load(std::sync::atomic::Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_single_host_flapping_hops_is_reported() {
+        let analyzer = HuginnAnalyzer::new();
+        let start = HostSignals::at(0).observed_at;
+
+        let mut profile = TrafficProfile::new_at("10.0.0.5".parse().unwrap(), 40001, start);
+        for (at_mins, distance) in [(0, 5), (1, 9), (2, 5), (3, 9)] {
+            let signals = HostSignals {
+                syn_signature: Some("4:64+5:0:1460".to_string()),
+                ttl_distance: Some(distance),
+                ..HostSignals::at(at_mins)
+            };
+            analyzer.track_hops(&mut profile, &signals);
+            analyzer.track_hosts(&mut profile, signals);
+        }
+
+        assert_eq!(profile.hops.changes, 3);
+        assert_eq!(
+            profile.hops.anomalies.last().map(|anomaly| anomaly.kind),
+            Some(HopAnomalyKind::Flapping)
+        );
+    }
+
+    #[test]
+    fn test_hosts_behind_nat_do_not_flap_hops() {
+        let analyzer = HuginnAnalyzer::new();
+        let start = HostSignals::at(0).observed_at;
+
+        let mut profile = TrafficProfile::new_at("10.0.0.5".parse().unwrap(), 40001, start);
+        for (at_mins, signature, distance) in [
+            (0, "4:64+5:0:1460", 5),
+            (1, "4:128+9:0:1460", 9),
+            (2, "4:64+5:0:1460", 5),
+            (3, "4:128+9:0:1460", 9),
+        ] {
+            let signals = HostSignals {
+                syn_signature: Some(signature.to_string()),
+                ttl_distance: Some(distance),
+                ..HostSignals::at(at_mins)
+            };
+            analyzer.track_hops(&mut profile, &signals);
+            analyzer.track_hosts(&mut profile, signals);
+        }
+
+        assert!(profile.nat.behind_nat);
+        assert_eq!(profile.hops.changes, 1);
+        assert!(profile
+            .hops
+            .anomalies
+            .iter()
+            .all(|anomaly| anomaly.kind != HopAnomalyKind::Flapping));
+    }
+
+    #[test]
+    fn test_unbounded_nat_window_keeps_every_observation() {
+        let start = HostSignals::at(0).observed_at;
+
+        for nat_window_secs in [i64::MAX as u64, u64::MAX] {
+            let analyzer = HuginnAnalyzer::with_config(AnalyzerConfig {
+                nat_window_secs,
+                ..Default::default()
+            });
+            let mut profile = TrafficProfile::new_at("10.0.0.5".parse().unwrap(), 40001, start);
+            for (signature, at_days) in [("4:64+0:0:1460", 0), ("4:128+0:0:1460", 365)] {
+                analyzer.track_hosts(
+                    &mut profile,
+                    HostSignals {
+                        syn_signature: Some(signature.to_string()),
+                        ..HostSignals::at(at_days * 24 * 60)
+                    },
+                );
+            }
+            assert_eq!(profile.nat.syn_signatures, 2);
+        }
+    }
+
     #[test]
     fn test_analyzer_config_default() {
         let config = AnalyzerConfig::default();
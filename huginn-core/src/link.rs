@@ -0,0 +1,209 @@
+use crate::error::{HuginnError, Result};
+use serde::{Deserialize, Serialize};
+use std::ops::RangeInclusive;
+use std::path::Path;
+use std::str::FromStr;
+
+/// IPv4 plus TCP header bytes between the MTU and the MSS
+const IPV4_TCP_HEADERS: u16 = 40;
+
+/// IPv6 plus TCP header bytes between the MTU and the MSS
+const IPV6_TCP_HEADERS: u16 = 60;
+
+/// Table used when no link table file is configured
+const BUILTIN_TABLE: &str = "\
+1500        no   Ethernet
+1492        no   PPPoE
+1480        yes  IPIP
+1476        yes  GRE
+1450        yes  VXLAN
+1440        yes  WireGuard
+1420        yes  WireGuard
+1409        yes  OpenVPN
+1380-1446   yes  IPsec
+1300-1379   yes  OpenVPN
+1280        yes  Generic tunnel
+";
+
+/// Link type a host's MTU points to
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LinkClassification {
+    /// Link label from the table (e.g. "PPPoE", "WireGuard")
+    pub link_type: String,
+    /// Whether the link is a VPN or tunnel
+    pub likely_vpn: bool,
+    /// MTU the label was looked up with
+    pub mtu: u16,
+}
+
+#[derive(Debug, Clone)]
+struct LinkEntry {
+    mtu: RangeInclusive<u16>,
+    vpn: bool,
+    label: String,
+}
+
+/// MTU → link type table
+///
+/// One entry per line: an MTU or `min-max` range, `yes`/`no` for whether the
+/// link is a VPN or tunnel, and the label. The first matching line wins;
+/// blank lines and lines starting with `#` are ignored:
+///
+/// ```text
+/// # mtu       vpn  label
+/// 1492        no   PPPoE
+/// 1380-1446   yes  IPsec
+/// ```
+///
+/// The default table covers Ethernet, PPPoE and common tunnels. MTUs are
+/// ambiguous (OpenVPN and IPsec overlap), so the result is a best guess.
+#[derive(Debug, Clone)]
+pub struct LinkTable {
+    entries: Vec<LinkEntry>,
+}
+
+impl LinkTable {
+    /// Load a table from a file
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let content = std::fs::read_to_string(path)?;
+        content.parse().map_err(|e: HuginnError| {
+            HuginnError::configuration(format!("Link table {}: {}", path.display(), e))
+        })
+    }
+
+    /// Classify a path MTU
+    pub fn classify(&self, mtu: u16) -> Option<LinkClassification> {
+        self.entries
+            .iter()
+            .find(|entry| entry.mtu.contains(&mtu))
+            .map(|entry| LinkClassification {
+                link_type: entry.label.clone(),
+                likely_vpn: entry.vpn,
+                mtu,
+            })
+    }
+
+    /// Classify the MTU implied by a TCP MSS (`ipv6` selects the header size)
+    pub fn classify_mss(&self, mss: u16, ipv6: bool) -> Option<LinkClassification> {
+        let headers = if ipv6 {
+            IPV6_TCP_HEADERS
+        } else {
+            IPV4_TCP_HEADERS
+        };
+        self.classify(mss.checked_add(headers)?)
+    }
+
+    /// Number of entries
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Whether the table has no entries
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+impl Default for LinkTable {
+    fn default() -> Self {
+        BUILTIN_TABLE.parse().expect("built-in link table is valid")
+    }
+}
+
+impl FromStr for LinkTable {
+    type Err = HuginnError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut entries = Vec::new();
+
+        for (index, line) in s.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let invalid =
+                |what: &str| HuginnError::invalid_data(format!("line {}: {}", index + 1, what));
+
+            let mut fields = line.split_whitespace();
+            let mtu = fields.next().unwrap_or_default();
+            let vpn = fields.next().ok_or_else(|| invalid("missing VPN flag"))?;
+            let label = fields.collect::<Vec<_>>().join(" ");
+            if label.is_empty() {
+                return Err(invalid("missing label"));
+            }
+
+            let parse_mtu = |value: &str| {
+                value
+                    .parse::<u16>()
+                    .map_err(|_| invalid(&format!("invalid MTU: {}", value)))
+            };
+            let mtu = match mtu.split_once('-') {
+                Some((min, max)) => parse_mtu(min)?..=parse_mtu(max)?,
+                None => {
+                    let mtu = parse_mtu(mtu)?;
+                    mtu..=mtu
+                }
+            };
+            if mtu.is_empty() {
+                return Err(invalid("MTU range is empty"));
+            }
+            let vpn = match vpn {
+                "yes" => true,
+                "no" => false,
+                other => return Err(invalid(&format!("VPN flag must be yes or no: {}", other))),
+            };
+
+            entries.push(LinkEntry { mtu, vpn, label });
+        }
+
+        Ok(Self { entries })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builtin_table_names_common_links() {
+        let table = LinkTable::default();
+
+        let ethernet = table.classify(1500).unwrap();
+        assert_eq!(ethernet.link_type, "Ethernet");
+        assert!(!ethernet.likely_vpn);
+
+        assert_eq!(table.classify(1492).unwrap().link_type, "PPPoE");
+        assert_eq!(table.classify(1420).unwrap().link_type, "WireGuard");
+        assert_eq!(table.classify(1400).unwrap().link_type, "IPsec");
+        assert!(table.classify(1400).unwrap().likely_vpn);
+        assert_eq!(table.classify(9001), None);
+
+        // MSS 1380 over IPv6 is a 1440 byte MTU
+        assert_eq!(
+            table.classify_mss(1380, true).unwrap().link_type,
+            "WireGuard"
+        );
+        assert_eq!(
+            table.classify_mss(1460, false).unwrap().link_type,
+            "Ethernet"
+        );
+    }
+
+    #[test]
+    fn test_custom_table_first_match_wins() {
+        let table: LinkTable = "# office links\n1400 no Carrier MPLS\n1380-1446 yes IPsec\n"
+            .parse()
+            .unwrap();
+
+        assert_eq!(table.len(), 2);
+        let mpls = table.classify(1400).unwrap();
+        assert_eq!(mpls.link_type, "Carrier MPLS");
+        assert!(!mpls.likely_vpn);
+        assert_eq!(table.classify(1401).unwrap().link_type, "IPsec");
+
+        assert!("1400 maybe Thing".parse::<LinkTable>().is_err());
+        assert!("abc no Thing".parse::<LinkTable>().is_err());
+        assert!("1400 no".parse::<LinkTable>().is_err());
+    }
+}
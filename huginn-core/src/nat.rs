@@ -0,0 +1,332 @@
+use crate::profile::{TrafficProfile, TtlKind};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+/// Boot times closer than this are assumed to be the same clock
+pub(crate) const CLOCK_TOLERANCE_SECS: i64 = 600;
+
+/// Relative difference above which two timestamp clock frequencies differ
+const CLOCK_FREQUENCY_TOLERANCE: f64 = 0.2;
+
+/// Distinct JA4s a single host commonly shows (browser, system services, tools)
+const JA4S_PER_HOST: usize = 4;
+
+/// Upper bound on remembered observations per profile
+const MAX_HISTORY: usize = 512;
+
+/// Host-identifying values taken from a single fingerprint result
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HostSignals {
+    /// p0f signature of the client SYN
+    pub syn_signature: Option<String>,
+    /// Hop distance of the client SYN
+    pub ttl_distance: Option<u8>,
+    /// Boot time derived from the TCP timestamp uptime
+    pub boot_time: Option<DateTime<Utc>>,
+    /// TCP timestamp clock frequency in Hz
+    pub clock_hz: Option<f64>,
+    /// Days after which the TCP timestamp clock wraps around
+    pub clock_modulo_days: Option<u32>,
+    /// JA4 of the TLS ClientHello
+    pub ja4: Option<String>,
+    /// When the result was observed
+    pub observed_at: DateTime<Utc>,
+}
+
+impl HostSignals {
+    /// Collect the client-side signals of a profile built from one fingerprint result
+    pub fn from_profile(profile: &TrafficProfile) -> Self {
+        let syn = profile.raw_data.syn.as_ref();
+        // Uptime may be measured on either side of the connection
+        let uptime = profile
+            .raw_data
+            .uptime
+            .as_ref()
+            .filter(|uptime| uptime.source.ip == profile.ip.to_string());
+
+        Self {
+            syn_signature: syn.map(|syn| syn.signature.clone()),
+            ttl_distance: syn
+                .filter(|syn| syn.details.initial_ttl.kind == TtlKind::Distance)
+                .and_then(|syn| syn.details.initial_ttl.distance),
+            boot_time: uptime.and_then(|uptime| {
+                let uptime_secs = i64::try_from(uptime.uptime_seconds).ok()?;
+                Some(uptime.timestamp - Duration::seconds(uptime_secs))
+            }),
+            clock_hz: uptime
+                .map(|uptime| uptime.frequency_hz)
+                .filter(|hz| *hz > 0.0),
+            clock_modulo_days: uptime
+                .map(|uptime| uptime.modulo_days)
+                .filter(|days| *days > 0),
+            ja4: profile
+                .raw_data
+                .tls_client
+                .as_ref()
+                .map(|tls| tls.ja4.clone())
+                .filter(|ja4| !ja4.is_empty()),
+            observed_at: profile.timestamp,
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.syn_signature.is_none()
+            && self.ttl_distance.is_none()
+            && self.boot_time.is_none()
+            && self.ja4.is_none()
+    }
+}
+
+/// Estimate of how many hosts share a profile's IP address
+///
+/// Counts distinct SYN signatures, TTL distances, uptime clocks and JA4s seen
+/// within the analyzer's NAT window. One host has one TCP stack and one clock,
+/// so more than one of either means several hosts. A route change moves a host
+/// to a new hop distance, so distances only count once they keep alternating;
+/// JA4s only count once they exceed what a single host usually shows.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct NatAnalysis {
+    /// Estimated number of distinct hosts behind this IP
+    pub estimated_hosts: usize,
+    /// Whether more than one host appears to use this IP
+    pub behind_nat: bool,
+    /// Distinct client SYN signatures in the window
+    pub syn_signatures: usize,
+    /// Distinct TTL hop distances in the window
+    pub ttl_distances: usize,
+    /// Distinct uptime clocks (boot time and frequency) in the window
+    pub uptime_clocks: usize,
+    /// Distinct TLS JA4 fingerprints in the window
+    pub ja4_fingerprints: usize,
+    /// Observations inside the window
+    #[serde(skip)]
+    pub(crate) history: Vec<HostSignals>,
+}
+
+impl NatAnalysis {
+    /// Record the signals of a new result and recompute the estimate
+    pub fn observe(&mut self, signals: HostSignals, window: Duration) {
+        if !signals.is_empty() {
+            self.history.push(signals);
+        }
+
+        // Replayed captures may arrive out of order; the window ends at the newest observation
+        let newest = self.history.iter().map(|s| s.observed_at).max();
+        if let Some(start) = newest.and_then(|newest| newest.checked_sub_signed(window)) {
+            self.history.retain(|s| s.observed_at >= start);
+        }
+        if self.history.len() > MAX_HISTORY {
+            let excess = self.history.len() - MAX_HISTORY;
+            self.history.drain(..excess);
+        }
+
+        self.recompute();
+    }
+
+    /// Forget boot times older than `boot_time` once their host has rebooted
+    ///
+    /// Otherwise the clock from before the reboot would count as a second host.
+    pub fn forget_clocks_before(&mut self, boot_time: DateTime<Utc>) {
+        for signals in &mut self.history {
+            let stale = signals
+                .boot_time
+                .is_some_and(|known| (boot_time - known).num_seconds() > CLOCK_TOLERANCE_SECS);
+            if stale {
+                signals.boot_time = None;
+                signals.clock_hz = None;
+            }
+        }
+        self.recompute();
+    }
+
+    fn recompute(&mut self) {
+        let syn_signatures: HashSet<&str> = self
+            .history
+            .iter()
+            .filter_map(|s| s.syn_signature.as_deref())
+            .collect();
+        let ttl_distances: HashSet<u8> =
+            self.history.iter().filter_map(|s| s.ttl_distance).collect();
+        let ja4s: HashSet<&str> = self
+            .history
+            .iter()
+            .filter_map(|s| s.ja4.as_deref())
+            .collect();
+
+        self.syn_signatures = syn_signatures.len();
+        self.ttl_distances = ttl_distances.len();
+        self.uptime_clocks = self.count_clocks();
+        self.ja4_fingerprints = ja4s.len();
+
+        self.estimated_hosts = if self.history.is_empty() {
+            0
+        } else {
+            [
+                1,
+                self.syn_signatures,
+                self.alternating_distances(),
+                self.uptime_clocks,
+                self.ja4_fingerprints.div_ceil(JA4S_PER_HOST),
+            ]
+            .into_iter()
+            .max()
+            .unwrap_or(1)
+        };
+        self.behind_nat = self.estimated_hosts > 1;
+    }
+
+    /// TTL distances each seen in more than one run of observations, in time order
+    ///
+    /// A route change gives each distance a single run; hosts at different
+    /// distances behind one IP keep coming back.
+    fn alternating_distances(&self) -> usize {
+        let mut distances: Vec<(DateTime<Utc>, u8)> = self
+            .history
+            .iter()
+            .filter_map(|s| Some((s.observed_at, s.ttl_distance?)))
+            .collect();
+        distances.sort_by_key(|(observed_at, _)| *observed_at);
+
+        let mut runs: HashMap<u8, usize> = HashMap::new();
+        let mut previous = None;
+        for (_, distance) in distances {
+            if previous != Some(distance) {
+                *runs.entry(distance).or_default() += 1;
+                previous = Some(distance);
+            }
+        }
+        runs.values().filter(|&&count| count > 1).count()
+    }
+
+    /// Group boot times (and frequencies, when known) into distinct clocks
+    fn count_clocks(&self) -> usize {
+        let mut clocks: Vec<(DateTime<Utc>, Option<f64>)> = Vec::new();
+
+        for signals in &self.history {
+            let Some(boot_time) = signals.boot_time else {
+                continue;
+            };
+            let same_clock = |(known_boot, known_hz): &(DateTime<Utc>, Option<f64>)| {
+                let boot_matches =
+                    (boot_time - *known_boot).num_seconds().abs() <= CLOCK_TOLERANCE_SECS;
+                let hz_matches = match (signals.clock_hz, known_hz) {
+                    (Some(a), Some(b)) => (a - b).abs() / a.max(*b) <= CLOCK_FREQUENCY_TOLERANCE,
+                    _ => true,
+                };
+                boot_matches && hz_matches
+            };
+            if !clocks.iter().any(same_clock) {
+                clocks.push((boot_time, signals.clock_hz));
+            }
+        }
+
+        clocks.len()
+    }
+}
+
+#[cfg(test)]
+impl HostSignals {
+    /// Signals carrying nothing but a time `at_mins` after 2024-03-01T12:00:00Z
+    pub(crate) fn at(at_mins: i64) -> Self {
+        let start = DateTime::parse_from_rfc3339("2024-03-01T12:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        Self {
+            syn_signature: None,
+            ttl_distance: None,
+            boot_time: None,
+            clock_hz: None,
+            clock_modulo_days: None,
+            ja4: None,
+            observed_at: start + Duration::minutes(at_mins),
+        }
+    }
+
+    /// Add a 1000 Hz uptime clock that reads `uptime_mins` when observed
+    pub(crate) fn with_uptime(mut self, uptime_mins: i64) -> Self {
+        self.boot_time = Some(self.observed_at - Duration::minutes(uptime_mins));
+        self.clock_hz = Some(1000.0);
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn signals(
+        syn: Option<&str>,
+        ttl_distance: Option<u8>,
+        boot_offset_mins: Option<i64>,
+        at_mins: i64,
+    ) -> HostSignals {
+        let signals = HostSignals {
+            syn_signature: syn.map(str::to_string),
+            ttl_distance,
+            ..HostSignals::at(at_mins)
+        };
+        match boot_offset_mins {
+            Some(mins) => signals.with_uptime(mins + at_mins),
+            None => signals,
+        }
+    }
+
+    #[test]
+    fn test_single_host_is_not_nat() {
+        let mut nat = NatAnalysis::default();
+        for minute in 0..5 {
+            nat.observe(
+                signals(
+                    Some("4:64+0:0:1460:mss*44,7:mss,sok,ts,nop,ws:df,id+:0"),
+                    Some(0),
+                    Some(3000),
+                    minute,
+                ),
+                Duration::hours(1),
+            );
+        }
+
+        assert_eq!(nat.estimated_hosts, 1);
+        assert!(!nat.behind_nat);
+    }
+
+    #[test]
+    fn test_distinct_stacks_and_clocks_reveal_nat() {
+        let mut nat = NatAnalysis::default();
+        let window = Duration::hours(1);
+        nat.observe(signals(Some("linux"), Some(1), Some(3000), 0), window);
+        nat.observe(signals(Some("windows"), Some(1), Some(90), 1), window);
+        nat.observe(signals(Some("linux"), Some(1), Some(20000), 2), window);
+
+        assert_eq!(nat.syn_signatures, 2);
+        assert_eq!(nat.uptime_clocks, 3);
+        assert_eq!(nat.estimated_hosts, 3);
+        assert!(nat.behind_nat);
+
+        // Once the window has passed only the latest host is left
+        nat.observe(signals(Some("linux"), Some(1), Some(3000), 120), window);
+        assert_eq!(nat.estimated_hosts, 1);
+        assert!(!nat.behind_nat);
+    }
+
+    #[test]
+    fn test_route_change_is_not_nat() {
+        let mut nat = NatAnalysis::default();
+        let window = Duration::hours(1);
+        for (minute, distance) in [(0, 5), (1, 5), (2, 9), (3, 9), (4, 9)] {
+            nat.observe(signals(None, Some(distance), None, minute), window);
+        }
+
+        assert_eq!(nat.ttl_distances, 2);
+        assert_eq!(nat.estimated_hosts, 1);
+        assert!(!nat.behind_nat);
+
+        // Two hosts at different distances keep alternating
+        for (minute, distance) in [(5, 5), (6, 9), (7, 5)] {
+            nat.observe(signals(None, Some(distance), None, minute), window);
+        }
+        assert_eq!(nat.estimated_hosts, 2);
+        assert!(nat.behind_nat);
+    }
+}
@@ -1,5 +1,14 @@
+use crate::consistency::ConsistencyFinding;
+use crate::hops::HopHistory;
+use crate::ja4db::ClientApplication;
+use crate::nat::{HostSignals, NatAnalysis};
+use crate::reboot::{UptimeHistory, UptimeSample};
+use crate::risk::RiskScore;
+use crate::user_agent::UserAgent;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, VecDeque};
+use std::fmt;
 use std::net::IpAddr;
 
 /// Complete traffic profile for a network endpoint
@@ -23,12 +32,49 @@ pub struct TrafficProfile {
     pub http: Option<HttpAnalysis>,
     /// TLS analysis results (legacy - for backwards compatibility)
     pub tls: Option<TlsAnalysis>,
+    /// Cross-layer inconsistencies (e.g. User-Agent vs TCP/TLS fingerprints)
+    #[serde(default)]
+    pub consistency: Vec<ConsistencyFinding>,
+    /// Estimate of how many hosts share this IP
+    #[serde(default)]
+    pub nat: NatAnalysis,
+    /// Link type inferred from the MTU/MSS (e.g. "PPPoE", "WireGuard")
+    #[serde(default)]
+    pub link_type: Option<String>,
+    /// Whether the link type is a VPN or tunnel
+    #[serde(default)]
+    pub likely_vpn: bool,
+    /// Client hop distance over time, with sudden changes flagged
+    #[serde(default)]
+    pub hops: HopHistory,
+    /// Estimated boot time of the most recent reboot seen in the uptime
+    #[serde(default)]
+    pub last_reboot_estimate: Option<DateTime<Utc>>,
+    /// Uptime samples used to detect reboots
+    #[serde(default)]
+    pub uptime_history: UptimeHistory,
+    /// Bot/automation risk score with per-signal breakdown
+    #[serde(default)]
+    pub risk: RiskScore,
+    /// Values added by custom enrichers, keyed by enricher name
+    #[serde(default)]
+    pub enrichment: BTreeMap<String, serde_json::Value>,
     /// Additional metadata
     pub metadata: ProfileMetadata,
 }
 
+/// Observations behind a profile's NAT and reboot detection
+///
+/// They are left out of the profile's own JSON; stores that serialize profiles
+/// keep them alongside it so detection carries on across updates.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TrackingHistory {
+    nat: Vec<HostSignals>,
+    uptime: VecDeque<UptimeSample>,
+}
+
 /// Raw fingerprint data separated by source type
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct RawFingerprintData {
     /// SYN packet (from client)
     pub syn: Option<SynPacketData>,
@@ -57,6 +103,9 @@ pub struct SynPacketData {
     pub os_detected: Option<OsDetection>,
     /// TCP signature
     pub signature: String,
+    /// JA4T fingerprint (`None` when the window size can't be recovered)
+    #[serde(default)]
+    pub ja4t: Option<String>,
     /// Raw packet details
     pub details: TcpDetails,
     /// When this was detected
@@ -74,6 +123,9 @@ pub struct SynAckPacketData {
     pub os_detected: Option<OsDetection>,
     /// TCP signature
     pub signature: String,
+    /// JA4TS fingerprint (`None` when the window size can't be recovered)
+    #[serde(default)]
+    pub ja4ts: Option<String>,
     /// Raw packet details
     pub details: TcpDetails,
     /// When this was detected
@@ -85,6 +137,9 @@ pub struct SynAckPacketData {
 pub struct MtuData {
     /// Source IP and port
     pub source: NetworkEndpoint,
+    /// Link type inferred from the MTU, if the signature database matched one
+    #[serde(default)]
+    pub link: Option<String>,
     /// Detected MTU value
     pub mtu_value: u16,
     /// When this was detected
@@ -98,6 +153,20 @@ pub struct UptimeData {
     pub source: NetworkEndpoint,
     /// Detected uptime in seconds
     pub uptime_seconds: u64,
+    /// Uptime as reported by huginn-net (days/hours/minutes)
+    #[serde(default)]
+    pub days: u32,
+    #[serde(default)]
+    pub hours: u32,
+    #[serde(default)]
+    pub minutes: u32,
+    /// Days after which the TCP timestamp clock wraps around; uptime is only
+    /// known modulo this period
+    #[serde(default)]
+    pub modulo_days: u32,
+    /// Estimated TCP timestamp clock frequency in Hz
+    #[serde(default)]
+    pub frequency_hz: f64,
     /// When this was detected
     pub timestamp: DateTime<Utc>,
 }
@@ -111,6 +180,12 @@ pub struct TlsClientData {
     pub ja4: String,
     /// JA4 raw fingerprint
     pub ja4_raw: String,
+    /// Legacy JA3 string (GREASE removed)
+    #[serde(default)]
+    pub ja3: String,
+    /// MD5 of the JA3 string
+    #[serde(default)]
+    pub ja3_hash: String,
     /// TLS details
     pub details: TlsDetails,
     /// When this was detected
@@ -135,21 +210,6 @@ pub struct OsDetection {
     pub distance: u8,
 }
 
-impl Default for RawFingerprintData {
-    fn default() -> Self {
-        Self {
-            syn: None,
-            syn_ack: None,
-            mtu: None,
-            uptime: None,
-            http_request: None,
-            http_response: None,
-            tls_client: None,
-            source_ip: None,
-        }
-    }
-}
-
 /// TCP connection analysis
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TcpAnalysis {
@@ -178,6 +238,9 @@ pub struct HttpAnalysis {
     pub diagnosis: String,
     /// HTTP signature
     pub signature: String,
+    /// Parsed User-Agent of the request, if one was sent
+    #[serde(default)]
+    pub user_agent: Option<UserAgent>,
     /// Detailed HTTP characteristics
     pub details: HttpDetails,
     /// Request-specific data (from client)
@@ -186,6 +249,37 @@ pub struct HttpAnalysis {
     pub response: Option<HttpResponseData>,
 }
 
+impl HttpAnalysis {
+    /// Merge the request or response half of another analysis into this one
+    pub fn merge(&mut self, http: HttpAnalysis) {
+        // Top-level fields describe the client: a response-only analysis only
+        // fills them until a request has been seen
+        let had_request = self.request.is_some();
+        let replace = if http.request.is_some() {
+            !had_request || http.quality > self.quality
+        } else {
+            !had_request
+        };
+
+        // Merge request and response data
+        if http.request.is_some() {
+            self.request = http.request;
+            self.user_agent = http.user_agent;
+        }
+        if http.response.is_some() {
+            self.response = http.response;
+        }
+        if replace {
+            self.browser = http.browser;
+            self.quality = http.quality;
+            self.language = http.language;
+            self.diagnosis = http.diagnosis;
+            self.signature = http.signature;
+            self.details = http.details;
+        }
+    }
+}
+
 /// HTTP request data (from client)
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct HttpRequestData {
@@ -201,6 +295,18 @@ pub struct HttpRequestData {
     pub connection: Option<String>,
     /// Request method
     pub method: Option<String>,
+    /// Request URI
+    #[serde(default)]
+    pub uri: Option<String>,
+    /// HTTP version of the request
+    #[serde(default)]
+    pub version: String,
+    /// JA4H fingerprint (`None` when the request method is unknown)
+    #[serde(default)]
+    pub ja4h: Option<String>,
+    /// JA4H raw fingerprint
+    #[serde(default)]
+    pub ja4h_raw: Option<String>,
     /// Host header
     pub host: Option<String>,
     /// Request signature
@@ -224,6 +330,15 @@ pub struct HttpResponseData {
     pub cache_control: Option<String>,
     /// Response status
     pub status: Option<String>,
+    /// HTTP version of the response
+    #[serde(default)]
+    pub version: String,
+    /// Matched web server, if the signature database recognised one
+    #[serde(default)]
+    pub web_server: Option<String>,
+    /// Response diagnosis (e.g. dishonest Server header)
+    #[serde(default)]
+    pub diagnosis: String,
     /// Response signature
     pub signature: String,
     /// Quality score for response analysis
@@ -241,24 +356,217 @@ pub struct TlsAnalysis {
     pub ja4_original: String,
     /// JA4 original raw fingerprint
     pub ja4_original_raw: String,
+    /// Application the JA4 was labelled as, if the JA4 database knows it
+    #[serde(default)]
+    pub client_application: Option<ClientApplication>,
+    /// Legacy JA3 string (GREASE removed)
+    #[serde(default)]
+    pub ja3: String,
+    /// MD5 of the JA3 string
+    #[serde(default)]
+    pub ja3_hash: String,
     /// Detailed TLS characteristics
     pub details: TlsDetails,
 }
 
 /// Detailed TCP characteristics
+///
+/// Typed view of the p0f signature; the signature string itself is kept in the
+/// `signature` field of the owning analysis or packet data.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TcpDetails {
     pub version: String,
-    pub initial_ttl: String,
+    pub initial_ttl: TcpTtl,
     pub options_length: u8,
     pub mss: Option<u16>,
-    pub window_size: String,
+    pub window_size: TcpWindowSize,
     pub window_scale: Option<u8>,
-    pub options_layout: String,
-    pub quirks: String,
+    /// TCP options in the order they were sent
+    pub options: Vec<TcpOptionKind>,
+    pub quirks: Vec<TcpQuirk>,
     pub payload_class: String,
 }
 
+/// How the initial TTL was determined
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TtlKind {
+    /// TTL taken as is (no hop distance known)
+    Value,
+    /// Observed TTL with a computed hop distance to a known initial TTL
+    Distance,
+    /// Initial TTL guessed from the observed TTL
+    Guess,
+    /// TTL that does not fit any known initial value
+    Bad,
+}
+
+/// Initial TTL of a packet
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TcpTtl {
+    pub kind: TtlKind,
+    /// TTL value (the observed TTL for `Distance`)
+    pub value: u8,
+    /// Hop distance, only known for `Distance`
+    pub distance: Option<u8>,
+}
+
+impl fmt::Display for TcpTtl {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.kind {
+            TtlKind::Value => write!(f, "{}", self.value),
+            TtlKind::Distance => write!(f, "{}+{}", self.value, self.distance.unwrap_or(0)),
+            TtlKind::Guess => write!(f, "{}+?", self.value),
+            TtlKind::Bad => write!(f, "{}-", self.value),
+        }
+    }
+}
+
+/// How the TCP window size is expressed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WindowSizeKind {
+    /// Literal window size
+    Value,
+    /// Multiple of the MSS
+    Mss,
+    /// Multiple of the MTU
+    Mtu,
+    /// Any window divisible by the value
+    Mod,
+    /// Any window
+    Any,
+}
+
+/// TCP window size of a packet
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TcpWindowSize {
+    pub kind: WindowSizeKind,
+    /// Window, multiplier or modulus depending on `kind`; `None` for `Any`
+    pub value: Option<u16>,
+}
+
+impl fmt::Display for TcpWindowSize {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let value = self.value.unwrap_or(0);
+        match self.kind {
+            WindowSizeKind::Value => write!(f, "{}", value),
+            WindowSizeKind::Mss => write!(f, "mss*{}", value),
+            WindowSizeKind::Mtu => write!(f, "mtu*{}", value),
+            WindowSizeKind::Mod => write!(f, "%{}", value),
+            WindowSizeKind::Any => f.write_str("*"),
+        }
+    }
+}
+
+/// TCP option present in the SYN/SYN-ACK
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TcpOptionKind {
+    /// End of options list, with the number of padding bytes that followed
+    Eol(u8),
+    Nop,
+    Mss,
+    /// Window scale
+    Ws,
+    /// Selective ACK permitted
+    Sok,
+    Sack,
+    /// Timestamps
+    Ts,
+    /// Any other option kind
+    Unknown(u8),
+}
+
+impl TcpOptionKind {
+    /// IANA option kind number
+    pub fn number(&self) -> u8 {
+        match self {
+            TcpOptionKind::Eol(_) => 0,
+            TcpOptionKind::Nop => 1,
+            TcpOptionKind::Mss => 2,
+            TcpOptionKind::Ws => 3,
+            TcpOptionKind::Sok => 4,
+            TcpOptionKind::Sack => 5,
+            TcpOptionKind::Ts => 8,
+            TcpOptionKind::Unknown(kind) => *kind,
+        }
+    }
+}
+
+impl fmt::Display for TcpOptionKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TcpOptionKind::Eol(padding) => write!(f, "eol+{}", padding),
+            TcpOptionKind::Nop => f.write_str("nop"),
+            TcpOptionKind::Mss => f.write_str("mss"),
+            TcpOptionKind::Ws => f.write_str("ws"),
+            TcpOptionKind::Sok => f.write_str("sok"),
+            TcpOptionKind::Sack => f.write_str("sack"),
+            TcpOptionKind::Ts => f.write_str("ts"),
+            TcpOptionKind::Unknown(kind) => write!(f, "?{}", kind),
+        }
+    }
+}
+
+/// Unusual IP/TCP header properties (p0f quirks)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TcpQuirk {
+    /// Don't fragment set
+    Df,
+    /// DF set with a non-zero IP ID
+    NonZeroId,
+    /// DF not set with a zero IP ID
+    ZeroId,
+    /// Explicit congestion notification
+    Ecn,
+    /// "Must be zero" IP field is set
+    MustBeZero,
+    /// Non-zero IPv6 flow label
+    FlowId,
+    SeqNumZero,
+    AckNumNonZero,
+    AckNumZero,
+    /// Non-zero urgent pointer without URG
+    NonZeroUrg,
+    /// URG flag set
+    Urg,
+    /// PUSH flag set
+    Push,
+    OwnTimestampZero,
+    PeerTimestampNonZero,
+    /// Non-zero data after the options
+    TrailingNonZero,
+    ExcessiveWindowScaling,
+    /// Malformed options
+    OptBad,
+}
+
+impl fmt::Display for TcpQuirk {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            TcpQuirk::Df => "df",
+            TcpQuirk::NonZeroId => "id+",
+            TcpQuirk::ZeroId => "id-",
+            TcpQuirk::Ecn => "ecn",
+            TcpQuirk::MustBeZero => "0+",
+            TcpQuirk::FlowId => "flow",
+            TcpQuirk::SeqNumZero => "seq-",
+            TcpQuirk::AckNumNonZero => "ack+",
+            TcpQuirk::AckNumZero => "ack-",
+            TcpQuirk::NonZeroUrg => "uptr+",
+            TcpQuirk::Urg => "urgf+",
+            TcpQuirk::Push => "pushf+",
+            TcpQuirk::OwnTimestampZero => "ts1-",
+            TcpQuirk::PeerTimestampNonZero => "ts2+",
+            TcpQuirk::TrailingNonZero => "opt+",
+            TcpQuirk::ExcessiveWindowScaling => "exws",
+            TcpQuirk::OptBad => "bad",
+        })
+    }
+}
+
 /// Detailed HTTP characteristics
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct HttpDetails {
@@ -278,6 +586,8 @@ pub struct TlsDetails {
     pub extensions: Vec<u16>,
     pub signature_algorithms: Vec<u16>,
     pub elliptic_curves: Vec<u16>,
+    #[serde(default)]
+    pub elliptic_curve_point_formats: Vec<u8>,
 }
 
 /// Additional profile metadata
@@ -294,9 +604,13 @@ pub struct ProfileMetadata {
 }
 
 impl TrafficProfile {
-    /// Create a new traffic profile
+    /// Create a new traffic profile observed now (wall-clock time)
     pub fn new(ip: IpAddr, port: u16) -> Self {
-        let now = Utc::now();
+        Self::new_at(ip, port, Utc::now())
+    }
+
+    /// Create a new traffic profile observed at the given time
+    pub fn new_at(ip: IpAddr, port: u16, now: DateTime<Utc>) -> Self {
         Self {
             ip,
             port,
@@ -307,6 +621,15 @@ impl TrafficProfile {
             tcp_server: None,
             http: None,
             tls: None,
+            consistency: Vec::new(),
+            nat: NatAnalysis::default(),
+            link_type: None,
+            likely_vpn: false,
+            hops: HopHistory::default(),
+            last_reboot_estimate: None,
+            uptime_history: UptimeHistory::default(),
+            risk: RiskScore::default(),
+            enrichment: BTreeMap::new(),
             metadata: ProfileMetadata {
                 first_seen: now,
                 last_updated: now,
@@ -336,25 +659,9 @@ impl TrafficProfile {
 
     /// Update the profile with HTTP analysis
     pub fn update_http(&mut self, http: HttpAnalysis) {
-        if let Some(existing_http) = &mut self.http {
-            // Merge request and response data
-            if http.request.is_some() {
-                existing_http.request = http.request;
-            }
-            if http.response.is_some() {
-                existing_http.response = http.response;
-            }
-            // Update other fields if they have better quality
-            if http.quality > existing_http.quality {
-                existing_http.browser = http.browser;
-                existing_http.quality = http.quality;
-                existing_http.language = http.language;
-                existing_http.diagnosis = http.diagnosis;
-                existing_http.signature = http.signature;
-                existing_http.details = http.details;
-            }
-        } else {
-            self.http = Some(http);
+        match &mut self.http {
+            Some(existing_http) => existing_http.merge(http),
+            None => self.http = Some(http),
         }
         self.update_metadata();
     }
@@ -367,7 +674,8 @@ impl TrafficProfile {
 
     /// Calculate and update profile completeness
     fn update_metadata(&mut self) {
-        self.metadata.last_updated = Utc::now();
+        // The profile timestamp is the observation time of the data being added
+        self.metadata.last_updated = self.metadata.last_updated.max(self.timestamp);
         self.metadata.packet_count += 1;
 
         // Calculate completeness based on available data
@@ -385,6 +693,25 @@ impl TrafficProfile {
         self.metadata.completeness = score;
     }
 
+    /// Copy of the observation histories that are not serialized with the profile
+    pub fn tracking_history(&self) -> TrackingHistory {
+        TrackingHistory {
+            nat: self.nat.history.clone(),
+            uptime: self.uptime_history.samples.clone(),
+        }
+    }
+
+    /// Put back histories saved with [`TrafficProfile::tracking_history`]
+    pub fn restore_tracking_history(&mut self, history: TrackingHistory) {
+        self.nat.history = history.nat;
+        self.uptime_history.samples = history.uptime;
+    }
+
+    /// Whether any cross-layer inconsistency was found
+    pub fn is_inconsistent(&self) -> bool {
+        !self.consistency.is_empty()
+    }
+
     /// Check if profile has any analysis data
     pub fn is_empty(&self) -> bool {
         self.tcp.is_none()
@@ -0,0 +1,174 @@
+use crate::nat::{HostSignals, CLOCK_TOLERANCE_SECS};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+/// Upper bound on remembered uptime samples per profile
+const MAX_SAMPLES: usize = 16;
+
+/// How a new uptime sample relates to the previous ones
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UptimeChange {
+    /// Uptime advanced with wall-clock time (or wrapped around its modulo)
+    Consistent,
+    /// Uptime dropped: the host booted again at `boot_time`
+    Reboot {
+        previous_boot: DateTime<Utc>,
+        boot_time: DateTime<Utc>,
+    },
+    /// Uptime grew faster than wall-clock time allows
+    Jump {
+        expected_boot: DateTime<Utc>,
+        boot_time: DateTime<Utc>,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct UptimeSample {
+    boot_time: DateTime<Utc>,
+    observed_at: DateTime<Utc>,
+}
+
+/// Short history of the uptime a host reported through its TCP timestamps
+///
+/// A steady clock keeps the same boot time across observations. A later boot
+/// time means the uptime dropped (a reboot); an earlier one means it grew more
+/// than the elapsed time, which a single clock cannot do.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct UptimeHistory {
+    /// Reboots detected since the profile was created
+    pub reboots: u32,
+    /// Samples whose uptime grew more than the elapsed time
+    pub inconsistent_jumps: u32,
+    /// Samples since the last reboot, oldest first
+    #[serde(skip)]
+    pub(crate) samples: VecDeque<UptimeSample>,
+}
+
+impl UptimeHistory {
+    /// Compare the uptime of a new result against the history and record it
+    ///
+    /// Returns `None` when the result carries no uptime, when there is nothing
+    /// to compare against yet, or when it is older than the newest sample.
+    pub fn observe(&mut self, signals: &HostSignals) -> Option<UptimeChange> {
+        let boot_time = signals.boot_time?;
+        let sample = UptimeSample {
+            boot_time,
+            observed_at: signals.observed_at,
+        };
+
+        let Some(latest) = self.samples.back() else {
+            self.samples.push_back(sample);
+            return None;
+        };
+        if signals.observed_at < latest.observed_at {
+            return None;
+        }
+
+        let shift = (boot_time - latest.boot_time).num_seconds();
+        let wrapped = signals
+            .clock_modulo_days
+            .map(|days| i64::from(days) * 86_400)
+            .is_some_and(|period| {
+                let offset = shift.rem_euclid(period);
+                offset <= CLOCK_TOLERANCE_SECS || period - offset <= CLOCK_TOLERANCE_SECS
+            });
+
+        let change = if shift.abs() <= CLOCK_TOLERANCE_SECS || (shift > 0 && wrapped) {
+            UptimeChange::Consistent
+        } else if shift > 0 {
+            UptimeChange::Reboot {
+                previous_boot: latest.boot_time,
+                boot_time,
+            }
+        } else {
+            UptimeChange::Jump {
+                expected_boot: latest.boot_time,
+                boot_time,
+            }
+        };
+
+        match change {
+            UptimeChange::Consistent => self.samples.push_back(sample),
+            UptimeChange::Reboot { .. } => {
+                self.reboots += 1;
+                self.samples.clear();
+                self.samples.push_back(sample);
+            }
+            // Keep comparing against the clock we trust
+            UptimeChange::Jump { .. } => self.inconsistent_jumps += 1,
+        }
+        if self.samples.len() > MAX_SAMPLES {
+            self.samples.pop_front();
+        }
+
+        Some(change)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn uptime(uptime_mins: i64, at_mins: i64, modulo_days: Option<u32>) -> HostSignals {
+        HostSignals {
+            clock_modulo_days: modulo_days,
+            ..HostSignals::at(at_mins).with_uptime(uptime_mins)
+        }
+    }
+
+    #[test]
+    fn test_uptime_drop_is_a_reboot() {
+        let mut history = UptimeHistory::default();
+        assert_eq!(history.observe(&uptime(3000, 0, None)), None);
+        assert_eq!(
+            history.observe(&uptime(3010, 10, None)),
+            Some(UptimeChange::Consistent)
+        );
+
+        let rebooted = uptime(5, 30, None);
+        assert_eq!(
+            history.observe(&rebooted),
+            Some(UptimeChange::Reboot {
+                previous_boot: uptime(3000, 0, None).boot_time.unwrap(),
+                boot_time: rebooted.boot_time.unwrap(),
+            })
+        );
+        assert_eq!(history.reboots, 1);
+
+        // The new clock is the reference from now on
+        assert_eq!(
+            history.observe(&uptime(15, 40, None)),
+            Some(UptimeChange::Consistent)
+        );
+    }
+
+    #[test]
+    fn test_modulo_wrap_is_not_a_reboot() {
+        let mut history = UptimeHistory::default();
+        let period_mins = 24 * 60;
+        history.observe(&uptime(period_mins - 10, 0, Some(1)));
+
+        assert_eq!(
+            history.observe(&uptime(10, 20, Some(1))),
+            Some(UptimeChange::Consistent)
+        );
+        assert_eq!(history.reboots, 0);
+    }
+
+    #[test]
+    fn test_uptime_growing_too_fast_is_a_jump() {
+        let mut history = UptimeHistory::default();
+        history.observe(&uptime(100, 0, None));
+
+        assert!(matches!(
+            history.observe(&uptime(5000, 10, None)),
+            Some(UptimeChange::Jump { .. })
+        ));
+        assert_eq!(history.inconsistent_jumps, 1);
+        assert_eq!(
+            history.observe(&uptime(120, 20, None)),
+            Some(UptimeChange::Consistent)
+        );
+    }
+}
@@ -0,0 +1,420 @@
+use crate::consistency::{ja4_looks_like_library, FindingKind, Severity};
+use crate::hops::HopAnomalyKind;
+use crate::profile::TrafficProfile;
+use crate::user_agent::{is_browser_label, AgentCategory, UserAgent};
+use serde::{Deserialize, Serialize};
+
+/// Highest possible risk score
+pub const MAX_RISK_SCORE: u8 = 100;
+
+/// Points each signal contributes at full strength
+///
+/// The defaults add up to 100, so a profile that trips every signal scores
+/// 100. Scores above 100 are capped; a weight of 0 disables a signal.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RiskWeights {
+    /// Weak or missing OS match for the TCP SYN
+    pub os_quality: f64,
+    /// p0f HTTP diagnosis (dishonest, anonymous or generic)
+    pub http_diagnosis: f64,
+    /// Request headers every browser sends are missing
+    pub missing_headers: f64,
+    /// TLS ClientHello looks like a library or tool
+    pub tls_library: f64,
+    /// Cross-layer inconsistencies (User-Agent vs TCP/HTTP/TLS)
+    pub ua_mismatch: f64,
+    /// Sudden hop-distance changes
+    pub hop_anomaly: f64,
+    /// User-Agent names a bot, crawler or HTTP tool
+    pub automation_user_agent: f64,
+}
+
+impl Default for RiskWeights {
+    fn default() -> Self {
+        Self {
+            os_quality: 10.0,
+            http_diagnosis: 15.0,
+            missing_headers: 15.0,
+            tls_library: 15.0,
+            ua_mismatch: 25.0,
+            hop_anomaly: 10.0,
+            automation_user_agent: 10.0,
+        }
+    }
+}
+
+/// Contribution of one signal to a risk score
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RiskSignal {
+    /// Signal name, matching the `RiskWeights` field
+    pub name: String,
+    /// Signal strength between 0.0 (benign) and 1.0 (automated)
+    pub value: f64,
+    /// Configured weight of the signal
+    pub weight: f64,
+    /// Points added to the score (`value * weight`)
+    pub points: f64,
+    /// What was observed
+    pub detail: String,
+}
+
+/// Bot/automation risk of a profile, 0 (human browser) to 100
+///
+/// Only signals whose layer was observed appear in the breakdown; a profile
+/// with nothing but a SYN is scored on the SYN alone.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RiskScore {
+    /// Combined score, capped at 100
+    pub score: u8,
+    /// Per-signal breakdown
+    pub signals: Vec<RiskSignal>,
+}
+
+/// Headers every mainstream browser sends with a request
+const BROWSER_HEADERS: [&str; 4] = ["user-agent", "accept", "accept-language", "accept-encoding"];
+
+impl RiskScore {
+    /// Score a (merged) profile
+    pub fn compute(profile: &TrafficProfile, weights: &RiskWeights) -> Self {
+        let mut signals = Vec::new();
+        let mut add = |name: &str, weight: f64, value: f64, detail: String| {
+            let value = value.clamp(0.0, 1.0);
+            signals.push(RiskSignal {
+                name: name.to_string(),
+                value,
+                weight,
+                points: value * weight,
+                detail,
+            });
+        };
+
+        if let Some(tcp) = profile.tcp_client.as_ref().or(profile.tcp.as_ref()) {
+            add(
+                "os_quality",
+                weights.os_quality,
+                1.0 - tcp.quality,
+                format!("{} (quality {:.2})", tcp.os, tcp.quality),
+            );
+        }
+
+        let http = profile.http.as_ref().filter(|http| http.request.is_some());
+        if let Some(http) = http {
+            let diagnosis = http.diagnosis.to_lowercase();
+            let value = match diagnosis.as_str() {
+                "dishonest" => 1.0,
+                "anonymous" => 0.6,
+                "generic" => 0.3,
+                _ => 0.0,
+            };
+            add(
+                "http_diagnosis",
+                weights.http_diagnosis,
+                value,
+                http.diagnosis.clone(),
+            );
+        }
+
+        if let Some(request) = &profile.raw_data.http_request {
+            let present = [
+                request.user_agent.is_some(),
+                request.accept.is_some(),
+                request.accept_language.is_some(),
+                request.accept_encoding.is_some(),
+            ];
+            let missing: Vec<&str> = BROWSER_HEADERS
+                .iter()
+                .zip(present)
+                .filter(|(_, present)| !present)
+                .map(|(name, _)| *name)
+                .collect();
+            add(
+                "missing_headers",
+                weights.missing_headers,
+                missing.len() as f64 / BROWSER_HEADERS.len() as f64,
+                if missing.is_empty() {
+                    "none".to_string()
+                } else {
+                    missing.join(",")
+                },
+            );
+
+            let user_agent = http
+                .and_then(|http| http.user_agent.clone())
+                .or_else(|| request.user_agent.as_deref().map(UserAgent::parse));
+            if let Some(user_agent) = user_agent {
+                let value = match user_agent.category {
+                    AgentCategory::Browser => 0.0,
+                    AgentCategory::Unknown => 0.5,
+                    AgentCategory::Bot | AgentCategory::Tool => 1.0,
+                };
+                add(
+                    "automation_user_agent",
+                    weights.automation_user_agent,
+                    value,
+                    user_agent.summary(),
+                );
+
+                let worst = profile
+                    .consistency
+                    .iter()
+                    .map(|finding| finding.severity)
+                    .max();
+                add(
+                    "ua_mismatch",
+                    weights.ua_mismatch,
+                    match worst {
+                        Some(Severity::High) => 1.0,
+                        Some(Severity::Medium) => 0.6,
+                        Some(Severity::Low) => 0.3,
+                        None => 0.0,
+                    },
+                    profile
+                        .consistency
+                        .iter()
+                        .map(|finding| finding.kind.as_str())
+                        .collect::<Vec<_>>()
+                        .join(","),
+                );
+            }
+        }
+
+        if let Some(tls) = &profile.tls {
+            // A JA4 database label beats the JA4 heuristic
+            let library = match &tls.client_application {
+                Some(application) => Some(!is_browser_label(&application.name)),
+                None => ja4_looks_like_library(&tls.ja4),
+            };
+            // With a browser User-Agent the heuristic already counts under ua_mismatch
+            let counted = profile
+                .consistency
+                .iter()
+                .any(|finding| finding.kind == FindingKind::TlsClientMismatch);
+            if let Some(library) = library {
+                let mut detail = match &tls.client_application {
+                    Some(application) => format!("{} ({})", tls.ja4, application.name),
+                    None => tls.ja4.clone(),
+                };
+                if library && counted {
+                    detail.push_str(", counted as tls_client_mismatch");
+                }
+                add(
+                    "tls_library",
+                    weights.tls_library,
+                    if library && !counted { 1.0 } else { 0.0 },
+                    detail,
+                );
+            }
+        }
+
+        if profile.hops.current.is_some() {
+            let worst = profile
+                .hops
+                .anomalies
+                .iter()
+                .map(|anomaly| match anomaly.kind {
+                    HopAnomalyKind::LargeJump => 1.0,
+                    HopAnomalyKind::Flapping => 0.6,
+                    HopAnomalyKind::RouteChange => 0.2,
+                })
+                .fold(0.0, f64::max);
+            add(
+                "hop_anomaly",
+                weights.hop_anomaly,
+                worst,
+                format!("{} changes", profile.hops.changes),
+            );
+        }
+
+        let total: f64 = signals.iter().map(|signal| signal.points).sum();
+        Self {
+            score: total.round().clamp(0.0, f64::from(MAX_RISK_SCORE)) as u8,
+            signals,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::profile::{HttpDetails, HttpRequestData};
+    use crate::HttpAnalysis;
+
+    fn http_request(user_agent: Option<&str>, diagnosis: &str) -> TrafficProfile {
+        let mut profile = TrafficProfile::new("10.0.0.5".parse().unwrap(), 40001);
+        let request = HttpRequestData {
+            user_agent: user_agent.map(str::to_string),
+            accept: Some("*/*".to_string()),
+            accept_language: None,
+            accept_encoding: None,
+            connection: None,
+            method: Some("GET".to_string()),
+            uri: Some("/".to_string()),
+            version: "1".to_string(),
+            ja4h: None,
+            ja4h_raw: None,
+            host: None,
+            signature: String::new(),
+            quality: 0.0,
+        };
+        profile.raw_data.http_request = Some(request.clone());
+        profile.http = Some(HttpAnalysis {
+            browser: "Unknown".to_string(),
+            quality: 0.0,
+            language: None,
+            diagnosis: diagnosis.to_string(),
+            signature: String::new(),
+            user_agent: user_agent.map(UserAgent::parse),
+            details: HttpDetails {
+                version: "1".to_string(),
+                header_order: String::new(),
+                headers_absent: String::new(),
+                expected_software: String::new(),
+            },
+            request: Some(request),
+            response: None,
+        });
+        profile
+    }
+
+    #[test]
+    fn test_curl_scores_higher_than_a_browser() {
+        let weights = RiskWeights::default();
+        let curl = RiskScore::compute(&http_request(Some("curl/8.5.0"), "anonymous"), &weights);
+        let firefox = RiskScore::compute(
+            &http_request(
+                Some("Mozilla/5.0 (X11; Linux x86_64; rv:125.0) Gecko/20100101 Firefox/125.0"),
+                "none",
+            ),
+            &weights,
+        );
+
+        assert!(curl.score > firefox.score);
+        let automation = curl
+            .signals
+            .iter()
+            .find(|signal| signal.name == "automation_user_agent")
+            .unwrap();
+        assert_eq!(automation.points, 10.0);
+        // 9 (anonymous) + 7.5 (two of four headers missing) + 10 (tool)
+        assert_eq!(curl.score, 27);
+    }
+
+    fn with_tls(mut profile: TrafficProfile, ja4: &str, label: Option<&str>) -> TrafficProfile {
+        profile.tls = Some(crate::TlsAnalysis {
+            ja4: ja4.to_string(),
+            ja4_raw: String::new(),
+            ja4_original: String::new(),
+            ja4_original_raw: String::new(),
+            client_application: label.map(|name| crate::ClientApplication {
+                name: name.to_string(),
+                confidence: 1.0,
+            }),
+            ja3: String::new(),
+            ja3_hash: String::new(),
+            details: crate::profile::TlsDetails {
+                version: "13".to_string(),
+                sni: None,
+                alpn: None,
+                cipher_suites: vec![],
+                extensions: vec![],
+                signature_algorithms: vec![],
+                elliptic_curves: vec![],
+                elliptic_curve_point_formats: vec![],
+            },
+        });
+        profile.consistency = crate::ConsistencyChecker::check(&profile);
+        profile
+    }
+
+    fn points(risk: &RiskScore, name: &str) -> f64 {
+        risk.signals
+            .iter()
+            .find(|signal| signal.name == name)
+            .map_or(0.0, |signal| signal.points)
+    }
+
+    fn signal<'a>(risk: &'a RiskScore, name: &str) -> &'a RiskSignal {
+        risk.signals
+            .iter()
+            .find(|signal| signal.name == name)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_captured_requests_are_scored_on_their_headers() {
+        let analyzer = crate::HuginnAnalyzer::new();
+        let curl = analyzer.analyze_request(
+            b"GET / HTTP/1.1\r\nHost: example.com\r\nUser-Agent: curl/8.5.0\r\nAccept: */*\r\n\r\n",
+        );
+        assert_eq!(
+            signal(&curl.risk, "missing_headers").detail,
+            "accept-language,accept-encoding"
+        );
+        assert_eq!(points(&curl.risk, "automation_user_agent"), 10.0);
+
+        let chrome = analyzer.analyze_request(
+            b"GET / HTTP/1.1\r\n\
+              Host: example.com\r\n\
+              Connection: keep-alive\r\n\
+              User-Agent: Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/126.0.0.0 Safari/537.36\r\n\
+              Accept: */*\r\n\
+              Accept-Encoding: gzip,deflate,sdch\r\n\
+              Accept-Language: en-US,en;q=0.8\r\n\
+              Accept-Charset: utf-8;q=0.7,*;q=0.3\r\n\r\n",
+        );
+        assert_eq!(signal(&chrome.risk, "missing_headers").detail, "none");
+        assert_eq!(signal(&chrome.risk, "automation_user_agent").points, 0.0);
+        assert_eq!(signal(&chrome.risk, "ua_mismatch").points, 0.0);
+        assert!(chrome.risk.score < curl.risk.score);
+    }
+
+    #[test]
+    fn test_tls_library_is_counted_once() {
+        const LIBRARY_JA4: &str = "t13d1109h1_76e208dd3e22_2dae41c691ec";
+        const BROWSER_JA4: &str = "t13d1516h2_8daaf6152771_02713d6af862";
+        let weights = RiskWeights::default();
+        let chrome = Some(
+            "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/126.0.0.0 Safari/537.36",
+        );
+
+        // A browser User-Agent on a library ClientHello is one mismatch, not two signals
+        let spoofed = RiskScore::compute(
+            &with_tls(http_request(chrome, "none"), LIBRARY_JA4, None),
+            &weights,
+        );
+        assert_eq!(points(&spoofed, "ua_mismatch"), 25.0);
+        assert_eq!(points(&spoofed, "tls_library"), 0.0);
+
+        // The JA4 database label decides over the heuristic
+        let curl = RiskScore::compute(
+            &with_tls(http_request(None, "none"), BROWSER_JA4, Some("curl")),
+            &weights,
+        );
+        assert_eq!(points(&curl, "tls_library"), 15.0);
+        let labelled_chrome = RiskScore::compute(
+            &with_tls(http_request(None, "none"), LIBRARY_JA4, Some("Chrome 12x")),
+            &weights,
+        );
+        assert_eq!(points(&labelled_chrome, "tls_library"), 0.0);
+    }
+
+    #[test]
+    fn test_weights_scale_and_cap_the_score() {
+        let profile = http_request(None, "dishonest");
+        let weights = RiskWeights {
+            http_diagnosis: 200.0,
+            ..RiskWeights::default()
+        };
+
+        let risk = RiskScore::compute(&profile, &weights);
+        assert_eq!(risk.score, MAX_RISK_SCORE);
+
+        let disabled = RiskWeights {
+            http_diagnosis: 0.0,
+            missing_headers: 0.0,
+            ..RiskWeights::default()
+        };
+        assert_eq!(RiskScore::compute(&profile, &disabled).score, 0);
+    }
+}
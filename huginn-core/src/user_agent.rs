@@ -0,0 +1,419 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Browsers recognised in `Mozilla/` User-Agents, most specific token first
+const BROWSER_TOKENS: &[(&str, &str)] = &[
+    ("Edg/", "Edge"),
+    ("EdgA/", "Edge"),
+    ("EdgiOS/", "Edge"),
+    ("OPR/", "Opera"),
+    ("SamsungBrowser/", "Samsung Internet"),
+    ("CriOS/", "Chrome"),
+    ("Chrome/", "Chrome"),
+    ("FxiOS/", "Firefox"),
+    ("Firefox/", "Firefox"),
+];
+
+/// Whether a label such as "Chrome 12x" (e.g. from the JA4 database) names a browser
+pub(crate) fn is_browser_label(label: &str) -> bool {
+    let label = label.to_lowercase();
+    BROWSER_TOKENS
+        .iter()
+        .map(|(_, name)| *name)
+        .chain(["Safari"])
+        .any(|name| label.starts_with(&name.to_lowercase()))
+}
+
+/// Well-known crawlers, matched before anything else
+const BOT_TOKENS: &[(&str, &str)] = &[
+    ("Googlebot/", "Googlebot"),
+    ("bingbot/", "Bingbot"),
+    ("YandexBot/", "YandexBot"),
+    ("DuckDuckBot/", "DuckDuckBot"),
+    ("Baiduspider/", "Baiduspider"),
+    ("Applebot/", "Applebot"),
+    ("facebookexternalhit/", "facebookexternalhit"),
+];
+
+/// What kind of software sent the User-Agent
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AgentCategory {
+    /// Interactive web browser
+    Browser,
+    /// Crawler or other self-declared bot
+    Bot,
+    /// HTTP library or command line tool (curl, python-requests, ...)
+    Tool,
+    Unknown,
+}
+
+/// Operating system family claimed by a User-Agent
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum OsFamily {
+    Windows,
+    Linux,
+    Android,
+    #[serde(rename = "ChromeOS")]
+    ChromeOs,
+    #[serde(rename = "macOS")]
+    MacOs,
+    #[serde(rename = "iOS")]
+    Ios,
+    #[serde(rename = "BSD")]
+    Bsd,
+}
+
+impl OsFamily {
+    /// Display name of this family
+    pub fn name(self) -> &'static str {
+        match self {
+            OsFamily::Windows => "Windows",
+            OsFamily::Linux => "Linux",
+            OsFamily::Android => "Android",
+            OsFamily::ChromeOs => "ChromeOS",
+            OsFamily::MacOs => "macOS",
+            OsFamily::Ios => "iOS",
+            OsFamily::Bsd => "BSD",
+        }
+    }
+}
+
+impl fmt::Display for OsFamily {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Kind of device a User-Agent claims to run on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeviceClass {
+    Desktop,
+    Mobile,
+    Tablet,
+    Bot,
+    Unknown,
+}
+
+impl DeviceClass {
+    /// Stable snake_case name of this class
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DeviceClass::Desktop => "desktop",
+            DeviceClass::Mobile => "mobile",
+            DeviceClass::Tablet => "tablet",
+            DeviceClass::Bot => "bot",
+            DeviceClass::Unknown => "unknown",
+        }
+    }
+}
+
+/// Structured view of a User-Agent header
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UserAgent {
+    /// Browser, bot or tool name (e.g. "Chrome", "Googlebot", "curl")
+    pub browser_family: Option<String>,
+    /// Version as written in the User-Agent (e.g. "126.0.0.0")
+    pub browser_version: Option<String>,
+    /// Operating system family
+    pub os_family: Option<OsFamily>,
+    /// Operating system version (e.g. "10", "14", "17.4")
+    pub os_version: Option<String>,
+    /// Device class
+    pub device: DeviceClass,
+    /// Browser, bot or tool
+    pub category: AgentCategory,
+}
+
+impl UserAgent {
+    /// Parse a User-Agent header
+    pub fn parse(user_agent: &str) -> Self {
+        let user_agent = user_agent.trim();
+        let (os_family, os_version) = Self::parse_os(user_agent);
+
+        let (browser_family, browser_version, category) = if let Some((token, name)) = BOT_TOKENS
+            .iter()
+            .find(|(token, _)| user_agent.contains(token))
+        {
+            (
+                Some(name.to_string()),
+                Self::token_version(user_agent, token),
+                AgentCategory::Bot,
+            )
+        } else if Self::is_generic_bot(user_agent) {
+            (Self::generic_bot_name(user_agent), None, AgentCategory::Bot)
+        } else if user_agent.starts_with("Mozilla/") {
+            match Self::parse_browser(user_agent) {
+                Some((name, version)) => (Some(name.to_string()), version, AgentCategory::Browser),
+                None => (None, None, AgentCategory::Unknown),
+            }
+        } else if let Some((product, version)) = Self::product(user_agent) {
+            (Some(product), version, AgentCategory::Tool)
+        } else {
+            (None, None, AgentCategory::Unknown)
+        };
+
+        let device = Self::device(user_agent, category, os_family);
+
+        Self {
+            browser_family,
+            browser_version,
+            os_family,
+            os_version,
+            device,
+            category,
+        }
+    }
+
+    /// Browser family if the User-Agent claims to be a mainstream browser
+    pub fn claimed_browser(&self) -> Option<&str> {
+        if self.category == AgentCategory::Browser {
+            self.browser_family.as_deref()
+        } else {
+            None
+        }
+    }
+
+    /// Short "browser OS" label, e.g. "Chrome Android" or "curl"
+    pub fn summary(&self) -> String {
+        let parts: Vec<&str> = self
+            .browser_family
+            .as_deref()
+            .into_iter()
+            .chain(self.os_family.map(OsFamily::name))
+            .collect();
+        if parts.is_empty() {
+            "Unknown".to_string()
+        } else {
+            parts.join(" ")
+        }
+    }
+
+    fn parse_browser(user_agent: &str) -> Option<(&'static str, Option<String>)> {
+        if let Some((token, name)) = BROWSER_TOKENS
+            .iter()
+            .find(|(token, _)| user_agent.contains(token))
+        {
+            return Some((name, Self::token_version(user_agent, token)));
+        }
+        if user_agent.contains("Safari/") && user_agent.contains("Version/") {
+            return Some(("Safari", Self::token_version(user_agent, "Version/")));
+        }
+        None
+    }
+
+    fn parse_os(user_agent: &str) -> (Option<OsFamily>, Option<String>) {
+        if let Some(version) = Self::token_version(user_agent, "Windows NT ") {
+            let version = match version.as_str() {
+                "10.0" => "10",
+                "6.3" => "8.1",
+                "6.2" => "8",
+                "6.1" => "7",
+                "6.0" => "Vista",
+                "5.1" | "5.2" => "XP",
+                other => other,
+            };
+            (Some(OsFamily::Windows), Some(version.to_string()))
+        } else if user_agent.contains("Windows") {
+            (Some(OsFamily::Windows), None)
+        } else if user_agent.contains("Android") {
+            (
+                Some(OsFamily::Android),
+                Self::token_version(user_agent, "Android "),
+            )
+        } else if ["iPhone", "iPad", "iPod"]
+            .iter()
+            .any(|device| user_agent.contains(device))
+        {
+            (Some(OsFamily::Ios), Self::apple_version(user_agent, " OS "))
+        } else if user_agent.contains("Macintosh") || user_agent.contains("Mac OS X") {
+            (
+                Some(OsFamily::MacOs),
+                Self::apple_version(user_agent, "Mac OS X "),
+            )
+        } else if user_agent.contains("CrOS") {
+            (Some(OsFamily::ChromeOs), None)
+        } else if user_agent.contains("Linux") || user_agent.contains("X11") {
+            (Some(OsFamily::Linux), None)
+        } else if user_agent.contains("BSD") {
+            (Some(OsFamily::Bsd), None)
+        } else {
+            (None, None)
+        }
+    }
+
+    fn device(user_agent: &str, category: AgentCategory, os: Option<OsFamily>) -> DeviceClass {
+        if category == AgentCategory::Bot {
+            return DeviceClass::Bot;
+        }
+        if user_agent.contains("iPad") || user_agent.contains("Tablet") {
+            return DeviceClass::Tablet;
+        }
+        if user_agent.contains("iPhone")
+            || user_agent.contains("iPod")
+            || user_agent.contains("Mobile")
+            || user_agent.contains("Windows Phone")
+        {
+            return DeviceClass::Mobile;
+        }
+        match os {
+            // Android browsers only omit "Mobile" on tablets
+            Some(OsFamily::Android) => DeviceClass::Tablet,
+            Some(OsFamily::Ios) => DeviceClass::Mobile,
+            Some(_) => DeviceClass::Desktop,
+            None => DeviceClass::Unknown,
+        }
+    }
+
+    /// Text following `token` up to the next separator
+    fn token_version(user_agent: &str, token: &str) -> Option<String> {
+        let start = user_agent.find(token)? + token.len();
+        let version: String = user_agent[start..]
+            .chars()
+            .take_while(|c| !c.is_whitespace() && !matches!(c, ';' | ')' | '('))
+            .collect();
+        Some(version).filter(|version| !version.is_empty())
+    }
+
+    /// Apple versions use underscores, e.g. "OS 17_4 like Mac OS X" → "17.4"
+    fn apple_version(user_agent: &str, token: &str) -> Option<String> {
+        Self::token_version(user_agent, token)
+            .filter(|version| version.starts_with(|c: char| c.is_ascii_digit()))
+            .map(|version| version.replace('_', "."))
+    }
+
+    /// Whether "bot" ends a product name ("Googlebot/2.1", "SomeBot;",
+    /// "feed-bot") or opens the User-Agent, rather than sitting inside a word
+    /// such as "CUBOT", "Abbott" or "-bottle"
+    fn is_generic_bot(user_agent: &str) -> bool {
+        let lower = user_agent.to_lowercase();
+        lower.starts_with("bot")
+            || ["bot/", "bot;", "crawler", "spider"]
+                .iter()
+                .any(|needle| lower.contains(needle))
+            || lower.match_indices("-bot").any(|(index, needle)| {
+                lower[index + needle.len()..]
+                    .chars()
+                    .next()
+                    .is_none_or(|c| matches!(c, '/' | ';' | ' '))
+            })
+    }
+
+    /// Name of the first product token that looks like a bot
+    fn generic_bot_name(user_agent: &str) -> Option<String> {
+        user_agent
+            .split(|c: char| c.is_whitespace() || matches!(c, '(' | ')' | '+'))
+            .filter(|token| Self::is_generic_bot(token) && !token.starts_with("http"))
+            .filter_map(|token| token.split(['/', ';']).next())
+            .find(|name| !name.is_empty())
+            .map(str::to_string)
+    }
+
+    /// Leading `product/version` token of a non-browser User-Agent
+    fn product(user_agent: &str) -> Option<(String, Option<String>)> {
+        let token = user_agent.split_whitespace().next()?;
+        let (product, version) = match token.split_once('/') {
+            Some((product, version)) => (product, Some(version.to_string())),
+            None => (token, None),
+        };
+        if product.is_empty() {
+            return None;
+        }
+        Some((
+            product.to_string(),
+            version.filter(|version| !version.is_empty()),
+        ))
+    }
+}
+
+impl fmt::Display for UserAgent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.summary())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parses_desktop_and_mobile_browsers() {
+        let chrome = UserAgent::parse("Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/126.0.0.0 Safari/537.36");
+        assert_eq!(chrome.browser_family.as_deref(), Some("Chrome"));
+        assert_eq!(chrome.browser_version.as_deref(), Some("126.0.0.0"));
+        assert_eq!(chrome.os_family, Some(OsFamily::Windows));
+        assert_eq!(chrome.os_version.as_deref(), Some("10"));
+        assert_eq!(chrome.device, DeviceClass::Desktop);
+        assert_eq!(chrome.claimed_browser(), Some("Chrome"));
+
+        let safari = UserAgent::parse("Mozilla/5.0 (iPhone; CPU iPhone OS 17_4 like Mac OS X) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/17.4 Mobile/15E148 Safari/604.1");
+        assert_eq!(safari.browser_family.as_deref(), Some("Safari"));
+        assert_eq!(safari.browser_version.as_deref(), Some("17.4"));
+        assert_eq!(safari.os_family, Some(OsFamily::Ios));
+        assert_eq!(safari.os_version.as_deref(), Some("17.4"));
+        assert_eq!(safari.device, DeviceClass::Mobile);
+
+        let android = UserAgent::parse("Mozilla/5.0 (Linux; Android 14; Pixel 8) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/126.0.0.0 Mobile Safari/537.36");
+        assert_eq!(android.summary(), "Chrome Android");
+        assert_eq!(android.os_version.as_deref(), Some("14"));
+        assert_eq!(android.device, DeviceClass::Mobile);
+
+        let edge = UserAgent::parse("Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/126.0.0.0 Safari/537.36 Edg/126.0.2592.87");
+        assert_eq!(edge.browser_family.as_deref(), Some("Edge"));
+        assert_eq!(edge.os_family, Some(OsFamily::MacOs));
+        assert_eq!(edge.os_version.as_deref(), Some("10.15.7"));
+    }
+
+    #[test]
+    fn test_parses_bots_and_tools() {
+        let googlebot = UserAgent::parse(
+            "Mozilla/5.0 (compatible; Googlebot/2.1; +http://www.google.com/bot.html)",
+        );
+        assert_eq!(googlebot.browser_family.as_deref(), Some("Googlebot"));
+        assert_eq!(googlebot.browser_version.as_deref(), Some("2.1"));
+        assert_eq!(googlebot.device, DeviceClass::Bot);
+        assert_eq!(googlebot.claimed_browser(), None);
+
+        let crawler = UserAgent::parse("Mozilla/5.0 (compatible; MJ12bot/v1.4.8)");
+        assert_eq!(crawler.browser_family.as_deref(), Some("MJ12bot"));
+        assert_eq!(crawler.category, AgentCategory::Bot);
+
+        let curl = UserAgent::parse("curl/8.5.0");
+        assert_eq!(curl.browser_family.as_deref(), Some("curl"));
+        assert_eq!(curl.browser_version.as_deref(), Some("8.5.0"));
+        assert_eq!(curl.category, AgentCategory::Tool);
+        assert_eq!(curl.device, DeviceClass::Unknown);
+        assert_eq!(curl.summary(), "curl");
+
+        assert_eq!(UserAgent::parse("").summary(), "Unknown");
+    }
+
+    #[test]
+    fn test_bot_must_end_a_product_name() {
+        let semrush = UserAgent::parse(
+            "Mozilla/5.0 (compatible; SemrushBot; +http://www.semrush.com/bot.html)",
+        );
+        assert_eq!(semrush.browser_family.as_deref(), Some("SemrushBot"));
+        assert_eq!(semrush.category, AgentCategory::Bot);
+
+        let cubot = UserAgent::parse(
+            "Mozilla/5.0 (Linux; Android 10; CUBOT X30) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Mobile Safari/537.36",
+        );
+        assert_eq!(cubot.browser_family.as_deref(), Some("Chrome"));
+        assert_eq!(cubot.category, AgentCategory::Browser);
+
+        let abbott = UserAgent::parse("Abbott-Monitor/2.3");
+        assert_eq!(abbott.browser_family.as_deref(), Some("Abbott-Monitor"));
+        assert_eq!(abbott.category, AgentCategory::Tool);
+
+        let feed = UserAgent::parse("feed-bot (+https://example.com/feed)");
+        assert_eq!(feed.browser_family.as_deref(), Some("feed-bot"));
+        assert_eq!(feed.category, AgentCategory::Bot);
+
+        for user_agent in ["Wine-bottle/1.0", "Sync-both/2.1 (Linux)"] {
+            let parsed = UserAgent::parse(user_agent);
+            assert_eq!(parsed.category, AgentCategory::Tool, "{}", user_agent);
+        }
+    }
+}
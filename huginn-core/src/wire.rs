@@ -0,0 +1,515 @@
+use pnet_packet::ethernet::{EtherTypes, EthernetPacket};
+use pnet_packet::ip::IpNextHeaderProtocols;
+use pnet_packet::ipv4::Ipv4Packet;
+use pnet_packet::ipv6::Ipv6Packet;
+use pnet_packet::tcp::{TcpFlags, TcpPacket};
+use pnet_packet::vlan::VlanPacket;
+use pnet_packet::Packet;
+use std::collections::{HashMap, VecDeque};
+use std::net::{IpAddr, SocketAddr};
+use tls_parser::{
+    parse_tls_extensions, parse_tls_plaintext, TlsExtension, TlsMessage, TlsMessageHandshake,
+};
+
+/// Flows whose HTTP message head is remembered at once
+const DEFAULT_CAPACITY: usize = 4096;
+
+/// Longest HTTP message head (start line and headers) looked at
+const MAX_HEAD: usize = 8192;
+
+/// Packet details huginn-net 1.4 parses but does not report
+///
+/// Filled in by [`WireTracker`] from the same frame huginn-net analyzed.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WireDetails {
+    /// Method from the request line of the HTTP request on this flow
+    pub http_method: Option<String>,
+    /// Request target from the same request line
+    pub http_uri: Option<String>,
+    /// Host header of the same request
+    pub http_host: Option<String>,
+    /// Cookie header of the same request
+    pub http_cookie: Option<String>,
+    /// Status code from the status line of the HTTP response on this flow
+    pub http_status: Option<u16>,
+    /// EC point formats of the TLS ClientHello in this frame (empty when the
+    /// ClientHello has no `ec_point_formats` extension)
+    pub tls_point_formats: Option<Vec<u8>>,
+}
+
+/// First line of the HTTP message sent in one direction of a flow
+#[derive(Debug, Clone)]
+enum StartLine {
+    Request { method: String, uri: String },
+    Response { status: u16 },
+}
+
+/// Head of the HTTP message sent in one direction of a flow
+#[derive(Debug, Clone)]
+struct Message {
+    line: StartLine,
+    /// Start line and headers received so far
+    head: Vec<u8>,
+    /// Whether the blank line ending the headers was received
+    complete: bool,
+}
+
+impl Message {
+    fn new(line: StartLine, payload: &[u8]) -> Self {
+        let mut message = Self {
+            line,
+            head: Vec::new(),
+            complete: false,
+        };
+        message.extend(payload);
+        message
+    }
+
+    /// Add the next segment of the message, until the headers are complete
+    fn extend(&mut self, payload: &[u8]) {
+        if self.complete {
+            return;
+        }
+        let room = MAX_HEAD.saturating_sub(self.head.len());
+        self.head
+            .extend_from_slice(&payload[..payload.len().min(room)]);
+        self.complete =
+            self.head.len() >= MAX_HEAD || self.head.windows(4).any(|window| window == b"\r\n\r\n");
+    }
+
+    /// Value of the first header called `name`, once its line is complete
+    fn header(&self, name: &str) -> Option<String> {
+        let end = self.head.windows(2).rposition(|pair| pair == b"\r\n")?;
+        let head = String::from_utf8_lossy(&self.head[..end]);
+        head.split("\r\n")
+            .skip(1)
+            .take_while(|line| !line.is_empty())
+            .find_map(|line| {
+                let (header, value) = line.split_once(':')?;
+                header
+                    .trim()
+                    .eq_ignore_ascii_case(name)
+                    .then(|| value.trim().to_string())
+            })
+    }
+}
+
+/// Direction of a TCP flow: (source, destination)
+type FlowKey = (SocketAddr, SocketAddr);
+
+/// Reads the details huginn-net drops from raw Ethernet frames
+///
+/// huginn-net reassembles a flow before parsing HTTP, so the request or status
+/// line may have arrived in an earlier segment than the one it reports on. The
+/// tracker remembers the message head of each flow direction until the next
+/// message starts or the flow ends.
+#[derive(Debug)]
+pub struct WireTracker {
+    messages: HashMap<FlowKey, (u64, Message)>,
+    /// Flow directions in the order they were first seen, for eviction
+    order: VecDeque<(u64, FlowKey)>,
+    next_id: u64,
+    capacity: usize,
+}
+
+impl Default for WireTracker {
+    fn default() -> Self {
+        Self::with_capacity(DEFAULT_CAPACITY)
+    }
+}
+
+impl WireTracker {
+    /// Create a tracker remembering at most `capacity` flow directions
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            messages: HashMap::new(),
+            order: VecDeque::new(),
+            next_id: 0,
+            capacity: capacity.max(1),
+        }
+    }
+
+    /// Details of one Ethernet frame (frames that are not TCP have none)
+    pub fn observe(&mut self, frame: &[u8]) -> WireDetails {
+        let Some(ethernet) = EthernetPacket::new(frame) else {
+            return WireDetails::default();
+        };
+        let mut ethertype = ethernet.get_ethertype();
+        let mut payload = ethernet.payload();
+        // huginn-net looks through 802.1Q tags too
+        while ethertype == EtherTypes::Vlan {
+            let Some(vlan) = VlanPacket::new(payload) else {
+                return WireDetails::default();
+            };
+            ethertype = vlan.get_ethertype();
+            payload = &payload[payload.len() - vlan.payload().len()..];
+        }
+
+        match ethertype {
+            EtherTypes::Ipv4 => match Ipv4Packet::new(payload) {
+                Some(ip) if ip.get_next_level_protocol() == IpNextHeaderProtocols::Tcp => self
+                    .observe_tcp(
+                        IpAddr::V4(ip.get_source()),
+                        IpAddr::V4(ip.get_destination()),
+                        ip.payload(),
+                    ),
+                _ => WireDetails::default(),
+            },
+            EtherTypes::Ipv6 => match Ipv6Packet::new(payload) {
+                Some(ip) if ip.get_next_header() == IpNextHeaderProtocols::Tcp => self.observe_tcp(
+                    IpAddr::V6(ip.get_source()),
+                    IpAddr::V6(ip.get_destination()),
+                    ip.payload(),
+                ),
+                _ => WireDetails::default(),
+            },
+            _ => WireDetails::default(),
+        }
+    }
+
+    fn observe_tcp(&mut self, source: IpAddr, destination: IpAddr, segment: &[u8]) -> WireDetails {
+        let Some(tcp) = TcpPacket::new(segment) else {
+            return WireDetails::default();
+        };
+        let flow = (
+            SocketAddr::new(source, tcp.get_source()),
+            SocketAddr::new(destination, tcp.get_destination()),
+        );
+        let flags = tcp.get_flags();
+        let payload = tcp.payload();
+
+        // A new connection on a reused 4-tuple starts over
+        if flags & TcpFlags::SYN != 0 {
+            self.forget(flow);
+        }
+        if !payload.is_empty() {
+            // Each message on a keep-alive connection brings its own start line
+            match parse_start_line(payload) {
+                Some(line) => self.remember(flow, Message::new(line, payload)),
+                None => {
+                    if let Some((_, message)) = self.messages.get_mut(&flow) {
+                        message.extend(payload);
+                    }
+                }
+            }
+        }
+
+        let mut details = WireDetails {
+            tls_point_formats: client_hello_point_formats(payload),
+            ..Default::default()
+        };
+        if let Some((_, message)) = self.messages.get(&flow) {
+            match &message.line {
+                StartLine::Request { method, uri } => {
+                    details.http_method = Some(method.clone());
+                    details.http_uri = Some(uri.clone());
+                    details.http_host = message.header("host");
+                    details.http_cookie = message.header("cookie");
+                }
+                StartLine::Response { status } => details.http_status = Some(*status),
+            }
+        }
+
+        if flags & (TcpFlags::FIN | TcpFlags::RST) != 0 {
+            self.forget(flow);
+        }
+        details
+    }
+
+    fn remember(&mut self, flow: FlowKey, message: Message) {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.messages.insert(flow, (id, message));
+        self.order.push_back((id, flow));
+
+        while self.messages.len() > self.capacity {
+            let Some((id, oldest)) = self.order.pop_front() else {
+                break;
+            };
+            // Skip entries already forgotten (and possibly seen again since)
+            if self
+                .messages
+                .get(&oldest)
+                .is_some_and(|(seen, _)| *seen == id)
+            {
+                self.messages.remove(&oldest);
+            }
+        }
+        // Forgotten flows leave stale ids behind; don't let them pile up
+        if self.order.len() > self.capacity * 2 {
+            let messages = &self.messages;
+            self.order
+                .retain(|(id, flow)| messages.get(flow).is_some_and(|(seen, _)| seen == id));
+        }
+    }
+
+    /// Forget both directions of a flow
+    fn forget(&mut self, (source, destination): FlowKey) {
+        self.messages.remove(&(source, destination));
+        self.messages.remove(&(destination, source));
+    }
+
+    /// Number of flow directions remembered
+    pub fn len(&self) -> usize {
+        self.messages.len()
+    }
+
+    /// Whether no flow direction is remembered
+    pub fn is_empty(&self) -> bool {
+        self.messages.is_empty()
+    }
+}
+
+/// EC point formats of a ClientHello, when `payload` is a TLS record carrying one
+///
+/// Like huginn-net, only a ClientHello that fits in a single segment is read.
+fn client_hello_point_formats(payload: &[u8]) -> Option<Vec<u8>> {
+    // Handshake record header: content type 22, then the record version
+    if payload.len() < 5 || payload[0] != 0x16 {
+        return None;
+    }
+    let (_, record) = parse_tls_plaintext(payload).ok()?;
+    record.msg.iter().find_map(|message| match message {
+        TlsMessage::Handshake(TlsMessageHandshake::ClientHello(hello)) => {
+            let Some(extensions) = hello.ext else {
+                return Some(Vec::new());
+            };
+            let (_, extensions) = parse_tls_extensions(extensions).ok()?;
+            Some(
+                extensions
+                    .iter()
+                    .find_map(|extension| match extension {
+                        TlsExtension::EcPointFormats(formats) => Some(formats.to_vec()),
+                        _ => None,
+                    })
+                    .unwrap_or_default(),
+            )
+        }
+        _ => None,
+    })
+}
+
+/// Parse `METHOD target HTTP/x.y` or `HTTP/x.y code reason`
+fn parse_start_line(payload: &[u8]) -> Option<StartLine> {
+    let head = &payload[..payload.len().min(MAX_HEAD)];
+    let end = head.windows(2).position(|pair| pair == b"\r\n")?;
+    let line = std::str::from_utf8(&head[..end]).ok()?;
+    let mut parts = line.splitn(3, ' ');
+    let (first, second) = (parts.next()?, parts.next()?);
+    // The reason phrase of a status line may be missing altogether
+    let third = parts.next().unwrap_or_default();
+
+    if first.starts_with("HTTP/") {
+        let status = second
+            .parse()
+            .ok()
+            .filter(|_| second.len() == 3)
+            .filter(|status| (100..1000).contains(status))?;
+        return Some(StartLine::Response { status });
+    }
+
+    let is_token = !first.is_empty() && first.bytes().all(|b| b.is_ascii_uppercase());
+    if is_token && !second.is_empty() && third.starts_with("HTTP/") {
+        return Some(StartLine::Request {
+            method: first.to_string(),
+            uri: second.to_string(),
+        });
+    }
+    None
+}
+
+/// Ethernet/IPv4/TCP frame carrying `payload`
+#[cfg(test)]
+pub(crate) fn tcp_frame(
+    source: ([u8; 4], u16),
+    destination: ([u8; 4], u16),
+    flags: u8,
+    payload: &[u8],
+) -> Vec<u8> {
+    let mut frame = vec![0u8; 14];
+    frame[12..14].copy_from_slice(&0x0800u16.to_be_bytes());
+
+    let mut ip = [0u8; 20];
+    ip[0] = 0x45;
+    ip[2..4].copy_from_slice(&((40 + payload.len()) as u16).to_be_bytes());
+    ip[8] = 64;
+    ip[9] = 6;
+    ip[12..16].copy_from_slice(&source.0);
+    ip[16..20].copy_from_slice(&destination.0);
+    frame.extend_from_slice(&ip);
+
+    let mut tcp = [0u8; 20];
+    tcp[0..2].copy_from_slice(&source.1.to_be_bytes());
+    tcp[2..4].copy_from_slice(&destination.1.to_be_bytes());
+    tcp[12] = 5 << 4;
+    tcp[13] = flags;
+    frame.extend_from_slice(&tcp);
+    frame.extend_from_slice(payload);
+    frame
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CLIENT: [u8; 4] = [10, 0, 0, 5];
+    const SERVER: [u8; 4] = [93, 184, 216, 34];
+
+    fn from_client(flags: u8, payload: &[u8]) -> Vec<u8> {
+        tcp_frame((CLIENT, 40001), (SERVER, 80), flags, payload)
+    }
+
+    fn from_server(flags: u8, payload: &[u8]) -> Vec<u8> {
+        tcp_frame((SERVER, 80), (CLIENT, 40001), flags, payload)
+    }
+
+    #[test]
+    fn test_request_line_is_kept_for_later_segments() {
+        let mut tracker = WireTracker::default();
+        tracker.observe(&from_client(TcpFlags::SYN, b""));
+
+        let first = tracker.observe(&from_client(
+            TcpFlags::ACK,
+            b"POST /login?next=%2F HTTP/1.1\r\nHost: exa",
+        ));
+        assert_eq!(first.http_method.as_deref(), Some("POST"));
+        assert_eq!(first.http_uri.as_deref(), Some("/login?next=%2F"));
+        assert_eq!(first.http_host, None, "the Host line is not complete yet");
+
+        // The segment completing the headers is the one huginn-net reports on
+        let last = tracker.observe(&from_client(TcpFlags::ACK, b"mple.com\r\n\r\n"));
+        assert_eq!(last.http_method.as_deref(), Some("POST"));
+        assert_eq!(last.http_host.as_deref(), Some("example.com"));
+        assert_eq!(last.http_status, None);
+    }
+
+    #[test]
+    fn test_status_line_belongs_to_the_server_direction() {
+        let mut tracker = WireTracker::default();
+        tracker.observe(&from_client(TcpFlags::ACK, b"GET / HTTP/1.1\r\n\r\n"));
+
+        let response = tracker.observe(&from_server(
+            TcpFlags::ACK,
+            b"HTTP/1.1 404 Not Found\r\n\r\n",
+        ));
+        assert_eq!(response.http_status, Some(404));
+        assert_eq!(response.http_method, None);
+
+        // The connection closes; a new one on the same ports starts over
+        tracker.observe(&from_server(TcpFlags::FIN | TcpFlags::ACK, b""));
+        assert!(tracker.is_empty());
+
+        // Servers may leave out the reason phrase
+        tracker.observe(&from_client(TcpFlags::SYN, b""));
+        let response = tracker.observe(&from_server(TcpFlags::ACK, b"HTTP/1.1 204\r\n\r\n"));
+        assert_eq!(response.http_status, Some(204));
+    }
+
+    #[test]
+    fn test_keep_alive_reports_the_latest_message() {
+        let mut tracker = WireTracker::default();
+        tracker.observe(&from_client(TcpFlags::SYN, b""));
+        tracker.observe(&from_client(
+            TcpFlags::ACK,
+            b"GET / HTTP/1.1\r\nHost: example.com\r\n\r\n",
+        ));
+        tracker.observe(&from_server(TcpFlags::ACK, b"HTTP/1.1 200 OK\r\n\r\n"));
+
+        let request = tracker.observe(&from_client(
+            TcpFlags::ACK,
+            b"POST /api/items HTTP/1.1\r\nHost: api.example.com\r\n\r\n",
+        ));
+        assert_eq!(request.http_method.as_deref(), Some("POST"));
+        assert_eq!(request.http_uri.as_deref(), Some("/api/items"));
+        assert_eq!(request.http_host.as_deref(), Some("api.example.com"));
+
+        let response =
+            tracker.observe(&from_server(TcpFlags::ACK, b"HTTP/1.1 201 Created\r\n\r\n"));
+        assert_eq!(response.http_status, Some(201));
+        assert_eq!(tracker.len(), 2);
+    }
+
+    #[test]
+    fn test_payloads_that_are_not_http_are_ignored() {
+        let mut tracker = WireTracker::default();
+        for payload in [
+            &b"\x16\x03\x01\x00\xa5\x01\x00\x00\xa1"[..],
+            b"HTTP/1.1 abc OK\r\n",
+            b"get / HTTP/1.1\r\n",
+            b"GET / HTTP/1.1",
+        ] {
+            assert_eq!(
+                tracker.observe(&from_client(TcpFlags::ACK, payload)),
+                WireDetails::default()
+            );
+        }
+        assert!(tracker.is_empty());
+    }
+
+    /// TLS record holding a TLS 1.2 ClientHello with the given extensions
+    fn client_hello(extensions: &[u8]) -> Vec<u8> {
+        let mut hello = vec![0x03, 0x03];
+        hello.extend_from_slice(&[0u8; 32]); // random
+        hello.push(0); // no session id
+        hello.extend_from_slice(&[0x00, 0x02, 0xc0, 0x2f]); // one cipher suite
+        hello.extend_from_slice(&[0x01, 0x00]); // null compression
+        hello.extend_from_slice(&(extensions.len() as u16).to_be_bytes());
+        hello.extend_from_slice(extensions);
+
+        let mut handshake = vec![0x01, 0x00];
+        handshake.extend_from_slice(&(hello.len() as u16).to_be_bytes());
+        handshake.extend_from_slice(&hello);
+
+        let mut record = vec![0x16, 0x03, 0x01];
+        record.extend_from_slice(&(handshake.len() as u16).to_be_bytes());
+        record.extend_from_slice(&handshake);
+        record
+    }
+
+    #[test]
+    fn test_point_formats_come_from_the_client_hello() {
+        let mut tracker = WireTracker::default();
+        // supported_groups (x25519), then ec_point_formats (uncompressed)
+        let extensions = [
+            0x00, 0x0a, 0x00, 0x04, 0x00, 0x02, 0x00, 0x1d, 0x00, 0x0b, 0x00, 0x02, 0x01, 0x00,
+        ];
+        let details = tracker.observe(&tcp_frame(
+            (CLIENT, 40002),
+            (SERVER, 443),
+            TcpFlags::ACK,
+            &client_hello(&extensions),
+        ));
+        assert_eq!(details.tls_point_formats, Some(vec![0]));
+
+        // A ClientHello without the extension has no point formats, not unknown ones
+        let details = tracker.observe(&tcp_frame(
+            (CLIENT, 40003),
+            (SERVER, 443),
+            TcpFlags::ACK,
+            &client_hello(&extensions[..8]),
+        ));
+        assert_eq!(details.tls_point_formats, Some(Vec::new()));
+    }
+
+    #[test]
+    fn test_capacity_evicts_the_oldest_flows() {
+        let mut tracker = WireTracker::with_capacity(2);
+        for port in 1..=3 {
+            tracker.observe(&tcp_frame(
+                (CLIENT, port),
+                (SERVER, 80),
+                TcpFlags::ACK,
+                b"GET / HTTP/1.1\r\n\r\n",
+            ));
+        }
+        assert_eq!(tracker.len(), 2);
+
+        let oldest = tracker.observe(&tcp_frame(
+            (CLIENT, 1),
+            (SERVER, 80),
+            TcpFlags::ACK,
+            b"body",
+        ));
+        assert_eq!(oldest.http_method, None);
+    }
+}